@@ -7,7 +7,7 @@ use std::{
 
 use ndarray::ArrayView1;
 use speciesnet_core::{
-    classifier::{Classification, ClassificationBundle},
+    classifier::{Classification, ClassificationBundle, ClassifierOutputOptions, ScoreRetention},
     io::Prediction,
 };
 
@@ -54,17 +54,72 @@ pub fn transform<P: AsRef<Path>>(
     outputs: ArrayView1<f32>,
     labels: &[String],
 ) -> Prediction {
-    let softmax_result = softmax(outputs);
-    let mapped_result = map_labels_to_classifications(labels, &softmax_result);
-    let top5_result = pick_top_n_from(mapped_result, 5);
-    let labels = top5_result.iter().map(|c| c.label().clone()).collect();
-    let scores = top5_result.iter().map(|c| *c.score()).collect();
+    transform_with(
+        file_path,
+        outputs,
+        labels,
+        &ClassifierOutputOptions::default(),
+    )
+}
+
+/// Same as [`transform`], keeping the top-k classifications and retaining the scores for the
+/// rollups following the given options.
+pub fn transform_with<P: AsRef<Path>>(
+    file_path: P,
+    outputs: ArrayView1<f32>,
+    labels: &[String],
+    options: &ClassifierOutputOptions,
+) -> Prediction {
     Prediction::from_classifications(
         file_path.as_ref().to_path_buf(),
-        ClassificationBundle::new(labels, scores),
+        classifications_with(outputs, labels, options),
     )
 }
 
+/// Applies softmax on the raw outputs of the classifier, keeping the top-k classifications and
+/// retaining the scores for the rollups following the given options.
+pub fn classifications_with(
+    outputs: ArrayView1<f32>,
+    labels: &[String],
+    options: &ClassifierOutputOptions,
+) -> ClassificationBundle {
+    let softmax_result = softmax(outputs);
+    let mapped_result = map_labels_to_classifications(labels, &softmax_result);
+    let retained_count = match options.retention() {
+        ScoreRetention::TopK => options.top_k(),
+        ScoreRetention::Full | ScoreRetention::Sparse { .. } => mapped_result.len(),
+    };
+    let sorted_result = pick_top_n_from(mapped_result, retained_count);
+
+    let to_bundle = |classifications: &[Classification]| {
+        ClassificationBundle::new(
+            classifications.iter().map(|c| c.label().clone()).collect(),
+            classifications.iter().map(|c| *c.score()).collect(),
+        )
+    };
+
+    let top_k = sorted_result.len().min(options.top_k());
+    let mut bundle = to_bundle(&sorted_result[..top_k]);
+
+    match options.retention() {
+        ScoreRetention::TopK => {}
+        ScoreRetention::Full => {
+            bundle.set_retained(Some(to_bundle(&sorted_result)));
+        }
+        ScoreRetention::Sparse { min_score } => {
+            // Sorted from high to low, so the kept classifications are the leading ones.
+            let kept = sorted_result
+                .iter()
+                .take_while(|c| *c.score() >= min_score)
+                .count()
+                .max(top_k);
+            bundle.set_retained(Some(to_bundle(&sorted_result[..kept])));
+        }
+    }
+
+    bundle
+}
+
 pub fn read_labels_from_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<String>, Error> {
     let label_file = File::open(file_path)?;
     let label_reader = BufReader::new(label_file);
@@ -1,10 +1,14 @@
 use std::{path::PathBuf, sync::LazyLock};
 
 use crate::classifier::{
-    Classification, map_labels_to_classifications, pick_top_n_from, softmax, to_chunks, transform,
+    Classification, classifications_with, map_labels_to_classifications, pick_top_n_from, softmax,
+    to_chunks, transform,
 };
 use ndarray::Array1;
-use speciesnet_core::io::Prediction;
+use speciesnet_core::{
+    classifier::{ClassifierOutputOptions, ScoreRetention},
+    io::Prediction,
+};
 
 static LABELS: LazyLock<Vec<String>> = LazyLock::new(|| {
     vec![
@@ -184,3 +188,60 @@ fn test_transform_fn() {
         ]
     );
 }
+
+#[test]
+fn test_classifications_with_top_k_fn() {
+    let scores = Array1::from_vec(vec![0.5, 3.0, -1.0, 2.0, 0.1, 1.0]);
+
+    let mut options = ClassifierOutputOptions::default();
+    options.set_top_k(3);
+
+    let top3 = classifications_with(scores.view(), &LABELS, &options);
+
+    assert_eq!(
+        *top3.labels(),
+        vec!["elephant".to_string(), "dog".to_string(), "bat".to_string()]
+    );
+    assert_eq!(top3.scores().len(), 3);
+    assert!(top3.scores().windows(2).all(|w| w[0] >= w[1]));
+}
+
+#[test]
+fn test_classifications_with_retention_fn() {
+    let scores = Array1::from_vec(vec![0.5, 3.0, -1.0, 2.0, 0.1, 1.0]);
+
+    let mut options = ClassifierOutputOptions::default();
+    options.set_top_k(2);
+
+    let top2 = classifications_with(scores.view(), &LABELS, &options);
+    assert_eq!(
+        *top2.labels(),
+        vec!["elephant".to_string(), "dog".to_string()]
+    );
+    assert!(top2.retained().is_none());
+    assert_eq!(top2.rollup_classifications(), &top2);
+
+    options.set_retention(ScoreRetention::Full);
+    let full = classifications_with(scores.view(), &LABELS, &options);
+    assert_eq!(full.labels(), top2.labels());
+    assert_eq!(full.retained().unwrap().labels().len(), LABELS.len());
+    assert_eq!(full.retained().unwrap().labels()[..2], top2.labels()[..]);
+
+    options.set_retention(ScoreRetention::Sparse { min_score: 0.04 });
+    let sparse = classifications_with(scores.view(), &LABELS, &options);
+    let retained = sparse.retained().unwrap();
+    assert_eq!(
+        *retained.labels(),
+        vec![
+            "elephant".to_string(),
+            "dog".to_string(),
+            "bat".to_string(),
+            "lion".to_string(),
+        ]
+    );
+    assert!(retained.scores().iter().all(|&score| score >= 0.04));
+
+    // Only the top-k classifications are written out.
+    let json = serde_json::to_value(&sparse).unwrap();
+    assert_eq!(json["classes"].as_array().unwrap().len(), 2);
+}
@@ -1,4 +1,4 @@
-use speciesnet_core::error::Error as SpeciesNetCoreError;
+use speciesnet_core::{error::Error as SpeciesNetCoreError, session::TensorMismatch};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -6,6 +6,32 @@ pub enum Error {
     #[error("ORT error: {0}")]
     ORTError(#[from] ort::error::Error),
 
+    // Model.
+    #[error("expected {kind} `{name}` in the classifier model, model has {found:?}")]
+    MissingTensor {
+        kind: &'static str,
+        name: String,
+        found: Vec<String>,
+    },
+    #[error("expected {kind} `{name}` of the classifier model to be {expected}, model has {found}")]
+    UnexpectedTensor {
+        kind: &'static str,
+        name: String,
+        expected: String,
+        found: String,
+    },
+    #[error(
+        "cannot tell which output of the classifier model has the scores among {found:?}, \
+         set the `output_name` in the `classifier_input` of `info.json`"
+    )]
+    AmbiguousOutput { found: Vec<String> },
+    #[error("expected output `{name}` with {labels} labels, model has {found} scores")]
+    LabelCountMismatch {
+        name: String,
+        labels: usize,
+        found: usize,
+    },
+
     // Miscellaneous
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -24,3 +50,24 @@ pub enum Error {
     #[error("SpeciesNet core error: {0}")]
     SpeciesNetCoreError(#[from] SpeciesNetCoreError),
 }
+
+impl From<TensorMismatch> for Error {
+    fn from(mismatch: TensorMismatch) -> Self {
+        match mismatch {
+            TensorMismatch::Missing { kind, name, found } => {
+                Self::MissingTensor { kind, name, found }
+            }
+            TensorMismatch::Unexpected {
+                kind,
+                name,
+                expected,
+                found,
+            } => Self::UnexpectedTensor {
+                kind,
+                name,
+                expected,
+                found,
+            },
+        }
+    }
+}
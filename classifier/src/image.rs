@@ -2,11 +2,18 @@ use std::path::PathBuf;
 
 use fast_image_resize::images::Image;
 use fast_image_resize::{PixelType, Resizer};
-use image::{DynamicImage, RgbImage};
+use image::{DynamicImage, RgbImage, imageops};
 use ndarray::Array4;
 use speciesnet_core::{detector::BoundingBox, load_image};
 
-use crate::{error::Error, input::ClassifierInput};
+use crate::{
+    error::Error,
+    input::ClassifierInput,
+    model::{ClassifierInputSpec, TensorLayout},
+};
+
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug)]
 pub struct ProceededImage {
@@ -14,47 +21,88 @@ pub struct ProceededImage {
     pub image_tensor: Array4<f32>,
 }
 
+/// Preprocesses the image of the input for the SpeciesNet classifier, see [`preprocess_with`].
 pub fn preprocess(classifier_input: &ClassifierInput) -> Result<ProceededImage, Error> {
-    let decoded_img = load_image(&classifier_input.file_path)?;
-
-    let proceeded_image = preprocess_impl(decoded_img.into(), classifier_input.bbox)?;
+    preprocess_with(classifier_input, &ClassifierInputSpec::default())
+}
 
-    let mut tensor = Array4::zeros([1usize, 480usize, 480usize, 3usize]);
+/// Loads the image of the input, crops it to the bounding box of the input and turns it into the
+/// tensor of a classifier with the given input spec.
+pub fn preprocess_with(
+    classifier_input: &ClassifierInput,
+    spec: &ClassifierInputSpec,
+) -> Result<ProceededImage, Error> {
+    let decoded_img = load_image(&classifier_input.file_path)?;
 
-    for pixel in proceeded_image.enumerate_pixels() {
-        let x = pixel.0 as _;
-        let y = pixel.1 as _;
-        let [r, g, b] = pixel.2.0;
-        tensor[[0, x, y, 0]] = (r as f32) / 255.;
-        tensor[[0, x, y, 1]] = (g as f32) / 255.;
-        tensor[[0, x, y, 2]] = (b as f32) / 255.;
-    }
+    let proceeded_image = preprocess_impl(decoded_img.into(), classifier_input.bbox, spec)?;
 
     Ok(ProceededImage {
         path: classifier_input.file_path.clone(),
-        image_tensor: tensor,
+        image_tensor: to_tensor(&proceeded_image, spec),
     })
 }
 
+/// Converts the preprocessed image, which is already resized to the size of the spec, into the
+/// tensor of the classifier. The pixels are scaled to `0..1` then normalized by the mean and std
+/// of the spec.
+pub fn to_tensor(image: &RgbImage, spec: &ClassifierInputSpec) -> Array4<f32> {
+    let mut tensor = Array4::zeros(spec.shape());
+
+    for pixel in image.enumerate_pixels() {
+        let x = pixel.0 as usize;
+        let y = pixel.1 as usize;
+
+        for (channel, value) in pixel.2.0.into_iter().enumerate() {
+            let value = (value as f32 / 255. - spec.mean[channel]) / spec.std[channel];
+
+            match spec.layout {
+                TensorLayout::Nhwc => tensor[[0, y, x, channel]] = value,
+                TensorLayout::Nchw => tensor[[0, channel, y, x]] = value,
+            }
+        }
+    }
+
+    tensor
+}
+
+/// Crops the given bounding box out of the image and resizes it to the classifier's input size,
+/// without taking the ownership of the image.
+pub fn preprocess_crop(
+    image: &RgbImage,
+    bbox: BoundingBox,
+    spec: &ClassifierInputSpec,
+) -> Result<RgbImage, Error> {
+    resize(crop(image, bbox), spec)
+}
+
 pub fn preprocess_impl(
     decoded_image: DynamicImage, // TODO: Change to RgbImage
     bbox: Option<BoundingBox>,
+    spec: &ClassifierInputSpec,
 ) -> Result<RgbImage, Error> {
+    let image = decoded_image.into_rgb8();
+
     // Performs cropping with given bounding box if there is a bounding box, otherwise just return.
     let cropped_image = match bbox {
-        Some(bbox) => {
-            let min_x = (bbox.x1() * decoded_image.width() as f64) as u32;
-            let min_y = (bbox.y1() * decoded_image.height() as f64) as u32;
-            let max_x = (bbox.x2() * decoded_image.width() as f64) as u32;
-            let max_y = (bbox.y2() * decoded_image.height() as f64) as u32;
-            let cropped_img = decoded_image.crop_imm(min_x, min_y, max_x - min_x, max_y - min_y);
-
-            cropped_img.to_rgb8()
-        }
-        None => decoded_image.to_rgb8(),
+        Some(bbox) => crop(&image, bbox),
+        None => image,
     };
 
-    // Resize the image to 480 by 480 (classifier's accept input size).
+    resize(cropped_image, spec)
+}
+
+/// Crops the bounding box, in coordinates relative to the size of the image, out of the image.
+fn crop(image: &RgbImage, bbox: BoundingBox) -> RgbImage {
+    let min_x = (bbox.x1() * image.width() as f64) as u32;
+    let min_y = (bbox.y1() * image.height() as f64) as u32;
+    let max_x = (bbox.x2() * image.width() as f64) as u32;
+    let max_y = (bbox.y2() * image.height() as f64) as u32;
+
+    imageops::crop_imm(image, min_x, min_y, max_x - min_x, max_y - min_y).to_image()
+}
+
+/// Resizes the image to the classifier's input size.
+fn resize(cropped_image: RgbImage, spec: &ClassifierInputSpec) -> Result<RgbImage, Error> {
     let mut resizer = Resizer::new();
 
     let src_image = Image::from_vec_u8(
@@ -63,12 +111,12 @@ pub fn preprocess_impl(
         cropped_image.into_raw(),
         PixelType::U8x3,
     )?;
-    let mut dest_image = Image::new(480, 480, PixelType::U8x3);
+    let mut dest_image = Image::new(spec.width, spec.height, PixelType::U8x3);
 
     resizer.resize(&src_image, &mut dest_image, None)?;
 
     // Creates the image back.
-    let image = RgbImage::from_raw(480, 480, dest_image.into_vec()).unwrap();
+    let image = RgbImage::from_raw(spec.width, spec.height, dest_image.into_vec()).unwrap();
 
     // Returns the image back.
     Ok(image)
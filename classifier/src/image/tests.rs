@@ -0,0 +1,77 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use speciesnet_core::detector::BoundingBox;
+
+use crate::{
+    error::Error,
+    image::{preprocess_crop, preprocess_impl, to_tensor},
+    model::{ClassifierInputSpec, TensorLayout},
+};
+
+/// A 3 by 2 image whose only non black pixel is at `(2, 0)`.
+fn image() -> RgbImage {
+    let mut image = RgbImage::new(3, 2);
+    image.put_pixel(2, 0, Rgb([255, 51, 0]));
+    image
+}
+
+fn spec(layout: TensorLayout) -> ClassifierInputSpec {
+    ClassifierInputSpec {
+        width: 3,
+        height: 2,
+        layout,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_to_tensor_nhwc() {
+    let tensor = to_tensor(&image(), &spec(TensorLayout::Nhwc));
+
+    assert_eq!(tensor.shape(), &[1, 2, 3, 3]);
+    assert_eq!(tensor[[0, 0, 2, 0]], 1.0);
+    assert_eq!(tensor[[0, 0, 2, 1]], 0.2);
+    assert_eq!(tensor.sum(), 1.2);
+}
+
+#[test]
+fn test_to_tensor_nchw_normalized() {
+    let spec = ClassifierInputSpec {
+        mean: [0.5; 3],
+        std: [0.5; 3],
+        ..spec(TensorLayout::Nchw)
+    };
+    let tensor = to_tensor(&image(), &spec);
+
+    assert_eq!(tensor.shape(), &[1, 3, 2, 3]);
+    assert_eq!(tensor[[0, 0, 0, 2]], 1.0);
+    assert_eq!(tensor[[0, 2, 0, 2]], -1.0);
+    // The black pixels go to -1 once normalized.
+    assert_eq!(tensor[[0, 1, 1, 0]], -1.0);
+}
+
+#[test]
+fn test_crops_of_both_paths_match() -> Result<(), Error> {
+    // A 4 by 4 image with a white 2 by 2 square in its bottom right corner.
+    let image = RgbImage::from_fn(4, 4, |x, y| {
+        if x >= 2 && y >= 2 {
+            Rgb([255, 255, 255])
+        } else {
+            Rgb([0, 0, 0])
+        }
+    });
+    let spec = ClassifierInputSpec {
+        width: 2,
+        height: 2,
+        ..Default::default()
+    };
+    let bbox = BoundingBox::new(0.5, 0.5, 1.0, 1.0);
+
+    let crop = preprocess_crop(&image, bbox, &spec)?;
+    assert!(crop.pixels().all(|pixel| *pixel == Rgb([255, 255, 255])));
+    assert_eq!(
+        preprocess_impl(DynamicImage::ImageRgb8(image), Some(bbox), &spec)?,
+        crop
+    );
+
+    Ok(())
+}
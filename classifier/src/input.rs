@@ -12,6 +12,8 @@ use crate::error::Error;
 pub struct ClassifierInput {
     pub file_path: PathBuf,
     pub bbox: Option<BoundingBox>,
+    /// Every detection of the image, used when the detections are classified on their own.
+    pub detections: Vec<Detection>,
 }
 
 impl ClassifierInput {
@@ -19,6 +21,7 @@ impl ClassifierInput {
         Self {
             file_path,
             bbox: bounding_box,
+            detections: Vec::new(),
         }
     }
 }
@@ -44,18 +47,13 @@ impl ClassifierInput {
         let classifier_inputs = detector_outputs
             .predictions
             .iter()
-            .map(|prediction| {
-                if let Some(detection) = prediction.detections.first() {
-                    ClassifierInput {
-                        file_path: PathBuf::from(&prediction.file_path),
-                        bbox: Some(*detection.bounding_box()),
-                    }
-                } else {
-                    ClassifierInput {
-                        file_path: PathBuf::from(&prediction.file_path),
-                        bbox: None,
-                    }
-                }
+            .map(|prediction| ClassifierInput {
+                file_path: PathBuf::from(&prediction.file_path),
+                bbox: prediction
+                    .detections
+                    .first()
+                    .map(|detection| *detection.bounding_box()),
+                detections: prediction.detections.clone(),
             })
             .collect();
 
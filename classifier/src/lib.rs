@@ -1,24 +1,29 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use ::image::DynamicImage;
-use ndarray::{Array1, Array4, Ix2};
-use ort::session::Session;
-use ort::session::builder::GraphOptimizationLevel;
-use ort::value::Tensor;
+use ::image::{DynamicImage, RgbImage};
+use ndarray::{Array1, Array4, ArrayView4, Axis, concatenate};
 
+use speciesnet_core::classifier::ModelType;
 use speciesnet_core::detector::BoundingBox;
+use speciesnet_core::session::SessionOptions;
 
 pub mod classifier;
 pub mod error;
 pub mod image;
 pub mod input;
+pub mod model;
 
-use crate::{error::Error, image::preprocess_impl};
+use crate::{
+    error::Error,
+    image::{preprocess_crop, preprocess_impl, to_tensor},
+    model::{Classifier, ClassifierInputSpec, OnnxClassifier},
+};
 
 #[derive(Debug, Clone)]
 pub struct SpeciesNetClassifier {
-    model: Arc<Session>,
+    model: Arc<dyn Classifier>,
+    model_type: ModelType,
 }
 
 impl SpeciesNetClassifier {
@@ -27,51 +32,112 @@ impl SpeciesNetClassifier {
     where
         P: AsRef<Path>,
     {
-        let session = Session::builder()?
-            .with_optimization_level(GraphOptimizationLevel::Level3)?
-            .with_intra_threads(2)?
-            .commit_from_file(model_path)?;
-
-        Ok(Self {
-            model: Arc::new(session),
-        })
+        Self::with_options(model_path, &SessionOptions::default())
+    }
+
+    /// Create classifier with the given session options, the input spec of the classifier is read
+    /// from the metadata of the model.
+    pub fn with_options<P>(model_path: P, options: &SessionOptions) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_spec(model_path, options, None)
+    }
+
+    /// Create classifier with the given session options and input spec, see
+    /// [`OnnxClassifier::with_options`].
+    pub fn with_spec<P>(
+        model_path: P,
+        options: &SessionOptions,
+        spec: Option<ClassifierInputSpec>,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::from_classifier(Arc::new(
+            OnnxClassifier::with_options(model_path, options, spec)?,
+        )))
+    }
+
+    /// Runs the images through the given classifier instead of an ONNX model.
+    pub fn from_classifier(model: Arc<dyn Classifier>) -> Self {
+        Self {
+            model,
+            model_type: ModelType::default(),
+        }
+    }
+
+    /// Returns what the classifier expects its images in.
+    pub fn input_spec(&self) -> &ClassifierInputSpec {
+        self.model.input_spec()
+    }
+
+    /// Checks that the model gives one score for each of the given number of labels, when the
+    /// number of scores of the model is known before running it.
+    pub fn check_label_count(&self, labels: usize) -> Result<(), Error> {
+        match self.model.label_count() {
+            Some(found) if found != labels => Err(Error::LabelCountMismatch {
+                name: self.input_spec().output_name.clone(),
+                labels,
+                found,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the type of the model, which decides whether the images are cropped to their top
+    /// detection before being classified.
+    pub fn set_model_type(&mut self, model_type: ModelType) -> &mut Self {
+        self.model_type = model_type;
+        self
+    }
+
+    pub fn model_type(&self) -> ModelType {
+        self.model_type
     }
 
     /// run a classification from given input
     pub fn classify(&self, input_tensor: Array4<f32>) -> Result<Array1<f32>, Error> {
-        let outputs = self
-            .model
-            .run(ort::inputs!["input" => Tensor::from_array(input_tensor)?]?)?;
-        let output: Array1<f32> = outputs
-            .get("dense")
-            .unwrap()
-            .try_extract_tensor::<f32>()?
-            .into_dimensionality::<Ix2>()?
-            .row(0) // to get the scores
-            .into_owned();
-        Ok(output)
+        let mut outputs = self.classify_batch(&[input_tensor])?;
+        Ok(outputs.swap_remove(0))
     }
 
-    /// Preprocess a given image to be classifier compatible format.
+    /// Runs the classification on a batch of preprocessed images, the tensors of the images are
+    /// stacked into one tensor and the scores of each image are returned in the same order as the
+    /// given tensors.
+    pub fn classify_batch(&self, input_tensors: &[Array4<f32>]) -> Result<Vec<Array1<f32>>, Error> {
+        let views: Vec<ArrayView4<f32>> = input_tensors.iter().map(|t| t.view()).collect();
+        let input_tensor = concatenate(Axis(0), &views)?;
+
+        self.model.classify_batch(input_tensor)
+    }
+
+    /// Preprocess a given image to be classifier compatible format, the image is cropped to the
+    /// first bounding box unless the model is a [`ModelType::FullImage`] model.
     pub fn preprocess(
         &self,
         image: DynamicImage,
         bboxes: &[BoundingBox],
     ) -> Result<Array4<f32>, Error> {
-        let processed_image = preprocess_impl(image, bboxes.first().cloned())?;
-
-        // The tensor's structure for classifier is batch, width, height, and channel.
-        let mut tensor = Array4::zeros([1usize, 480usize, 480usize, 3usize]);
-
-        for pixel in processed_image.enumerate_pixels() {
-            let x = pixel.0 as _;
-            let y = pixel.1 as _;
-            let [r, g, b] = pixel.2.0;
-            tensor[[0, x, y, 0]] = (r as f32) / 255.;
-            tensor[[0, x, y, 1]] = (g as f32) / 255.;
-            tensor[[0, x, y, 2]] = (b as f32) / 255.;
-        }
+        let spec = self.input_spec();
+        let processed_image = preprocess_impl(image, self.model_type.crop_box(bboxes), spec)?;
 
-        Ok(tensor)
+        Ok(to_tensor(&processed_image, spec))
+    }
+
+    /// Preprocesses the crop of each given bounding box of the image on its own, returning one
+    /// tensor per bounding box in the same order.
+    pub fn preprocess_crops(
+        &self,
+        image: &RgbImage,
+        bboxes: &[BoundingBox],
+    ) -> Result<Vec<Array4<f32>>, Error> {
+        bboxes
+            .iter()
+            .map(|bbox| {
+                let spec = self.input_spec();
+                Ok(to_tensor(&preprocess_crop(image, *bbox, spec)?, spec))
+            })
+            .collect()
     }
 }
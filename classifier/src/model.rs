@@ -0,0 +1,288 @@
+use std::{fmt::Debug, path::Path};
+
+use ndarray::{Array1, Array4, Ix2};
+use ort::{
+    session::Session,
+    tensor::TensorElementType,
+    value::{Tensor, ValueType},
+};
+use serde::{Deserialize, Serialize};
+use speciesnet_core::{
+    constants::classification::{IMAGE_HEIGHT, IMAGE_WIDTH},
+    session::{SessionOptions, expect_tensor, graph_optimization_level},
+};
+
+use crate::error::Error;
+
+#[cfg(test)]
+mod tests;
+
+/// Order of the dimensions of the image tensor going into the classifier.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TensorLayout {
+    /// `[batch, height, width, channel]`, the layout of the TensorFlow models like SpeciesNet.
+    #[default]
+    Nhwc,
+    /// `[batch, channel, height, width]`, the layout of the PyTorch models.
+    Nchw,
+}
+
+/// What the classifier expects its images in, and where it takes them from and gives its scores
+/// on.
+///
+/// The pixels are scaled to `0..1` before being normalized by `(pixel - mean) / std` for each of
+/// the red, green and blue channels. The defaults are the ones of SpeciesNet, any field left out of
+/// the `classifier_input` of `info.json` keeps its default.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct ClassifierInputSpec {
+    /// Name of the image input of the model.
+    pub input_name: String,
+    /// Name of the scores output of the model.
+    pub output_name: String,
+    /// Width of the image going into the model.
+    pub width: u32,
+    /// Height of the image going into the model.
+    pub height: u32,
+    pub layout: TensorLayout,
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+}
+
+impl Default for ClassifierInputSpec {
+    fn default() -> Self {
+        Self {
+            input_name: "input".to_string(),
+            output_name: "dense".to_string(),
+            width: IMAGE_WIDTH,
+            height: IMAGE_HEIGHT,
+            layout: TensorLayout::default(),
+            mean: [0.0; 3],
+            std: [1.0; 3],
+        }
+    }
+}
+
+impl ClassifierInputSpec {
+    /// Reads the spec from the metadata of the model, the name of its first input and of its
+    /// scores output, the layout from where the 3 channels are, and the size when the model has a
+    /// fixed size. The normalization cannot be read from the model, so it is left to the defaults.
+    pub fn from_session(session: &Session) -> Result<Self, Error> {
+        let inputs = session
+            .inputs
+            .iter()
+            .map(|input| (input.name.as_str(), &input.input_type));
+        let outputs = session
+            .outputs
+            .iter()
+            .map(|output| (output.name.as_str(), &output.output_type));
+
+        Self::from_tensors(inputs, outputs)
+    }
+
+    /// Reads the spec from the inputs and outputs of a model. The scores output is the only
+    /// `[N, labels]` `f32` tensor among the outputs, or the only output of the model. A model with
+    /// several outputs which could be the scores is an error, its `output_name` has to be given.
+    pub(crate) fn from_tensors<'a>(
+        mut inputs: impl Iterator<Item = (&'a str, &'a ValueType)>,
+        outputs: impl Iterator<Item = (&'a str, &'a ValueType)>,
+    ) -> Result<Self, Error> {
+        let mut spec = Self::default();
+
+        let outputs: Vec<_> = outputs.collect();
+        let scores: Vec<_> = outputs
+            .iter()
+            .filter(|(_, value_type)| {
+                matches!(
+                    value_type,
+                    ValueType::Tensor { ty: TensorElementType::Float32, dimensions, .. }
+                        if dimensions.len() == 2
+                )
+            })
+            .collect();
+
+        match (scores.as_slice(), outputs.as_slice()) {
+            ([(name, _)], _) | ([], [(name, _)]) => spec.output_name = name.to_string(),
+            // Without outputs the default name is kept, checking the outputs reports it missing.
+            ([], []) => {}
+            _ => {
+                return Err(Error::AmbiguousOutput {
+                    found: outputs.iter().map(|(name, _)| name.to_string()).collect(),
+                });
+            }
+        }
+
+        let Some((name, input_type)) = inputs.next() else {
+            return Ok(spec);
+        };
+
+        spec.input_name = name.to_string();
+
+        if let ValueType::Tensor { dimensions, .. } = input_type {
+            let (layout, height, width) = match dimensions.as_slice() {
+                [_, 3, height, width] => (TensorLayout::Nchw, *height, *width),
+                [_, height, width, _] => (TensorLayout::Nhwc, *height, *width),
+                _ => return Ok(spec),
+            };
+
+            spec.layout = layout;
+
+            // Dynamic dimensions are -1, the default size is kept for them.
+            if height > 0 && width > 0 {
+                spec.height = height as u32;
+                spec.width = width as u32;
+            }
+        }
+
+        Ok(spec)
+    }
+
+    /// Checks that the input of the spec is among the inputs of the model, as an `f32` tensor of
+    /// the layout and size of the spec.
+    pub(crate) fn expect_input<'a>(
+        &self,
+        inputs: impl Iterator<Item = (&'a str, &'a ValueType)>,
+    ) -> Result<(), Error> {
+        let shape = self.shape();
+        let expected = match self.layout {
+            TensorLayout::Nhwc => format!("a `[N, {}, {}, 3]` f32 tensor", shape[1], shape[2]),
+            TensorLayout::Nchw => format!("a `[N, 3, {}, {}]` f32 tensor", shape[2], shape[3]),
+        };
+
+        expect_tensor("input", &self.input_name, inputs, &expected, |dimensions| {
+            dimensions.len() == 4
+                && dimensions[1..]
+                    .iter()
+                    .zip(&shape[1..])
+                    .all(|(&dimension, &size)| dimension < 0 || dimension as usize == size)
+        })?;
+
+        Ok(())
+    }
+
+    /// Checks that the output of the spec is among the outputs of the model, as a
+    /// `[N, labels]` `f32` tensor. Returns the number of labels when the model has a fixed one.
+    pub(crate) fn expect_output<'a>(
+        &self,
+        outputs: impl Iterator<Item = (&'a str, &'a ValueType)>,
+    ) -> Result<Option<usize>, Error> {
+        let dimensions = expect_tensor(
+            "output",
+            &self.output_name,
+            outputs,
+            "a `[N, labels]` f32 tensor",
+            |dimensions| dimensions.len() == 2,
+        )?;
+
+        Ok((dimensions[1] > 0).then_some(dimensions[1] as usize))
+    }
+
+    /// Returns the shape of the tensor of one image.
+    pub fn shape(&self) -> [usize; 4] {
+        let (width, height) = (self.width as usize, self.height as usize);
+
+        match self.layout {
+            TensorLayout::Nhwc => [1, height, width, 3],
+            TensorLayout::Nchw => [1, 3, height, width],
+        }
+    }
+}
+
+/// A classification model which gives a score for each of its labels.
+///
+/// The cropping and resizing of the images are done by
+/// [`SpeciesNetClassifier`](crate::SpeciesNetClassifier) following the
+/// [`Classifier::input_spec`], an implementation only runs the model. Regional or custom
+/// classifiers can be plugged in this way and run through the same pipeline.
+pub trait Classifier: Debug + Send + Sync {
+    /// Returns what the classifier expects its images in.
+    fn input_spec(&self) -> &ClassifierInputSpec;
+
+    /// Runs the model on a batch of images stacked into one tensor following the input spec,
+    /// returns the scores of each image in the same order.
+    fn classify_batch(&self, images: Array4<f32>) -> Result<Vec<Array1<f32>>, Error>;
+
+    /// Returns the number of scores the model gives for each image, when it is known before
+    /// running the model. This is checked against the number of labels of the classifier.
+    fn label_count(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Classifier running an ONNX model.
+#[derive(Debug)]
+pub struct OnnxClassifier {
+    session: Session,
+    spec: ClassifierInputSpec,
+    label_count: Option<usize>,
+}
+
+impl OnnxClassifier {
+    /// Loads the model with the given session options. When no spec is given, it is read from the
+    /// metadata of the model with [`ClassifierInputSpec::from_session`]. The inputs and outputs of
+    /// the model are checked against the spec, so a wrong model fails here instead of on its first
+    /// run.
+    pub fn with_options<P>(
+        model_path: P,
+        options: &SessionOptions,
+        spec: Option<ClassifierInputSpec>,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let session = Session::builder()?
+            .with_optimization_level(graph_optimization_level(options.optimization_level()))?
+            .with_intra_threads(options.intra_threads())?
+            .commit_from_file(model_path)?;
+
+        let spec = match spec {
+            Some(spec) => spec,
+            None => ClassifierInputSpec::from_session(&session)?,
+        };
+
+        let inputs = session
+            .inputs
+            .iter()
+            .map(|input| (input.name.as_str(), &input.input_type));
+        let outputs = session
+            .outputs
+            .iter()
+            .map(|output| (output.name.as_str(), &output.output_type));
+
+        spec.expect_input(inputs)?;
+        let label_count = spec.expect_output(outputs)?;
+
+        Ok(Self {
+            session,
+            spec,
+            label_count,
+        })
+    }
+}
+
+impl Classifier for OnnxClassifier {
+    fn input_spec(&self) -> &ClassifierInputSpec {
+        &self.spec
+    }
+
+    fn label_count(&self) -> Option<usize> {
+        self.label_count
+    }
+
+    fn classify_batch(&self, images: Array4<f32>) -> Result<Vec<Array1<f32>>, Error> {
+        let outputs = self.session.run(ort::inputs![
+            self.spec.input_name.as_str() => Tensor::from_array(images)?
+        ]?)?;
+        let output = outputs[self.spec.output_name.as_str()]
+            .try_extract_tensor::<f32>()?
+            .into_dimensionality::<Ix2>()?;
+
+        // Each row is the scores of an image.
+        Ok(output
+            .rows()
+            .into_iter()
+            .map(|row| row.to_owned())
+            .collect())
+    }
+}
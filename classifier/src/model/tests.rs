@@ -0,0 +1,119 @@
+use ort::{tensor::TensorElementType, value::ValueType};
+
+use crate::{
+    error::Error,
+    model::{ClassifierInputSpec, TensorLayout},
+};
+
+fn tensor(ty: TensorElementType, dimensions: &[i64]) -> ValueType {
+    ValueType::Tensor {
+        ty,
+        dimensions: dimensions.to_vec(),
+        dimension_symbols: vec![None; dimensions.len()],
+    }
+}
+
+#[test]
+fn test_expect_input_checks_the_layout_and_size() {
+    let spec = ClassifierInputSpec::default();
+    let nhwc = tensor(TensorElementType::Float32, &[-1, 480, 480, 3]);
+    let nchw = tensor(TensorElementType::Float32, &[-1, 3, 480, 480]);
+    let smaller = tensor(TensorElementType::Float32, &[-1, 224, 224, 3]);
+
+    assert!(spec.expect_input([("input", &nhwc)].into_iter()).is_ok());
+    assert!(spec.expect_input([("input", &nchw)].into_iter()).is_err());
+
+    let error = spec
+        .expect_input([("input", &smaller)].into_iter())
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "expected input `input` of the classifier model to be a `[N, 480, 480, 3]` f32 tensor, \
+         model has Tensor<f32>(dyn, 224, 224, 3)"
+    );
+
+    let spec = ClassifierInputSpec {
+        layout: TensorLayout::Nchw,
+        ..Default::default()
+    };
+    assert!(spec.expect_input([("input", &nchw)].into_iter()).is_ok());
+}
+
+#[test]
+fn test_expect_output_returns_the_label_count() -> Result<(), Error> {
+    let spec = ClassifierInputSpec::default();
+    let dense = tensor(TensorElementType::Float32, &[-1, 2498]);
+    let dynamic = tensor(TensorElementType::Float32, &[-1, -1]);
+    let integers = tensor(TensorElementType::Int64, &[-1, 2498]);
+
+    assert_eq!(
+        spec.expect_output([("dense", &dense)].into_iter())?,
+        Some(2498)
+    );
+    assert_eq!(spec.expect_output([("dense", &dynamic)].into_iter())?, None);
+    assert!(matches!(
+        spec.expect_output([("dense", &integers)].into_iter()),
+        Err(Error::UnexpectedTensor { .. })
+    ));
+    assert!(matches!(
+        spec.expect_output([("logits", &dense)].into_iter()),
+        Err(Error::MissingTensor { found, .. }) if found == ["logits"]
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_from_tensors_picks_the_scores_output() -> Result<(), Error> {
+    let input = tensor(TensorElementType::Float32, &[-1, 3, 224, 224]);
+    let scores = tensor(TensorElementType::Float32, &[-1, 2498]);
+    let features = tensor(TensorElementType::Float32, &[-1, 1280, 7, 7]);
+    let indices = tensor(TensorElementType::Int64, &[-1, 5]);
+
+    let spec = ClassifierInputSpec::from_tensors(
+        [("image", &input)].into_iter(),
+        [
+            ("features", &features),
+            ("logits", &scores),
+            ("top_k", &indices),
+        ]
+        .into_iter(),
+    )?;
+    assert_eq!(spec.input_name, "image");
+    assert_eq!(spec.output_name, "logits");
+    assert_eq!(spec.layout, TensorLayout::Nchw);
+    assert_eq!((spec.width, spec.height), (224, 224));
+
+    // The only output is taken even when it is not scores, checking it reports the mismatch.
+    let spec = ClassifierInputSpec::from_tensors(
+        [("image", &input)].into_iter(),
+        [("features", &features)].into_iter(),
+    )?;
+    assert_eq!(spec.output_name, "features");
+
+    Ok(())
+}
+
+#[test]
+fn test_from_tensors_fails_on_several_scores_outputs() {
+    let input = tensor(TensorElementType::Float32, &[-1, 480, 480, 3]);
+    let scores = tensor(TensorElementType::Float32, &[-1, 2498]);
+    let features = tensor(TensorElementType::Float32, &[-1, 1280, 7, 7]);
+
+    assert!(matches!(
+        ClassifierInputSpec::from_tensors(
+            [("input", &input)].into_iter(),
+            [("dense", &scores), ("softmax", &scores)].into_iter(),
+        ),
+        Err(Error::AmbiguousOutput { found }) if found == ["dense", "softmax"]
+    ));
+
+    // Several outputs of which none are scores are as ambiguous.
+    assert!(matches!(
+        ClassifierInputSpec::from_tensors(
+            [("input", &input)].into_iter(),
+            [("features", &features), ("pooled", &features)].into_iter(),
+        ),
+        Err(Error::AmbiguousOutput { .. })
+    ));
+}
@@ -26,10 +26,7 @@ fn test_entire_process() -> Result<(), Box<dyn Error>> {
         .join("assets")
         .join("images")
         .join("african_elephants.jpg");
-    let classifier_input = ClassifierInput {
-        file_path: img_path,
-        bbox: None,
-    };
+    let classifier_input = ClassifierInput::new(img_path, None);
     let inputs = preprocess(&classifier_input)?;
 
     // Run classify inputs
@@ -5,10 +5,14 @@ use serde::{
 };
 
 /// A list of classifications stored separately as a list of labels and scores.
+///
+/// Besides the top classifications, the bundle can retain more of the scores of the classifier
+/// which are used by the ensemble for the rollups but never written out.
 #[derive(Debug, PartialEq, Clone)]
 pub struct ClassificationBundle {
     labels: Vec<String>,
     scores: Vec<f64>,
+    retained: Option<Box<ClassificationBundle>>,
 }
 
 /// Struct for storing a classification from the model.
@@ -20,8 +24,31 @@ pub struct Classification {
 
 impl ClassificationBundle {
     pub fn new(labels: Vec<String>, scores: Vec<f64>) -> Self {
-        Self { labels, scores }
+        Self {
+            labels,
+            scores,
+            retained: None,
+        }
+    }
+
+    /// Sets the retained classifications, sorted by their scores from high to low, which the
+    /// ensemble rolls up instead of the top classifications.
+    pub fn set_retained(&mut self, retained: Option<ClassificationBundle>) -> &mut Self {
+        self.retained = retained.map(Box::new);
+        self
+    }
+
+    /// Returns the retained classifications, when there are any.
+    pub fn retained(&self) -> Option<&ClassificationBundle> {
+        self.retained.as_deref()
+    }
+
+    /// Returns the classifications used for the rollups, the retained ones when there are any,
+    /// otherwise the top classifications.
+    pub fn rollup_classifications(&self) -> &ClassificationBundle {
+        self.retained().unwrap_or(self)
     }
+
     pub fn labels(&self) -> &Vec<String> {
         &self.labels
     }
@@ -96,7 +123,7 @@ impl<'de> Deserialize<'de> for ClassificationBundle {
                     ))?;
                 }
 
-                Ok(ClassificationBundle { labels, scores })
+                Ok(ClassificationBundle::new(labels, scores))
             }
         }
 
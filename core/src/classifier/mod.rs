@@ -1,4 +1,8 @@
 pub mod classification;
+pub mod model_type;
+pub mod output;
 
 pub use classification::Classification;
 pub use classification::ClassificationBundle;
+pub use model_type::ModelType;
+pub use output::{ClassifierOutputOptions, ScoreRetention};
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use crate::detector::BoundingBox;
+
+/// Possible types of the speciesnet model.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelType {
+    /// The classifier runs on the crop of the top detection, or on the full image when there are
+    /// no detections, like SpeciesNet v4.0.0a.
+    #[default]
+    AlwaysCrop,
+    /// The classifier always runs on the full image, like SpeciesNet v4.0.0b.
+    FullImage,
+}
+
+impl ModelType {
+    /// Returns the bounding box the image should be cropped to before going into the classifier,
+    /// [`None`] meaning the full image.
+    pub fn crop_box(&self, bboxes: &[BoundingBox]) -> Option<BoundingBox> {
+        match self {
+            ModelType::AlwaysCrop => bboxes.first().copied(),
+            ModelType::FullImage => None,
+        }
+    }
+
+    /// Returns `true` when the model classifies crops of the detections.
+    pub fn crops(&self) -> bool {
+        matches!(self, ModelType::AlwaysCrop)
+    }
+}
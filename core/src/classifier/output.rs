@@ -0,0 +1,57 @@
+/// Default number of the highest scoring classifications kept on each classified image.
+pub const DEFAULT_TOP_K: usize = 5;
+
+/// Default minimum score of the classifications kept by [`ScoreRetention::Sparse`].
+pub const DEFAULT_SPARSE_MIN_SCORE: f64 = 1e-3;
+
+/// How much of the classifier's scores is kept for the ensemble's rollups, on top of the top-k
+/// classifications written out.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScoreRetention {
+    /// Only the top-k classifications are kept.
+    #[default]
+    TopK,
+    /// Every classification is kept.
+    Full,
+    /// The classifications with a score of at least `min_score` are kept.
+    Sparse { min_score: f64 },
+}
+
+/// Options of what is kept from the outputs of the classifier.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassifierOutputOptions {
+    top_k: usize,
+    retention: ScoreRetention,
+}
+
+impl Default for ClassifierOutputOptions {
+    fn default() -> Self {
+        Self {
+            top_k: DEFAULT_TOP_K,
+            retention: ScoreRetention::default(),
+        }
+    }
+}
+
+impl ClassifierOutputOptions {
+    /// Sets the number of the highest scoring classifications kept on each image, 0 is treated as
+    /// 1.
+    pub fn set_top_k(&mut self, top_k: usize) -> &mut Self {
+        self.top_k = top_k.max(1);
+        self
+    }
+
+    /// Sets how much of the scores is kept for the rollups of the ensemble.
+    pub fn set_retention(&mut self, retention: ScoreRetention) -> &mut Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn top_k(&self) -> usize {
+        self.top_k
+    }
+
+    pub fn retention(&self) -> ScoreRetention {
+        self.retention
+    }
+}
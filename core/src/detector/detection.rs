@@ -2,7 +2,10 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize, ser::SerializeStruct};
 
-use crate::detector::{BoundingBox, Category};
+use crate::{
+    classifier::ClassificationBundle,
+    detector::{BoundingBox, Category},
+};
 
 /// The detection produced from running the detector model.
 #[derive(Debug, Clone, Deserialize)]
@@ -12,6 +15,10 @@ pub struct Detection {
     confidence: f64,
     #[serde(rename(deserialize = "bbox"))]
     bounding_box: BoundingBox,
+    /// The classifications of the crop of this detection, only present when the detections are
+    /// classified on their own.
+    #[serde(default)]
+    classifications: Option<ClassificationBundle>,
 }
 
 impl Display for Detection {
@@ -29,13 +36,18 @@ impl Serialize for Detection {
     where
         S: serde::Serializer,
     {
-        let mut s = serializer.serialize_struct("Detection", 4)?;
+        let field_count = if self.classifications.is_some() { 5 } else { 4 };
+        let mut s = serializer.serialize_struct("Detection", field_count)?;
 
         s.serialize_field("category", &self.category.index())?;
         s.serialize_field("label", &self.category)?;
         s.serialize_field("conf", &self.confidence)?;
         s.serialize_field("bbox", &self.bounding_box)?;
 
+        if let Some(classifications) = &self.classifications {
+            s.serialize_field("classifications", classifications)?;
+        }
+
         s.end()
     }
 }
@@ -47,9 +59,19 @@ impl Detection {
             category,
             confidence,
             bounding_box,
+            classifications: None,
         }
     }
 
+    /// Sets the classifications of the crop of this detection.
+    pub fn set_classifications(
+        &mut self,
+        classifications: Option<ClassificationBundle>,
+    ) -> &mut Self {
+        self.classifications = classifications;
+        self
+    }
+
     /// Returns the label of the category.
     pub fn label(&self) -> String {
         self.category.to_string()
@@ -69,4 +91,10 @@ impl Detection {
     pub fn confidence(&self) -> f64 {
         self.confidence
     }
+
+    /// Returns the classifications of the crop of this detection, when it has been classified on
+    /// its own.
+    pub fn classifications(&self) -> Option<&ClassificationBundle> {
+        self.classifications.as_ref()
+    }
 }
@@ -1,20 +1,41 @@
+use crate::{
+    ensemble::{EnsembleTrace, PredictionSource},
+    taxonomy::Label,
+};
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct GeofenceResult {
-    label: String,
+    label: Label,
     score: f64,
-    source: String,
+    source: PredictionSource,
+    enable_geofence: Option<bool>,
+    trace: Option<EnsembleTrace>,
 }
 
 impl GeofenceResult {
-    pub fn new(label: String, score: f64, source: String) -> Self {
+    pub fn new(label: Label, score: f64, source: PredictionSource) -> Self {
         Self {
             label,
             score,
             source,
+            enable_geofence: None,
+            trace: None,
         }
     }
 
-    pub fn label(&self) -> &str {
+    /// Records whether the result came out of an ensemble run with geofencing enabled.
+    pub fn set_enable_geofence(&mut self, enable_geofence: Option<bool>) -> &mut Self {
+        self.enable_geofence = enable_geofence;
+        self
+    }
+
+    /// Attaches the explanation of how the ensemble came to this result.
+    pub fn set_trace(&mut self, trace: Option<EnsembleTrace>) -> &mut Self {
+        self.trace = trace;
+        self
+    }
+
+    pub fn label(&self) -> &Label {
         &self.label
     }
 
@@ -22,7 +43,24 @@ impl GeofenceResult {
         self.score
     }
 
-    pub fn source(&self) -> &str {
-        &self.source
+    pub fn source(&self) -> PredictionSource {
+        self.source
+    }
+
+    /// Returns whether the ensemble which produced this result had geofencing enabled, [`None`]
+    /// when it was not recorded.
+    pub fn enable_geofence(&self) -> Option<bool> {
+        self.enable_geofence
+    }
+
+    /// Returns the explanation of how the ensemble came to this result, [`None`] when the explain
+    /// mode of the ensemble was disabled.
+    pub fn trace(&self) -> Option<&EnsembleTrace> {
+        self.trace.as_ref()
+    }
+
+    /// Consumes the result and returns its explanation.
+    pub fn into_trace(self) -> Option<EnsembleTrace> {
+        self.trace
     }
 }
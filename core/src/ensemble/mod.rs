@@ -1,3 +1,7 @@
 pub mod geofence;
+pub mod source;
+pub mod trace;
 
 pub use geofence::GeofenceResult;
+pub use source::PredictionSource;
+pub use trace::EnsembleTrace;
@@ -0,0 +1,100 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize, de};
+
+use crate::{constants::source, error::Error, taxonomy::Rank};
+
+/// Where the prediction of the ensemble came from, written as `classifier+rollup_to_family` and
+/// the like in the outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PredictionSource {
+    /// The top detection, written as `detector`.
+    Detector,
+    /// The top classification, written as `classifier`.
+    Classifier,
+    /// The classifications rolled up to a rank, written as `classifier+rollup_to_<rank>`.
+    Rollup(Rank),
+    /// The top classification was geofenced and the classifications were rolled up to a rank,
+    /// written as `classifier+geofence+rollup_to_<rank>`.
+    GeofenceRollup(Rank),
+    /// The top classification was geofenced and none of the rollups passed, written as
+    /// `classifier+geofence+rollup_failed`.
+    GeofenceRollupFailed,
+}
+
+const ROLLUP_PREFIX: &str = "classifier+rollup_to_";
+const GEOFENCE_ROLLUP_PREFIX: &str = "classifier+geofence+rollup_to_";
+const GEOFENCE_ROLLUP_FAILED: &str = "classifier+geofence+rollup_failed";
+
+impl PredictionSource {
+    /// Returns the rank the classifications were rolled up to, when they were.
+    pub fn rollup_rank(&self) -> Option<Rank> {
+        match self {
+            Self::Rollup(rank) | Self::GeofenceRollup(rank) => Some(*rank),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the top classification was geofenced.
+    pub fn is_geofenced(&self) -> bool {
+        matches!(self, Self::GeofenceRollup(_) | Self::GeofenceRollupFailed)
+    }
+}
+
+impl Display for PredictionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Detector => f.write_str(source::DETECTOR),
+            Self::Classifier => f.write_str(source::CLASSIFIER),
+            Self::Rollup(rank) => write!(f, "{ROLLUP_PREFIX}{rank}"),
+            Self::GeofenceRollup(rank) => write!(f, "{GEOFENCE_ROLLUP_PREFIX}{rank}"),
+            Self::GeofenceRollupFailed => f.write_str(GEOFENCE_ROLLUP_FAILED),
+        }
+    }
+}
+
+impl FromStr for PredictionSource {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidPredictionSource(s.to_string());
+
+        match s {
+            source::DETECTOR => Ok(Self::Detector),
+            source::CLASSIFIER => Ok(Self::Classifier),
+            GEOFENCE_ROLLUP_FAILED => Ok(Self::GeofenceRollupFailed),
+            _ => {
+                if let Some(rank) = s.strip_prefix(ROLLUP_PREFIX) {
+                    Ok(Self::Rollup(rank.parse().map_err(|_| invalid())?))
+                } else if let Some(rank) = s.strip_prefix(GEOFENCE_ROLLUP_PREFIX) {
+                    Ok(Self::GeofenceRollup(rank.parse().map_err(|_| invalid())?))
+                } else {
+                    Err(invalid())
+                }
+            }
+        }
+    }
+}
+
+impl Serialize for PredictionSource {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PredictionSource {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let source = String::deserialize(deserializer)?;
+
+        source.parse().map_err(de::Error::custom)
+    }
+}
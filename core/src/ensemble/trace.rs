@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+use crate::taxonomy::Label;
+
+/// Explanation of how the ensemble came to its prediction, recorded when the explain mode of the
+/// ensemble is enabled.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct EnsembleTrace {
+    /// The rules in the order they were evaluated, the ensemble stops at the first one which
+    /// fired.
+    pub rules: Vec<RuleStep>,
+    /// The geofence lookups in the order they were made.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub geofence_lookups: Vec<GeofenceLookup>,
+    /// The rolled up scores of each taxonomy level which was tried.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rollups: Vec<RollupStep>,
+}
+
+/// A rule of the ensemble, fired when all of its conditions passed.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct RuleStep {
+    /// Number of the rule, e.g. `1a`, following the numbering of the Python implementation.
+    pub rule: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<Condition>,
+    pub fired: bool,
+}
+
+/// A condition of a rule along with the values it compared.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Condition {
+    /// A score compared with a threshold of the ensemble config.
+    Score {
+        name: String,
+        value: f64,
+        operator: Operator,
+        threshold_name: String,
+        threshold: f64,
+        passed: bool,
+    },
+    /// A label or a detection category checked against a set of values.
+    Label {
+        name: String,
+        value: String,
+        /// Whether the value must be one of the values, or none of them.
+        one_of: bool,
+        values: Vec<String>,
+        passed: bool,
+    },
+}
+
+/// Operator used to compare a score with its threshold.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Operator {
+    #[serde(rename = ">")]
+    GreaterThan,
+    #[serde(rename = "<")]
+    LessThan,
+}
+
+impl Operator {
+    /// Compares the value with the threshold.
+    pub fn compare(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Self::GreaterThan => value > threshold,
+            Self::LessThan => value < threshold,
+        }
+    }
+}
+
+/// Whether a label was geofenced in the country and admin1 region of the image, and why.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct GeofenceLookup {
+    pub label: Label,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin1_region: Option<String>,
+    pub geofenced: bool,
+    pub reason: GeofenceReason,
+    /// The entry of the geofence which decided the lookup, when one did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<GeofenceEntry>,
+}
+
+/// Why a label was or was not geofenced.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GeofenceReason {
+    /// Geofencing is disabled.
+    Disabled,
+    /// The image has no country.
+    NoCountry,
+    /// The geofence has no rules for the label.
+    NoRules,
+    /// The label has an allow list which does not have the country.
+    CountryNotAllowed,
+    /// The country is allowed, but only in other admin1 regions.
+    RegionNotAllowed,
+    /// The whole country is blocked.
+    CountryBlocked,
+    /// The admin1 region is blocked.
+    RegionBlocked,
+    /// None of the rules of the label geofence it.
+    Allowed,
+}
+
+/// An `allow` or `block` entry of the geofence, the regions are empty when it is for the whole
+/// country.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct GeofenceEntry {
+    pub rule: String,
+    pub country: String,
+    #[serde(default)]
+    pub regions: Vec<String>,
+}
+
+/// The scores of the labels rolled up to a taxonomy level, from the highest to the lowest.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct RollupStep {
+    pub level: String,
+    pub threshold: f64,
+    pub scores: Vec<RollupScore>,
+    /// The label with the highest score which is not geofenced, when there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<Label>,
+    /// Whether the score of the label passed the threshold, which ends the rollup.
+    pub passed: bool,
+}
+
+/// A label rolled up to a taxonomy level along with the sum of the scores under it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct RollupScore {
+    pub label: Label,
+    pub score: f64,
+}
@@ -18,4 +18,18 @@ pub enum Error {
     SerdeJsonError(#[from] serde_json::error::Error),
     #[error("mozjpeg panicked: {0}")]
     MozjpegPanicError(String),
+    #[error("Invalid country code {0}, expected an ISO 3166-1 alpha-3 code such as `USA`.")]
+    InvalidCountryCode(String),
+    #[error("Invalid admin1 region {0}, expected an ISO 3166-2 subdivision code of {1}.")]
+    InvalidAdmin1Region(String, String),
+    #[error("Admin1 region {0} is given without a country.")]
+    Admin1RegionWithoutCountry(String),
+    #[error("Expected a label made of 7 parts, found {0}: {1}.")]
+    InvalidLabel(usize, String),
+    #[error(
+        "Invalid taxonomic rank {0}, expected one of `kingdom`, `class`, `order`, `family`, `genus` or `species`."
+    )]
+    InvalidRank(String),
+    #[error("Invalid prediction source {0}.")]
+    InvalidPredictionSource(String),
 }
@@ -0,0 +1,93 @@
+use super::{
+    COUNTRY_CODES, SUBDIVISION_CODES, admin1_region_code, country_alpha_2, is_admin1_region_code,
+    is_country_code, validate_location,
+};
+use crate::error::Error;
+
+#[test]
+fn test_country_alpha_2() {
+    assert_eq!(country_alpha_2("USA"), Some("US"));
+    assert_eq!(country_alpha_2("GBR"), Some("GB"));
+    assert_eq!(country_alpha_2("THA"), Some("TH"));
+    assert_eq!(country_alpha_2("ABW"), Some("AW"));
+    assert_eq!(country_alpha_2("ZWE"), Some("ZW"));
+
+    assert_eq!(country_alpha_2("usa"), None);
+    assert_eq!(country_alpha_2("US"), None);
+    assert_eq!(country_alpha_2("XXX"), None);
+    assert!(!is_country_code(""));
+}
+
+#[test]
+fn test_country_codes_table() {
+    // `country_alpha_2` binary searches the table.
+    assert!(COUNTRY_CODES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+    for (alpha_3, alpha_2) in COUNTRY_CODES {
+        assert_eq!(alpha_3.len(), 3, "{alpha_3}");
+        assert_eq!(alpha_2.len(), 2, "{alpha_2}");
+        assert_eq!(country_alpha_2(alpha_3), Some(alpha_2));
+    }
+}
+
+#[test]
+fn test_subdivision_codes_table() {
+    for code in SUBDIVISION_CODES.lines() {
+        let (alpha_2, subdivision) = code.split_once('-').unwrap();
+
+        assert!(
+            COUNTRY_CODES.iter().any(|(_, a)| *a == alpha_2),
+            "unknown country of {code}"
+        );
+        assert!(
+            !subdivision.is_empty() && !subdivision.contains('-'),
+            "{code}"
+        );
+    }
+}
+
+#[test]
+fn test_is_admin1_region_code() {
+    assert!(is_admin1_region_code("USA", "CA"));
+    assert!(is_admin1_region_code("USA", "US-CA"));
+    assert!(is_admin1_region_code("GBR", "ENG"));
+    assert!(is_admin1_region_code("FRA", "75"));
+    assert!(is_admin1_region_code("MEX", "MX-SON"));
+
+    // The region has to be of the given country.
+    assert!(!is_admin1_region_code("USA", "ENG"));
+    assert!(!is_admin1_region_code("USA", "GB-CA"));
+    assert!(!is_admin1_region_code("GBR", "US-CA"));
+    assert!(!is_admin1_region_code("USA", "ca"));
+    assert!(!is_admin1_region_code("XXX", "CA"));
+}
+
+#[test]
+fn test_admin1_region_code() {
+    assert_eq!(admin1_region_code("USA", "US-CA"), "CA");
+    assert_eq!(admin1_region_code("USA", "CA"), "CA");
+    assert_eq!(admin1_region_code("MEX", "MX-SON"), "SON");
+    // Only the prefix of the country is removed.
+    assert_eq!(admin1_region_code("USA", "GB-ENG"), "GB-ENG");
+    assert_eq!(admin1_region_code("XXX", "US-CA"), "US-CA");
+}
+
+#[test]
+fn test_validate_location() {
+    assert!(validate_location(None, None).is_ok());
+    assert!(validate_location(Some("USA"), None).is_ok());
+    assert!(validate_location(Some("USA"), Some("US-CA")).is_ok());
+
+    assert!(matches!(
+        validate_location(None, Some("CA")),
+        Err(Error::Admin1RegionWithoutCountry(_))
+    ));
+    assert!(matches!(
+        validate_location(Some("US"), None),
+        Err(Error::InvalidCountryCode(_))
+    ));
+    assert!(matches!(
+        validate_location(Some("USA"), Some("ENG")),
+        Err(Error::InvalidAdmin1Region(_, _))
+    ));
+}
@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// The stage of the pipeline which failed while running on an image, serialized the same way as
+/// the `failures` list of cameratrapai's predictions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Failure {
+    Detector,
+    Classifier,
+    Ensemble,
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Detector => "DETECTOR",
+                Self::Classifier => "CLASSIFIER",
+                Self::Ensemble => "ENSEMBLE",
+            }
+        )
+    }
+}
+
+/// The details of an error which happened while running a stage of the pipeline on an image.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FailureDetail {
+    /// The stage where the error happened.
+    stage: Failure,
+    /// Short name of the kind of the error, e.g. `io`, `image`, or `ort`.
+    kind: String,
+    /// The error message.
+    message: String,
+}
+
+impl FailureDetail {
+    pub fn new(stage: Failure, kind: String, message: String) -> Self {
+        Self {
+            stage,
+            kind,
+            message,
+        }
+    }
+
+    pub fn stage(&self) -> Failure {
+        self.stage
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
@@ -2,6 +2,8 @@ use std::path::{Path, PathBuf};
 
 use serde::Deserialize;
 
+use crate::{error::Error, geography::validate_location};
+
 /// The type of the `instances.json` file.
 #[derive(Debug, Deserialize, Clone)]
 pub struct Instances {
@@ -12,6 +14,10 @@ impl Instances {
     pub fn instances(&self) -> &[Instance] {
         &self.instances
     }
+
+    pub fn instances_mut(&mut self) -> &mut [Instance] {
+        &mut self.instances
+    }
 }
 
 /// The type of each instance of image that will be passed in to the model.
@@ -20,7 +26,7 @@ pub struct Instance {
     /// File path of the given image which is relative to where the instances json file resides.
     #[serde(rename = "filepath")]
     file_path: PathBuf,
-    /// Country code in ISO-639-1 to do ensemble on.
+    /// Country code in ISO 3166-1 alpha-3 to do ensemble on.
     country: Option<String>,
     admin1_region: Option<String>,
 }
@@ -38,6 +44,22 @@ impl Instance {
         self.admin1_region.as_deref()
     }
 
+    pub fn set_country(&mut self, country: Option<String>) -> &mut Self {
+        self.country = country;
+        self
+    }
+
+    pub fn set_admin1_region(&mut self, admin1_region: Option<String>) -> &mut Self {
+        self.admin1_region = admin1_region;
+        self
+    }
+
+    /// Validates the country and the admin1 region of the instance against the ISO 3166 codes,
+    /// see [`validate_location`].
+    pub fn validate_location(&self) -> Result<(), Error> {
+        validate_location(self.country(), self.admin1_region())
+    }
+
     /// Constructs the [`Instance`] from given input.
     pub fn new(file_path: PathBuf, country: Option<String>, admin1_region: Option<String>) -> Self {
         Self {
@@ -1,7 +1,9 @@
 //! Module for storing types related to the input and output required for running the model.
 
+pub mod failure;
 pub mod instance;
 pub mod prediction;
 
+pub use failure::{Failure, FailureDetail};
 pub use instance::{Instance, Instances};
 pub use prediction::{Prediction, Predictions};
@@ -6,9 +6,14 @@ use uuid::Uuid;
 use crate::{
     classifier::ClassificationBundle,
     detector::{BoundingBox, Detection},
-    ensemble::GeofenceResult,
+    ensemble::{EnsembleTrace, GeofenceResult, PredictionSource},
+    io::{Failure, FailureDetail},
+    taxonomy::Label,
 };
 
+#[cfg(test)]
+mod tests;
+
 /// The output type of `predictions.json` file.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Predictions {
@@ -29,6 +34,11 @@ impl Predictions {
     pub fn predictions(&self) -> &[Prediction] {
         &self.predictions
     }
+
+    /// Consumes the [`Predictions`] and returns the inner vector of [`Prediction`].
+    pub fn into_predictions(self) -> Vec<Prediction> {
+        self.predictions
+    }
 }
 
 /// The possible output of each predictions found during the run.
@@ -45,13 +55,26 @@ pub struct Prediction {
     #[serde(skip_serializing_if = "Option::is_none")]
     classifications: Option<ClassificationBundle>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    prediction: Option<String>,
+    prediction: Option<Label>,
     #[serde(skip_serializing_if = "Option::is_none")]
     prediction_score: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    prediction_source: Option<String>,
+    prediction_source: Option<PredictionSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    geofence_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    species_present: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     model_version: Option<String>,
+    /// The stages of the pipeline which failed on this image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    failures: Option<Vec<Failure>>,
+    /// The errors which caused the failures of this image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    errors: Option<Vec<FailureDetail>>,
+    /// How the ensemble came to the prediction, when its explain mode is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ensemble_trace: Option<EnsembleTrace>,
 }
 
 impl Prediction {
@@ -65,7 +88,12 @@ impl Prediction {
             prediction: None,
             prediction_score: None,
             prediction_source: None,
+            geofence_enabled: None,
+            species_present: None,
             model_version: None,
+            failures: None,
+            errors: None,
+            ensemble_trace: None,
         }
     }
 
@@ -79,7 +107,12 @@ impl Prediction {
             prediction: None,
             prediction_score: None,
             prediction_source: None,
+            geofence_enabled: None,
+            species_present: None,
             model_version: None,
+            failures: None,
+            errors: None,
+            ensemble_trace: None,
         }
     }
 
@@ -93,7 +126,12 @@ impl Prediction {
             prediction: None,
             prediction_score: None,
             prediction_source: None,
+            geofence_enabled: None,
+            species_present: None,
             model_version: None,
+            failures: None,
+            errors: None,
+            ensemble_trace: None,
         }
     }
 
@@ -111,10 +149,15 @@ impl Prediction {
             admin1_region,
             detections: Some(detections),
             classifications: Some(classifications),
-            prediction: Some(geofence_result.label().to_string()),
+            prediction: Some(geofence_result.label().clone()),
             prediction_score: Some(geofence_result.score()),
-            prediction_source: Some(geofence_result.source().to_string()),
+            prediction_source: Some(geofence_result.source()),
+            geofence_enabled: geofence_result.enable_geofence(),
+            species_present: None,
             model_version: None,
+            failures: None,
+            errors: None,
+            ensemble_trace: geofence_result.into_trace(),
         }
     }
 
@@ -146,7 +189,7 @@ impl Prediction {
         self
     }
 
-    pub fn set_prediction(&mut self, prediction: Option<String>) -> &mut Self {
+    pub fn set_prediction(&mut self, prediction: Option<Label>) -> &mut Self {
         self.prediction = prediction;
         self
     }
@@ -156,11 +199,26 @@ impl Prediction {
         self
     }
 
-    pub fn set_prediction_source(&mut self, prediction_source: Option<String>) -> &mut Self {
+    pub fn set_prediction_source(
+        &mut self,
+        prediction_source: Option<PredictionSource>,
+    ) -> &mut Self {
         self.prediction_source = prediction_source;
         self
     }
 
+    /// Sets whether the prediction came out of an ensemble run with geofencing enabled.
+    pub fn set_geofence_enabled(&mut self, geofence_enabled: Option<bool>) -> &mut Self {
+        self.geofence_enabled = geofence_enabled;
+        self
+    }
+
+    /// Sets the distinct labels of the detections which have been classified on their own.
+    pub fn set_species_present(&mut self, species_present: Option<Vec<String>>) -> &mut Self {
+        self.species_present = species_present;
+        self
+    }
+
     /// Sets the prediction object's model version to a given value.
     // TODO: Model version can be an enum.
     pub fn set_model_version(&mut self, model_version: Option<String>) -> &mut Self {
@@ -168,6 +226,27 @@ impl Prediction {
         self
     }
 
+    /// Sets the explanation of how the ensemble came to the prediction.
+    pub fn set_ensemble_trace(&mut self, ensemble_trace: Option<EnsembleTrace>) -> &mut Self {
+        self.ensemble_trace = ensemble_trace;
+        self
+    }
+
+    /// Records a failure of the given stage of the pipeline along with the error which caused it.
+    pub fn add_failure(&mut self, stage: Failure, kind: String, message: String) -> &mut Self {
+        let failures = self.failures.get_or_insert_with(Vec::new);
+
+        if !failures.contains(&stage) {
+            failures.push(stage);
+        }
+
+        self.errors
+            .get_or_insert_with(Vec::new)
+            .push(FailureDetail::new(stage, kind, message));
+
+        self
+    }
+
     /// Merges 2 [`Prediction`] structs together, where the other [`Prediction`] would override the
     /// initial predictions value if there are values in the [`Some`] variant.
     ///
@@ -176,22 +255,20 @@ impl Prediction {
     /// ```
     /// use std::path::{Path, PathBuf};
     ///
-    /// use crate::{
-    ///     category::Category,
-    ///     classification::ClassificationBundle,
-    ///     detection::Detection,
-    ///     prediction::Prediction,
+    /// use speciesnet_core::{
+    ///     classifier::ClassificationBundle,
+    ///     detector::{BoundingBox, Category, Detection},
+    ///     io::Prediction,
     /// };
     ///
-    /// let mut prediction1 = Prediction::default();
-    /// prediction1.set_file_path(PathBuf::from("./abd12333/20241212_121922.jpeg"));
+    /// let mut prediction1 = Prediction::new(PathBuf::from("./abd12333/20241212_121922.jpeg"));
     /// prediction1.set_detections(Some(vec![Detection::new(
     ///     Category::Human,
     ///     0.93,
     ///     BoundingBox::new(0.1, 0.2, 0.3, 0.3),
     /// )]));
     ///
-    /// let mut prediction2 = Prediction::default();
+    /// let mut prediction2 = Prediction::new(PathBuf::from("./abd12333/20241212_121922.jpeg"));
     ///
     /// prediction2.set_classifications(Some(ClassificationBundle::new(
     ///     vec![
@@ -213,6 +290,7 @@ impl Prediction {
     /// assert_eq!(
     ///     prediction1
     ///         .detections()
+    ///         .as_ref()
     ///         .unwrap()
     ///         .first()
     ///         .unwrap()
@@ -223,6 +301,7 @@ impl Prediction {
     /// assert_eq!(
     ///     prediction1
     ///         .detections()
+    ///         .as_ref()
     ///         .unwrap()
     ///         .first()
     ///         .unwrap()
@@ -261,10 +340,39 @@ impl Prediction {
             self.prediction_source = Some(prediction_source);
         }
 
+        if let Some(geofence_enabled) = other.geofence_enabled {
+            self.geofence_enabled = Some(geofence_enabled);
+        }
+
+        if let Some(species_present) = other.species_present {
+            self.species_present = Some(species_present);
+        }
+
         if let Some(model_version) = other.model_version {
             self.model_version = Some(model_version);
         }
 
+        if let Some(ensemble_trace) = other.ensemble_trace {
+            self.ensemble_trace = Some(ensemble_trace);
+        }
+
+        // Failures are accumulated instead of being overridden.
+        if let Some(other_failures) = other.failures {
+            let failures = self.failures.get_or_insert_with(Vec::new);
+
+            for stage in other_failures {
+                if !failures.contains(&stage) {
+                    failures.push(stage);
+                }
+            }
+        }
+
+        if let Some(other_errors) = other.errors {
+            self.errors
+                .get_or_insert_with(Vec::new)
+                .extend(other_errors);
+        }
+
         self
     }
 
@@ -272,6 +380,11 @@ impl Prediction {
         &self.detections
     }
 
+    /// Returns the detections as mutable, e.g. for setting the classifications of each detection.
+    pub fn detections_mut(&mut self) -> Option<&mut Vec<Detection>> {
+        self.detections.as_mut()
+    }
+
     pub fn classifications(&self) -> &Option<ClassificationBundle> {
         &self.classifications
     }
@@ -280,25 +393,34 @@ impl Prediction {
         &self.file_path
     }
 
-    /// Extracts the `id` part of the predicted prediction as a [`Uuid`] from the labels string in format
-    /// of.
-    ///
-    /// ```
-    /// id;class;order;family;genus;species;common name
-    /// ```
-    pub fn prediction_id(&self) -> Option<Uuid> {
-        if let Some(prediction) = &self.prediction {
-            let id_string = prediction.split(';').next().unwrap();
-            let id = uuid::Uuid::try_parse(id_string).unwrap();
+    /// Returns the stages of the pipeline which failed on this image.
+    pub fn failures(&self) -> &Option<Vec<Failure>> {
+        &self.failures
+    }
 
-            return Some(id);
-        };
+    /// Returns the details of the errors which caused the failures.
+    pub fn errors(&self) -> &Option<Vec<FailureDetail>> {
+        &self.errors
+    }
+
+    /// Returns `true` when any stage of the pipeline failed on this image.
+    pub fn has_failed(&self) -> bool {
+        self.failures.as_ref().is_some_and(|f| !f.is_empty())
+    }
+
+    /// Returns the id of the predicted label as a [`Uuid`], [`None`] when there is no prediction or
+    /// its id is not a valid UUID.
+    pub fn prediction_id(&self) -> Option<Uuid> {
+        self.prediction.as_ref().and_then(Label::uuid)
+    }
 
-        None
+    pub fn prediction_reference(&self) -> Option<&Label> {
+        self.prediction.as_ref()
     }
 
-    pub fn prediction_reference(&self) -> Option<&str> {
-        self.prediction.as_deref()
+    /// Returns where the prediction of the ensemble came from.
+    pub fn prediction_source(&self) -> Option<PredictionSource> {
+        self.prediction_source
     }
 
     /// Retrieves the confidence value of the prediction.
@@ -306,6 +428,21 @@ impl Prediction {
         self.prediction_score
     }
 
+    /// Returns whether the prediction came out of an ensemble run with geofencing enabled.
+    pub fn geofence_enabled(&self) -> Option<bool> {
+        self.geofence_enabled
+    }
+
+    /// Returns the distinct labels of the detections which have been classified on their own.
+    pub fn species_present(&self) -> Option<&[String]> {
+        self.species_present.as_deref()
+    }
+
+    /// Returns how the ensemble came to the prediction, when its explain mode was enabled.
+    pub fn ensemble_trace(&self) -> Option<&EnsembleTrace> {
+        self.ensemble_trace.as_ref()
+    }
+
     /// Copies the bounding boxes in the detection and returns it as a vector of [`BoundingBox`]es.
     pub fn bounding_boxes(&self) -> Option<Vec<BoundingBox>> {
         self.detections
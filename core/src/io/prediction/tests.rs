@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use super::Prediction;
+use crate::io::{Failure, FailureDetail};
+
+fn failed_prediction(stage: Failure, kind: &str) -> Prediction {
+    let mut prediction = Prediction::new(PathBuf::from("a.jpg"));
+    prediction.add_failure(stage, kind.to_string(), format!("{kind} failed"));
+    prediction
+}
+
+#[test]
+fn test_failures_serialization() -> Result<(), serde_json::Error> {
+    let mut prediction = failed_prediction(Failure::Detector, "image");
+    prediction.add_failure(
+        Failure::Classifier,
+        "ort".to_string(),
+        "ort failed".to_string(),
+    );
+
+    let value = serde_json::to_value(&prediction)?;
+
+    assert_eq!(value["failures"], json!(["DETECTOR", "CLASSIFIER"]));
+    assert_eq!(
+        value["errors"],
+        json!([
+            { "stage": "DETECTOR", "kind": "image", "message": "image failed" },
+            { "stage": "CLASSIFIER", "kind": "ort", "message": "ort failed" },
+        ])
+    );
+
+    // A prediction without failures leaves both fields out.
+    let value = serde_json::to_value(Prediction::new(PathBuf::from("a.jpg")))?;
+    assert!(value.get("failures").is_none());
+    assert!(value.get("errors").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_add_failure_twice() {
+    let mut prediction = failed_prediction(Failure::Classifier, "image");
+    prediction.add_failure(
+        Failure::Classifier,
+        "ort".to_string(),
+        "ort failed".to_string(),
+    );
+
+    assert!(prediction.has_failed());
+    assert_eq!(prediction.failures(), &Some(vec![Failure::Classifier]));
+    // Every error is kept even though the stage is only listed once.
+    assert_eq!(
+        prediction.errors(),
+        &Some(vec![
+            FailureDetail::new(
+                Failure::Classifier,
+                "image".to_string(),
+                "image failed".to_string()
+            ),
+            FailureDetail::new(
+                Failure::Classifier,
+                "ort".to_string(),
+                "ort failed".to_string()
+            ),
+        ])
+    );
+}
+
+#[test]
+fn test_merge_failures() {
+    let mut prediction = failed_prediction(Failure::Detector, "image");
+    prediction.merge(failed_prediction(Failure::Classifier, "ort"));
+    prediction.merge(failed_prediction(Failure::Detector, "io"));
+
+    assert_eq!(
+        prediction.failures(),
+        &Some(vec![Failure::Detector, Failure::Classifier])
+    );
+
+    let kinds: Vec<&str> = prediction
+        .errors()
+        .iter()
+        .flatten()
+        .map(FailureDetail::kind)
+        .collect();
+    assert_eq!(kinds, vec!["image", "ort", "io"]);
+
+    // Merging a prediction without failures keeps the failures.
+    prediction.merge(Prediction::new(PathBuf::from("a.jpg")));
+    assert!(prediction.has_failed());
+    assert_eq!(prediction.errors().as_ref().map(Vec::len), Some(3));
+}
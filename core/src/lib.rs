@@ -3,9 +3,12 @@ pub mod constants;
 pub mod detector;
 pub mod ensemble;
 pub mod error;
+pub mod geography;
 pub mod image_reader;
 pub mod io;
 mod macros;
+pub mod session;
 pub mod shape;
+pub mod taxonomy;
 
 pub use crate::image_reader::load_image;
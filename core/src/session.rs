@@ -0,0 +1,128 @@
+use ort::{session::builder::GraphOptimizationLevel, tensor::TensorElementType, value::ValueType};
+
+/// Level of the graph optimizations applied by onnxruntime when loading a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptimizationLevel {
+    /// Disables all of the optimizations.
+    Disable,
+    /// Basic optimizations, such as constant folding and redundant node eliminations.
+    Level1,
+    /// Extended optimizations, such as complex node fusions.
+    Level2,
+    /// All of the optimizations, including layout optimizations.
+    #[default]
+    Level3,
+}
+
+/// Struct for storing the runtime options of a model session, this is shared by the detector and
+/// the classifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionOptions {
+    intra_threads: usize,
+    optimization_level: OptimizationLevel,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            intra_threads: 2,
+            optimization_level: OptimizationLevel::Level3,
+        }
+    }
+}
+
+impl SessionOptions {
+    pub fn new(intra_threads: usize, optimization_level: OptimizationLevel) -> Self {
+        Self {
+            intra_threads,
+            optimization_level,
+        }
+    }
+
+    /// Sets the number of threads used to parallelize the execution within the nodes of the
+    /// model.
+    pub fn set_intra_threads(&mut self, intra_threads: usize) -> &mut Self {
+        self.intra_threads = intra_threads;
+        self
+    }
+
+    pub fn set_optimization_level(&mut self, optimization_level: OptimizationLevel) -> &mut Self {
+        self.optimization_level = optimization_level;
+        self
+    }
+
+    pub fn intra_threads(&self) -> usize {
+        self.intra_threads
+    }
+
+    pub fn optimization_level(&self) -> OptimizationLevel {
+        self.optimization_level
+    }
+}
+
+/// Converts the optimization level into the onnxruntime's graph optimization level.
+pub fn graph_optimization_level(level: OptimizationLevel) -> GraphOptimizationLevel {
+    match level {
+        OptimizationLevel::Disable => GraphOptimizationLevel::Disable,
+        OptimizationLevel::Level1 => GraphOptimizationLevel::Level1,
+        OptimizationLevel::Level2 => GraphOptimizationLevel::Level2,
+        OptimizationLevel::Level3 => GraphOptimizationLevel::Level3,
+    }
+}
+
+/// An input or output of a model which is not the one expected, see [`expect_tensor`]. The
+/// detector and the classifier turn it into their own errors, which name their model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TensorMismatch {
+    /// The model has no tensor of the name, `found` lists the names of the tensors it has.
+    Missing {
+        kind: &'static str,
+        name: String,
+        found: Vec<String>,
+    },
+    /// The tensor of the name is not an `f32` tensor of the expected dimensions.
+    Unexpected {
+        kind: &'static str,
+        name: String,
+        expected: String,
+        found: String,
+    },
+}
+
+/// Finds the input or output of the given name among the tensors of the model, and checks that
+/// it is an `f32` tensor whose dimensions are accepted. Dynamic dimensions are `-1`. Returns the
+/// dimensions of the tensor.
+///
+/// The `kind` is either `input` or `output`, and `expected` describes the tensor in the error,
+/// e.g. ``a `[N, labels]` f32 tensor``.
+pub fn expect_tensor<'a>(
+    kind: &'static str,
+    name: &str,
+    tensors: impl Iterator<Item = (&'a str, &'a ValueType)>,
+    expected: &str,
+    accepts: impl Fn(&[i64]) -> bool,
+) -> Result<&'a [i64], TensorMismatch> {
+    let tensors: Vec<(&str, &ValueType)> = tensors.collect();
+
+    let Some((_, value_type)) = tensors.iter().find(|(tensor, _)| *tensor == name) else {
+        return Err(TensorMismatch::Missing {
+            kind,
+            name: name.to_string(),
+            found: tensors.iter().map(|(name, _)| name.to_string()).collect(),
+        });
+    };
+
+    match value_type {
+        ValueType::Tensor {
+            ty: TensorElementType::Float32,
+            dimensions,
+            ..
+        } if accepts(dimensions) => Ok(dimensions),
+        _ => Err(TensorMismatch::Unexpected {
+            kind,
+            name: name.to_string(),
+            expected: expected.to_string(),
+            found: value_type.to_string(),
+        }),
+    }
+}
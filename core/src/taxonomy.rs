@@ -0,0 +1,309 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize, de};
+use uuid::Uuid;
+
+use crate::{constants::classification, error::Error};
+
+/// The taxonomic ranks of the labels, from the highest to the lowest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rank {
+    Kingdom,
+    Class,
+    Order,
+    Family,
+    Genus,
+    Species,
+}
+
+impl Rank {
+    /// Returns the name of the rank as used in the configs and the prediction sources.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Kingdom => "kingdom",
+            Self::Class => "class",
+            Self::Order => "order",
+            Self::Family => "family",
+            Self::Genus => "genus",
+            Self::Species => "species",
+        }
+    }
+}
+
+impl Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Rank {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kingdom" => Ok(Self::Kingdom),
+            "class" => Ok(Self::Class),
+            "order" => Ok(Self::Order),
+            "family" => Ok(Self::Family),
+            "genus" => Ok(Self::Genus),
+            "species" => Ok(Self::Species),
+            _ => Err(Error::InvalidRank(s.to_string())),
+        }
+    }
+}
+
+/// A label of the classifier, written as `uuid;class;order;family;genus;species;common name` in
+/// the labels file, the taxonomy and the outputs.
+///
+/// The non-taxonomic labels like blank and vehicle have empty ranks, and the unknown label has
+/// `no cv result` in all of them. The id is kept as it is written since it is not always a valid
+/// UUID, see [`Label::uuid`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Label {
+    id: String,
+    class: String,
+    order: String,
+    family: String,
+    genus: String,
+    species: String,
+    common_name: String,
+}
+
+impl Label {
+    /// The blank label, see [`classification::BLANK`].
+    pub fn blank() -> Self {
+        Self::known(classification::BLANK)
+    }
+
+    /// The label of the animal kingdom, see [`classification::ANIMAL`].
+    pub fn animal() -> Self {
+        Self::known(classification::ANIMAL)
+    }
+
+    /// The human label, see [`classification::HUMAN`].
+    pub fn human() -> Self {
+        Self::known(classification::HUMAN)
+    }
+
+    /// The vehicle label, see [`classification::VEHICLE`].
+    pub fn vehicle() -> Self {
+        Self::known(classification::VEHICLE)
+    }
+
+    /// The label given when the ensemble cannot tell, see [`classification::UNKNOWN`].
+    pub fn unknown() -> Self {
+        Self::known(classification::UNKNOWN)
+    }
+
+    /// Parses one of the label constants, which are known to be valid.
+    fn known(label: &str) -> Self {
+        label
+            .parse()
+            .expect("the label constants are made of 7 parts")
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the id of the label as a UUID, [`None`] when it is not a valid one.
+    pub fn uuid(&self) -> Option<Uuid> {
+        Uuid::try_parse(&self.id).ok()
+    }
+
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    pub fn order(&self) -> &str {
+        &self.order
+    }
+
+    pub fn family(&self) -> &str {
+        &self.family
+    }
+
+    pub fn genus(&self) -> &str {
+        &self.genus
+    }
+
+    pub fn species(&self) -> &str {
+        &self.species
+    }
+
+    pub fn common_name(&self) -> &str {
+        &self.common_name
+    }
+
+    /// Returns the name of the label at the given rank, empty when the label is above that rank.
+    /// The kingdom has no name in the labels, an empty string is returned for it.
+    pub fn rank_name(&self, rank: Rank) -> &str {
+        match rank {
+            Rank::Kingdom => "",
+            Rank::Class => &self.class,
+            Rank::Order => &self.order,
+            Rank::Family => &self.family,
+            Rank::Genus => &self.genus,
+            Rank::Species => &self.species,
+        }
+    }
+
+    /// Returns the lowest rank the label is named at, e.g. [`Rank::Genus`] for
+    /// `uuid;mammalia;carnivora;felidae;panthera;;panthera species`. The animal label is at the
+    /// kingdom, the other labels without any rank like blank and vehicle have no rank.
+    pub fn rank(&self) -> Option<Rank> {
+        [
+            Rank::Species,
+            Rank::Genus,
+            Rank::Family,
+            Rank::Order,
+            Rank::Class,
+        ]
+        .into_iter()
+        .find(|rank| !self.rank_name(*rank).is_empty())
+        .or_else(|| (*self == *classification::ANIMAL).then_some(Rank::Kingdom))
+    }
+
+    /// Returns the scientific name of the label, `genus species` for a species and the name of its
+    /// lowest rank otherwise, e.g. `felidae` for the cat family. [`None`] for the labels without a
+    /// named rank like animal, blank and vehicle.
+    pub fn scientific_name(&self) -> Option<String> {
+        match self.rank()? {
+            Rank::Kingdom => None,
+            Rank::Species => Some(format!("{} {}", self.genus, self.species)),
+            rank => Some(self.rank_name(rank).to_string()),
+        }
+    }
+
+    /// Returns whether the label is the taxon itself or one of its descendants, e.g. the lion is
+    /// under the cat family and every animal is under the animal kingdom.
+    pub fn is_under(&self, taxon: &Label) -> bool {
+        if self == taxon {
+            return true;
+        }
+
+        taxon.rank().is_some_and(|rank| {
+            self.ancestor_class_string(rank)
+                .is_some_and(|ancestor| ancestor == taxon.full_class_string())
+        })
+    }
+
+    /// Returns the full class string of the label, the `class;order;family;genus;species` part
+    /// which the taxonomy and the geofence are keyed by.
+    pub fn full_class_string(&self) -> String {
+        format!(
+            "{};{};{};{};{}",
+            self.class, self.order, self.family, self.genus, self.species
+        )
+    }
+
+    /// Returns the full class string of the ancestor of the label at the given rank, [`None`] when
+    /// the label is not named at that rank. Every label with a class, and the animal label, have
+    /// the kingdom `;;;;` as their ancestor.
+    ///
+    /// e.g. The ancestor at the family rank of `uuid;mammalia;carnivora;felidae;panthera;leo;lion`
+    /// is `mammalia;carnivora;felidae;;`.
+    pub fn ancestor_class_string(&self, rank: Rank) -> Option<String> {
+        if rank == Rank::Kingdom {
+            return (!self.class.is_empty() || *self == *classification::ANIMAL)
+                .then(|| ";;;;".to_string());
+        }
+
+        if self.rank_name(rank).is_empty() {
+            return None;
+        }
+
+        let ranks = [
+            Rank::Class,
+            Rank::Order,
+            Rank::Family,
+            Rank::Genus,
+            Rank::Species,
+        ];
+
+        Some(
+            ranks
+                .map(|r| if r <= rank { self.rank_name(r) } else { "" })
+                .join(";"),
+        )
+    }
+
+    fn parts(&self) -> [&str; 7] {
+        [
+            &self.id,
+            &self.class,
+            &self.order,
+            &self.family,
+            &self.genus,
+            &self.species,
+            &self.common_name,
+        ]
+    }
+}
+
+impl Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.parts().join(";"))
+    }
+}
+
+impl FromStr for Label {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(';').collect();
+
+        let [id, class, order, family, genus, species, common_name] = parts[..] else {
+            return Err(Error::InvalidLabel(parts.len(), s.to_string()));
+        };
+
+        Ok(Self {
+            id: id.to_string(),
+            class: class.to_string(),
+            order: order.to_string(),
+            family: family.to_string(),
+            genus: genus.to_string(),
+            species: species.to_string(),
+            common_name: common_name.to_string(),
+        })
+    }
+}
+
+/// Compares the label with its written form without parsing it, e.g. with the label constants.
+impl PartialEq<str> for Label {
+    fn eq(&self, other: &str) -> bool {
+        let mut parts = other.split(';');
+
+        self.parts().iter().all(|part| parts.next() == Some(part)) && parts.next().is_none()
+    }
+}
+
+impl PartialEq<&str> for Label {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl Serialize for Label {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Label {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let label = String::deserialize(deserializer)?;
+
+        label.parse().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,151 @@
+# This script generates the expected outputs of the suppression tests of the detector, to be
+# pasted in the tests when the cases change:
+#
+# - The indexes kept by `torchvision.ops.nms` and `torchvision.ops.batched_nms`, for the tests in
+#   `detector/src/torchvision.rs`. The cases cover boxes with the same score, which torchvision
+#   keeps in their original order, boxes of zero area, whose IoU with each other is 0 / 0 and never
+#   suppresses anything, and an IoU right at the threshold, which is only suppressed above it.
+# - The indexes and decayed scores kept by the linear and Gaussian Soft-NMS of `ensemble_boxes`,
+#   for the tests in `detector/src/suppression.rs`.
+# - The boxes fused by the Weighted Boxes Fusion of `ensemble_boxes`, for the tests in
+#   `detector/src/fusion.rs`.
+#
+# `ensemble_boxes` picks the next box of the Soft-NMS and sorts the boxes of the fusion without
+# keeping ties in their original order, so the scores of those cases are all different.
+#
+# Usage: pip install torch torchvision ensemble-boxes && python nms_fixtures.py
+
+import numpy as np
+import torch
+from ensemble_boxes import weighted_boxes_fusion
+from ensemble_boxes.ensemble_boxes_nms import cpu_soft_nms_float
+from torchvision.ops import batched_nms, nms
+
+OVERLAPPING = [
+    [0.0, 0.0, 10.0, 10.0],
+    [1.0, 1.0, 11.0, 11.0],
+    [20.0, 20.0, 30.0, 30.0],
+    [0.0, 0.0, 10.0, 5.0],
+    [21.0, 21.0, 29.0, 31.0],
+]
+OVERLAPPING_SCORES = [0.9, 0.8, 0.7, 0.95, 0.6]
+
+TIES = [
+    [0.0, 0.0, 10.0, 10.0],
+    [5.0, 0.0, 15.0, 10.0],
+    [10.0, 0.0, 20.0, 10.0],
+    [0.0, 0.0, 10.0, 10.0],
+]
+TIES_SCORES = [0.8, 0.8, 0.8, 0.8]
+
+ZERO_AREA = [
+    [0.0, 0.0, 10.0, 10.0],
+    [5.0, 5.0, 5.0, 5.0],
+    [5.0, 5.0, 5.0, 5.0],
+    [2.0, 0.0, 2.0, 10.0],
+    [0.0, 0.0, 10.0, 10.0],
+]
+ZERO_AREA_SCORES = [0.9, 0.8, 0.7, 0.6, 0.5]
+
+BATCHED = [
+    [0.0, 0.0, 10.0, 10.0],
+    [1.0, 1.0, 11.0, 11.0],
+    [1.0, 1.0, 11.0, 11.0],
+    [0.0, 0.0, 10.0, 10.0],
+    [20.0, 20.0, 30.0, 30.0],
+]
+BATCHED_SCORES = [0.9, 0.8, 0.7, 0.9, 0.6]
+BATCHED_CLASSES = [0, 1, 0, 1, 0]
+
+NMS_CASES = [
+    ('overlapping', OVERLAPPING, OVERLAPPING_SCORES, [0.5, 0.7, 0.49]),
+    ('ties', TIES, TIES_SCORES, [0.3, 0.5]),
+    ('zero_area', ZERO_AREA, ZERO_AREA_SCORES, [0.0, 0.5]),
+]
+
+BATCHED_CASES = [
+    ('batched', BATCHED, BATCHED_SCORES, BATCHED_CLASSES, [0.5, 0.7]),
+]
+
+SOFT_NMS = [
+    [0.0, 0.0, 10.0, 10.0],
+    [1.0, 1.0, 11.0, 11.0],
+    [2.0, 0.0, 12.0, 10.0],
+    [20.0, 20.0, 30.0, 30.0],
+    [22.0, 22.0, 31.0, 33.0],
+    [5.0, 5.0, 15.0, 15.0],
+]
+SOFT_NMS_SCORES = [0.9, 0.8, 0.75, 0.7, 0.65, 0.3]
+
+# The method of `cpu_soft_nms_float`, 1 for linear and 2 for Gaussian, the IoU threshold of the
+# linear decay, the sigma of the Gaussian decay and the score threshold.
+SOFT_NMS_CASES = [
+    ('linear', 1, 0.5, 0.5, 0.01),
+    ('linear', 1, 0.3, 0.5, 0.2),
+    ('gaussian', 2, 0.5, 0.5, 0.001),
+    ('gaussian', 2, 0.5, 0.1, 0.3),
+]
+
+# The boxes of the fusion are relative to the size of the image, as `ensemble_boxes` expects.
+WBF = [
+    [0.10, 0.10, 0.30, 0.30],
+    [0.12, 0.11, 0.31, 0.32],
+    [0.11, 0.09, 0.29, 0.31],
+    [0.50, 0.50, 0.70, 0.70],
+    [0.55, 0.52, 0.74, 0.71],
+    [0.13, 0.12, 0.33, 0.30],
+    [0.80, 0.80, 0.90, 0.90],
+]
+WBF_SCORES = [0.9, 0.8, 0.4, 0.7, 0.6, 0.35, 0.2]
+WBF_CLASSES = [0, 0, 1, 0, 0, 0, 1]
+
+WBF_CASES = [0.6, 0.3]
+
+
+def tensor(values):
+    return torch.tensor(values, dtype=torch.float32)
+
+
+print('torchvision %s' % __import__('torchvision').__version__)
+
+for name, boxes, scores, thresholds in NMS_CASES:
+    for threshold in thresholds:
+        keep = nms(tensor(boxes), tensor(scores), threshold).tolist()
+        print('nms %s %s: %s' % (name, threshold, keep))
+
+for name, boxes, scores, classes, thresholds in BATCHED_CASES:
+    for threshold in thresholds:
+        idxs = torch.tensor(classes, dtype=torch.int64)
+        keep = batched_nms(tensor(boxes), tensor(scores), idxs, threshold).tolist()
+        print('batched_nms %s %s: %s' % (name, threshold, keep))
+
+for name, method, iou_threshold, sigma, score_threshold in SOFT_NMS_CASES:
+    # The scores are decayed in place, in the order the boxes are picked in.
+    scores = np.array(SOFT_NMS_SCORES, dtype=np.float64)
+    keep = cpu_soft_nms_float(
+        np.array(SOFT_NMS, dtype=np.float64),
+        scores,
+        Nt=iou_threshold,
+        sigma=sigma,
+        thresh=score_threshold,
+        method=method,
+    )
+    kept_scores = scores[scores > score_threshold]
+    print(
+        'soft_nms %s iou %s sigma %s threshold %s: %s'
+        % (
+            name,
+            iou_threshold,
+            sigma,
+            score_threshold,
+            [(int(i), round(float(score), 6)) for i, score in zip(keep, kept_scores)],
+        )
+    )
+
+for iou_threshold in WBF_CASES:
+    boxes, scores, labels = weighted_boxes_fusion(
+        [WBF], [WBF_SCORES], [WBF_CLASSES], iou_thr=iou_threshold, skip_box_thr=0.0
+    )
+    print('weighted_boxes_fusion %s:' % iou_threshold)
+    for box, score, label in zip(boxes, scores, labels):
+        print('    %s' % [round(float(value), 6) for value in [*box, score, label]])
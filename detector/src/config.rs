@@ -0,0 +1,85 @@
+use crate::{error::Error, suppression::Suppression};
+
+/// Default minimum confidence of a detection, the same as the detector of cameratrapai.
+pub const DEFAULT_CONFIDENCE_THRESHOLD: f32 = 0.01;
+
+/// Default IoU above which the overlapping boxes are suppressed.
+pub const DEFAULT_IOU_THRESHOLD: f32 = 0.45;
+
+/// Default maximum number of detections kept on each image.
+pub const DEFAULT_MAX_DETECTIONS: usize = 300;
+
+/// Post-processing options of the detector.
+///
+/// The defaults are the same as the detector of cameratrapai, [`DetectorConfig::validate`] rejects
+/// any value which is out of its range instead of replacing it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectorConfig {
+    /// Minimum confidence of a detection to be kept, between 0 and 1.
+    pub confidence_threshold: f32,
+    /// IoU above which the less confident of two overlapping boxes is suppressed, between 0 and 1.
+    pub iou_threshold: f32,
+    /// Maximum number of detections kept on each image, at least 1.
+    pub max_detections: usize,
+    /// Suppresses the overlapping boxes regardless of their categories, instead of only the boxes
+    /// of the same category.
+    pub class_agnostic: bool,
+    /// Minimum area of a box as a fraction of the image's area, between 0 and 1.
+    pub min_box_area: f64,
+    /// Minimum width and height of a box in pixels of the original image.
+    pub min_box_size: f64,
+    /// How the overlapping boxes are suppressed, greedy non-max suppression by default.
+    pub suppression: Suppression,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            confidence_threshold: DEFAULT_CONFIDENCE_THRESHOLD,
+            iou_threshold: DEFAULT_IOU_THRESHOLD,
+            max_detections: DEFAULT_MAX_DETECTIONS,
+            class_agnostic: false,
+            min_box_area: 0.0,
+            min_box_size: 0.0,
+            suppression: Suppression::default(),
+        }
+    }
+}
+
+impl DetectorConfig {
+    /// Checks that every option is inside of its range.
+    pub fn validate(&self) -> Result<(), Error> {
+        let fractions = [
+            ("confidence_threshold", f64::from(self.confidence_threshold)),
+            ("iou_threshold", f64::from(self.iou_threshold)),
+            ("min_box_area", self.min_box_area),
+        ];
+
+        for (name, value) in fractions {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(Error::InvalidConfig(format!(
+                    "`{name}` must be between 0 and 1, but found {value}"
+                )));
+            }
+        }
+
+        if self.max_detections == 0 {
+            return Err(Error::InvalidConfig(
+                "`max_detections` must be at least 1".to_string(),
+            ));
+        }
+
+        if !self.min_box_size.is_finite() || self.min_box_size < 0.0 {
+            return Err(Error::InvalidConfig(format!(
+                "`min_box_size` must be a positive number of pixels, but found {}",
+                self.min_box_size
+            )));
+        }
+
+        if let Some(reason) = self.suppression.invalid_reason() {
+            return Err(Error::InvalidConfig(reason));
+        }
+
+        Ok(())
+    }
+}
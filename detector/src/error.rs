@@ -1,3 +1,5 @@
+use speciesnet_core::session::TensorMismatch;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("ort error: {0}")]
@@ -8,6 +10,44 @@ pub enum Error {
     ImageDecodeError(#[from] image::error::ImageError),
     #[error("ndarray shape error: {0}")]
     ShapeError(#[from] ndarray::ShapeError),
+    #[error("invalid detector config: {0}")]
+    InvalidConfig(String),
+    #[error("invalid detector model: {0}")]
+    InvalidModel(String),
+    #[error("expected {kind} `{name}` in the detector model, model has {found:?}")]
+    MissingTensor {
+        kind: &'static str,
+        name: String,
+        found: Vec<String>,
+    },
+    #[error("expected {kind} `{name}` of the detector model to be {expected}, model has {found}")]
+    UnexpectedTensor {
+        kind: &'static str,
+        name: String,
+        expected: String,
+        found: String,
+    },
     #[error("speciesnet core error: {0}")]
     SpeciesNetCoreError(#[from] speciesnet_core::error::Error),
 }
+
+impl From<TensorMismatch> for Error {
+    fn from(mismatch: TensorMismatch) -> Self {
+        match mismatch {
+            TensorMismatch::Missing { kind, name, found } => {
+                Self::MissingTensor { kind, name, found }
+            }
+            TensorMismatch::Unexpected {
+                kind,
+                name,
+                expected,
+                found,
+            } => Self::UnexpectedTensor {
+                kind,
+                name,
+                expected,
+                found,
+            },
+        }
+    }
+}
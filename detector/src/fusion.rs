@@ -0,0 +1,173 @@
+use ndarray::{Array2, ArrayView2};
+
+use crate::torchvision::iou;
+
+/// Merges the overlapping `(x1, y1, x2, y2, confidence, class)` boxes with [Weighted Boxes Fusion].
+///
+/// Unlike non-max suppression, which keeps the most confident box and throws away the rest, the
+/// coordinates of the overlapping boxes are averaged weighted by their confidences, and the
+/// confidence of the fused box is the mean confidence of the boxes it is made of. Boxes of
+/// different classes are only fused together when `class_agnostic` is set, the fused box then
+/// takes the class of its most confident box. The fused boxes are sorted from the most confident
+/// one.
+///
+/// [Weighted Boxes Fusion]: https://arxiv.org/abs/1910.13302
+pub fn weighted_box_fusion(
+    detections: ArrayView2<f32>,
+    iou_threshold: f32,
+    class_agnostic: bool,
+) -> Array2<f32> {
+    let mut order: Vec<usize> = (0..detections.nrows()).collect();
+    order.sort_by(|&a, &b| detections[[b, 4]].total_cmp(&detections[[a, 4]]));
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+
+    for index in order {
+        let row = detections.row(index);
+        let bbox = [row[0], row[1], row[2], row[3]];
+        let (confidence, class) = (row[4], row[5]);
+
+        let best = clusters
+            .iter_mut()
+            .filter(|cluster| class_agnostic || cluster.class == class)
+            .map(|cluster| (iou(&cluster.fused, &bbox), cluster))
+            .filter(|(iou, _)| *iou > iou_threshold)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        match best {
+            Some((_, cluster)) => cluster.add(bbox, confidence),
+            None => clusters.push(Cluster::new(bbox, confidence, class)),
+        }
+    }
+
+    let mut fused: Vec<[f32; 6]> = clusters.iter().map(Cluster::fused_row).collect();
+    fused.sort_by(|a, b| b[4].total_cmp(&a[4]));
+
+    let rows = fused.len();
+    Array2::from_shape_vec((rows, 6), fused.into_iter().flatten().collect())
+        .expect("each fused row has 6 columns")
+}
+
+/// Boxes which are fused together.
+#[derive(Debug)]
+struct Cluster {
+    /// Sum of the coordinates of the boxes weighted by their confidences.
+    weighted: [f32; 4],
+    confidence_sum: f32,
+    count: usize,
+    fused: [f32; 4],
+    class: f32,
+}
+
+impl Cluster {
+    fn new(bbox: [f32; 4], confidence: f32, class: f32) -> Self {
+        let mut cluster = Self {
+            weighted: [0.0; 4],
+            confidence_sum: 0.0,
+            count: 0,
+            fused: bbox,
+            class,
+        };
+        cluster.add(bbox, confidence);
+        cluster
+    }
+
+    fn add(&mut self, bbox: [f32; 4], confidence: f32) {
+        for (weighted, coordinate) in self.weighted.iter_mut().zip(bbox) {
+            *weighted += coordinate * confidence;
+        }
+
+        self.confidence_sum += confidence;
+        self.count += 1;
+
+        if self.confidence_sum > 0.0 {
+            self.fused = self.weighted.map(|weighted| weighted / self.confidence_sum);
+        }
+    }
+
+    fn fused_row(&self) -> [f32; 6] {
+        let [x1, y1, x2, y2] = self.fused;
+        let confidence = self.confidence_sum / self.count as f32;
+
+        [x1, y1, x2, y2, confidence, self.class]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn weighted_box_fusion_averages_overlapping_boxes() {
+        let detections: Array2<f32> = array![
+            [10.0, 10.0, 20.0, 20.0, 0.2, 0.0],
+            [12.0, 10.0, 22.0, 20.0, 0.6, 0.0],
+            [50.0, 50.0, 60.0, 60.0, 0.3, 0.0],
+        ];
+
+        let fused = weighted_box_fusion(detections.view(), 0.5, false);
+
+        assert_eq!(fused.nrows(), 2);
+        assert!((fused[[0, 0]] - 11.5).abs() < 1e-5);
+        assert!((fused[[0, 2]] - 21.5).abs() < 1e-5);
+        assert!((fused[[0, 4]] - 0.4).abs() < 1e-5);
+        assert_eq!(fused[[1, 0]], 50.0);
+    }
+
+    // The expected boxes are the output of `weighted_boxes_fusion` of `ensemble_boxes` on the same
+    // boxes, generated by `detector/scripts/nms_fixtures.py`. Rerun it and update them when
+    // changing the cases.
+
+    fn reference_detections() -> Array2<f32> {
+        array![
+            [0.10, 0.10, 0.30, 0.30, 0.9, 0.0],
+            [0.12, 0.11, 0.31, 0.32, 0.8, 0.0],
+            [0.11, 0.09, 0.29, 0.31, 0.4, 1.0],
+            [0.50, 0.50, 0.70, 0.70, 0.7, 0.0],
+            [0.55, 0.52, 0.74, 0.71, 0.6, 0.0],
+            [0.13, 0.12, 0.33, 0.30, 0.35, 0.0],
+            [0.80, 0.80, 0.90, 0.90, 0.2, 1.0],
+        ]
+    }
+
+    fn assert_fused(fused: &Array2<f32>, expected: &Array2<f32>) {
+        assert_eq!(fused.dim(), expected.dim());
+
+        for (value, expected) in fused.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-5,
+                "expected {expected}, got {value} in {fused}"
+            );
+        }
+    }
+
+    #[test]
+    fn weighted_box_fusion_matches_ensemble_boxes() {
+        let detections = reference_detections();
+
+        let fused = weighted_box_fusion(detections.view(), 0.6, false);
+        assert_fused(
+            &fused,
+            &array![
+                [0.5, 0.5, 0.7, 0.7, 0.7, 0.0],
+                [0.112927, 0.107317, 0.309024, 0.307805, 0.683333, 0.0],
+                [0.55, 0.52, 0.74, 0.71, 0.6, 0.0],
+                [0.11, 0.09, 0.29, 0.31, 0.4, 1.0],
+                [0.8, 0.8, 0.9, 0.9, 0.2, 1.0],
+            ],
+        );
+
+        let fused = weighted_box_fusion(detections.view(), 0.3, false);
+        assert_fused(
+            &fused,
+            &array![
+                [0.112927, 0.107317, 0.309024, 0.307805, 0.683333, 0.0],
+                [0.523077, 0.509231, 0.718462, 0.704615, 0.65, 0.0],
+                [0.11, 0.09, 0.29, 0.31, 0.4, 1.0],
+                [0.8, 0.8, 0.9, 0.9, 0.2, 1.0],
+            ],
+        );
+    }
+}
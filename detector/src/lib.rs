@@ -1,29 +1,37 @@
-use std::{path::Path, sync::Arc};
-
-use image::DynamicImage;
-use ndarray::Ix3;
-use ort::{
-    session::{Session, builder::GraphOptimizationLevel},
-    value::Tensor,
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
 };
+
+use image::{DynamicImage, GenericImageView, RgbImage};
+use model::{Detector, DetectorKind};
+use ndarray::{Array2, s};
 use preprocess::{LetterboxOptions, PreprocessedImage, PreprocessedImageInner, letterbox};
 use speciesnet_core::{
     detector::{BoundingBox, Category, Detection},
     io::Prediction,
+    session::SessionOptions,
 };
+use tiling::{Tile, TilingOptions};
 use tracing::info;
-use yolo::non_max_suppression;
 
-use crate::error::Error;
+use crate::{config::DetectorConfig, error::Error};
 
+pub mod config;
 pub mod error;
+pub mod fusion;
+pub mod model;
 pub mod preprocess;
+pub mod suppression;
+pub mod tiling;
 pub mod torchvision;
 pub mod yolo;
 
 #[derive(Debug, Clone)]
 pub struct SpeciesNetDetector {
-    model: Arc<Session>,
+    model: Arc<dyn Detector>,
+    config: DetectorConfig,
+    tiling: Option<TilingOptions>,
 }
 
 impl SpeciesNetDetector {
@@ -31,20 +39,72 @@ impl SpeciesNetDetector {
     where
         P: AsRef<Path>,
     {
-        let model = Session::builder()?
-            .with_optimization_level(GraphOptimizationLevel::Level3)?
-            .with_intra_threads(2)?
-            .commit_from_file(model_path)?;
+        Self::with_options(model_path, &SessionOptions::default())
+    }
+
+    /// Loads the detector model with the given session options, the model is expected to have
+    /// the YOLOv5 output of MegaDetector v5.
+    pub fn with_options<P>(model_path: P, options: &SessionOptions) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::from_detector(
+            DetectorKind::YoloV5.load(model_path, options)?,
+        ))
+    }
+
+    /// Runs the images through the given detector instead of an ONNX model of MegaDetector v5.
+    pub fn from_detector(model: Arc<dyn Detector>) -> Self {
+        Self {
+            model,
+            config: DetectorConfig::default(),
+            tiling: None,
+        }
+    }
+
+    /// Sets the post-processing options of the detector, the config is rejected when any of its
+    /// values is out of range.
+    pub fn set_config(&mut self, config: DetectorConfig) -> Result<&mut Self, Error> {
+        config.validate()?;
+        self.config = config;
+        Ok(self)
+    }
+
+    pub fn config(&self) -> &DetectorConfig {
+        &self.config
+    }
+
+    /// Sets the options of the sliced inference, see [`SpeciesNetDetector::predict_tiled`]. The
+    /// options are rejected when any of their values is out of range, [`None`] turns the sliced
+    /// inference off.
+    pub fn set_tiling(&mut self, tiling: Option<TilingOptions>) -> Result<&mut Self, Error> {
+        if let Some(tiling) = &tiling {
+            tiling.validate()?;
+        }
+
+        self.tiling = tiling;
+        Ok(self)
+    }
 
-        Ok(Self {
-            model: Arc::new(model),
-        })
+    pub fn tiling(&self) -> Option<&TilingOptions> {
+        self.tiling.as_ref()
     }
 
     pub fn preprocess(
         &self,
         image: DynamicImage,
         options: LetterboxOptions,
+    ) -> Result<PreprocessedImageInner, Error> {
+        let preprocessed_image = letterbox(&image.into_rgb8(), options)?;
+        Ok(preprocessed_image)
+    }
+
+    /// Letterboxes the borrowed image to be detector compatible, unlike
+    /// [`SpeciesNetDetector::preprocess`] the image is not consumed.
+    pub fn preprocess_ref(
+        &self,
+        image: &RgbImage,
+        options: LetterboxOptions,
     ) -> Result<PreprocessedImageInner, Error> {
         let preprocessed_image = letterbox(image, options)?;
         Ok(preprocessed_image)
@@ -54,53 +114,236 @@ impl SpeciesNetDetector {
         &self,
         preprocessed_image: PreprocessedImage,
     ) -> Result<Option<Prediction>, Error> {
-        let (original_width, original_height) = preprocessed_image.original_size();
-        let (resized_width, resized_height) = preprocessed_image.resized_size();
-        let path = preprocessed_image.path_owned();
-        let tensor = preprocessed_image.into_tensor();
-
-        info!("Running predictions on image {}.", path.display());
-        let outputs = self
-            .model
-            .run(ort::inputs!["images" => Tensor::from_array(tensor)?]?)?;
-
-        let output = outputs
-            .get("output")
-            .unwrap()
-            .try_extract_tensor::<f32>()?
-            .into_dimensionality::<Ix3>()?
-            .into_owned();
-
-        info!("Running non-max suppression on image {}.", path.display());
-        let nms_results = non_max_suppression(output, Some(0.01))?;
-
-        if nms_results.is_empty() {
-            return Ok(None);
+        let mut predictions = self.predict_batch(std::slice::from_ref(&preprocessed_image))?;
+        Ok(predictions.pop().flatten())
+    }
+
+    /// Runs the detector on overlapping tiles of the image instead of the whole image at once,
+    /// with the tiling options of the detector or their defaults when they are not set.
+    ///
+    /// Each tile is letterboxed with the given options, the boxes found in the tiles are mapped
+    /// back onto the image and merged, then filtered by the config of the detector like the boxes
+    /// of [`SpeciesNetDetector::predict`].
+    pub fn predict_tiled<P>(
+        &self,
+        image: &RgbImage,
+        path: P,
+        options: LetterboxOptions,
+    ) -> Result<Option<Prediction>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let tiling = self.tiling.unwrap_or_default();
+        let (width, height) = image.dimensions();
+
+        let mut tiles = tiling.tiles(width, height);
+
+        if tiling.include_full_image && tiles.len() > 1 {
+            tiles.insert(
+                0,
+                Tile {
+                    x: 0,
+                    y: 0,
+                    width,
+                    height,
+                },
+            );
+        }
+
+        info!(
+            "Running the detector on {} tiles of {}.",
+            tiles.len(),
+            path.as_ref().display()
+        );
+
+        let mut rows: Vec<f32> = Vec::new();
+
+        for chunk in tiles.chunks(tiling.batch_size) {
+            let mut images = Vec::with_capacity(chunk.len());
+
+            for tile in chunk {
+                let tile_image = image
+                    .view(tile.x, tile.y, tile.width, tile.height)
+                    .to_image();
+                images.push(PreprocessedImage::new(
+                    letterbox(&tile_image, options)?,
+                    path.as_ref(),
+                ));
+            }
+
+            for ((tile, preprocessed_image), nms_result) in
+                chunk.iter().zip(&images).zip(self.predict_raw(&images)?)
+            {
+                let (tile_width, tile_height) = preprocessed_image.original_size();
+                let (resized_width, resized_height) = preprocessed_image.resized_size();
+
+                if nms_result.is_empty() {
+                    continue;
+                }
+
+                for raw_detection in nms_result.rows() {
+                    let bbox = BoundingBox::new(
+                        f64::from(raw_detection[0]),
+                        f64::from(raw_detection[1]),
+                        f64::from(raw_detection[2]),
+                        f64::from(raw_detection[3]),
+                    )
+                    .scale_to(
+                        resized_width,
+                        resized_height,
+                        tile_width,
+                        tile_height,
+                    );
+
+                    rows.extend([
+                        (bbox.x1() + f64::from(tile.x)) as f32,
+                        (bbox.y1() + f64::from(tile.y)) as f32,
+                        (bbox.x2() + f64::from(tile.x)) as f32,
+                        (bbox.y2() + f64::from(tile.y)) as f32,
+                        raw_detection[4],
+                        raw_detection[5],
+                    ]);
+                }
+            }
         }
 
-        let mut detections: Vec<Detection> = Vec::new();
+        let detections = Array2::from_shape_vec((rows.len() / 6, 6), rows)?;
+        let mut merged = tiling.merge(detections, self.config.class_agnostic);
 
-        for raw_detection in nms_results.rows() {
-            let x1: f64 = f64::from(raw_detection[0]);
-            let y1: f64 = f64::from(raw_detection[1]);
-            let x2: f64 = f64::from(raw_detection[2]);
-            let y2: f64 = f64::from(raw_detection[3]);
+        if merged.nrows() > self.config.max_detections {
+            merged = merged.slice_move(s![..self.config.max_detections, ..]);
+        }
 
-            let confidence = raw_detection[4];
-            let category = Category::try_from(raw_detection[5].trunc() as i32 + 1).unwrap();
+        // The merged boxes are already on the original image, so they are only clipped and
+        // normalized.
+        Ok(to_prediction(
+            path.as_ref().to_path_buf(),
+            merged,
+            (width, height),
+            (width, height),
+            &self.config,
+        ))
+    }
 
-            let bbox = BoundingBox::new(x1, y1, x2, y2)
-                .scale_to(
-                    resized_width,
-                    resized_height,
-                    original_width,
-                    original_height,
+    /// Runs the detector on a batch of images, returns the prediction of each image in the same
+    /// order as the given images.
+    ///
+    /// The images which have the same letterboxed dimensions are stacked into one `[N, 3, H, W]`
+    /// tensor and run through the model together.
+    pub fn predict_batch(
+        &self,
+        preprocessed_images: &[PreprocessedImage],
+    ) -> Result<Vec<Option<Prediction>>, Error> {
+        Ok(preprocessed_images
+            .iter()
+            .zip(self.predict_raw(preprocessed_images)?)
+            .map(|(preprocessed_image, nms_result)| {
+                to_prediction(
+                    preprocessed_image.path_owned(),
+                    nms_result,
+                    preprocessed_image.resized_size(),
+                    preprocessed_image.original_size(),
+                    &self.config,
                 )
-                .normalize(original_width, original_height);
+            })
+            .collect())
+    }
 
-            detections.push(Detection::new(category, confidence.into(), bbox));
+    /// Runs the detector on a batch of images like [`SpeciesNetDetector::predict_batch`], returns
+    /// the non-max suppressed boxes of each image on its letterboxed image.
+    fn predict_raw(
+        &self,
+        preprocessed_images: &[PreprocessedImage],
+    ) -> Result<Vec<Array2<f32>>, Error> {
+        let mut results: Vec<Array2<f32>> = vec![Array2::zeros((0, 6)); preprocessed_images.len()];
+
+        // Groups the indexes of the images by their dimensions, keeping the order of the first
+        // appearance of each dimension.
+        let mut groups: Vec<((u32, u32), Vec<usize>)> = Vec::new();
+
+        for (index, image) in preprocessed_images.iter().enumerate() {
+            let dimensions = image.image().dimensions();
+
+            match groups.iter_mut().find(|(d, _)| *d == dimensions) {
+                Some((_, indexes)) => indexes.push(index),
+                None => groups.push((dimensions, vec![index])),
+            }
         }
 
-        Ok(Some(Prediction::from_detections(path, detections)))
+        for (_, indexes) in groups {
+            let batch: Vec<&PreprocessedImage> = indexes
+                .iter()
+                .map(|&index| &preprocessed_images[index])
+                .collect();
+
+            for (index, result) in indexes.into_iter().zip(self.run_batch(&batch)?) {
+                results[index] = result;
+            }
+        }
+
+        Ok(results)
     }
+
+    /// Runs the model on a batch of images which all have the same dimensions.
+    fn run_batch(&self, batch: &[&PreprocessedImage]) -> Result<Vec<Array2<f32>>, Error> {
+        let tensor = PreprocessedImage::batch_tensor(batch);
+
+        info!("Running predictions on a batch of {} images.", batch.len());
+        self.model.detect(tensor, &self.config)
+    }
+}
+
+/// Converts the non-max suppressed output of an image into its prediction, leaving out the boxes
+/// smaller than the minimum box area or size of the config. Returns [`None`] when nothing was
+/// detected.
+fn to_prediction(
+    path: PathBuf,
+    nms_results: Array2<f32>,
+    (resized_width, resized_height): (u32, u32),
+    (original_width, original_height): (u32, u32),
+    config: &DetectorConfig,
+) -> Option<Prediction> {
+    if nms_results.is_empty() {
+        return None;
+    }
+
+    let mut detections: Vec<Detection> = Vec::new();
+
+    for raw_detection in nms_results.rows() {
+        let x1: f64 = f64::from(raw_detection[0]);
+        let y1: f64 = f64::from(raw_detection[1]);
+        let x2: f64 = f64::from(raw_detection[2]);
+        let y2: f64 = f64::from(raw_detection[3]);
+
+        let confidence = raw_detection[4];
+        // A detector with more classes than MegaDetector has its extra classes left out.
+        let Ok(category) = Category::try_from(raw_detection[5].trunc() as i32 + 1) else {
+            continue;
+        };
+
+        let bbox = BoundingBox::new(x1, y1, x2, y2).scale_to(
+            resized_width,
+            resized_height,
+            original_width,
+            original_height,
+        );
+
+        if (bbox.x2() - bbox.x1()).min(bbox.y2() - bbox.y1()) < config.min_box_size {
+            continue;
+        }
+
+        let bbox = bbox.normalize(original_width, original_height);
+
+        if bbox.area() < config.min_box_area {
+            continue;
+        }
+
+        detections.push(Detection::new(category, confidence.into(), bbox));
+    }
+
+    if detections.is_empty() {
+        return None;
+    }
+
+    Some(Prediction::from_detections(path, detections))
 }
@@ -0,0 +1,279 @@
+use std::{fmt::Debug, path::Path, sync::Arc};
+
+use ndarray::{Array2, Array4, Ix3};
+use ort::{
+    session::Session,
+    value::{Tensor, ValueType},
+};
+use speciesnet_core::session::{SessionOptions, expect_tensor, graph_optimization_level};
+use tracing::info;
+
+use crate::{
+    config::DetectorConfig,
+    error::Error,
+    yolo::{non_max_suppression_anchor_free, non_max_suppression_with},
+};
+
+/// A detection model along with the decoder of its output.
+///
+/// The letterboxing, the tiling and the mapping of the boxes back onto the images are done by
+/// [`SpeciesNetDetector`](crate::SpeciesNetDetector), an implementation only runs the model and
+/// turns its raw output into boxes. Any model which finds animals, people and vehicles can be
+/// plugged in this way, without touching the rest of the pipeline.
+pub trait Detector: Debug + Send + Sync {
+    /// Runs the model on a `[N, 3, H, W]` batch of letterboxed images, returns the suppressed
+    /// `(x1, y1, x2, y2, confidence, class)` detections of each image in the coordinates of its
+    /// letterboxed image. The class is `0` for animal, `1` for person and `2` for vehicle.
+    fn detect(
+        &self,
+        images: Array4<f32>,
+        config: &DetectorConfig,
+    ) -> Result<Vec<Array2<f32>>, Error>;
+}
+
+/// Name of the image input of the YOLOv5 detectors.
+const YOLOV5_INPUT: &str = "images";
+/// Name of the boxes output of the YOLOv5 detectors.
+const YOLOV5_OUTPUT: &str = "output";
+
+/// Detector with the YOLOv5 `[N, boxes, 5 + classes]` output, which MegaDetector v5 uses.
+#[derive(Debug)]
+pub struct YoloV5Detector {
+    session: Session,
+}
+
+impl YoloV5Detector {
+    /// Loads the model with the given session options, the model takes its images from the
+    /// `images` input and gives its boxes on the `output` output. The inputs and outputs of the
+    /// model are checked against this layout, so a wrong model fails here instead of on its first
+    /// run.
+    pub fn with_options<P>(model_path: P, options: &SessionOptions) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let session = load_session(model_path, options)?;
+
+        expect_image_input(inputs(&session), YOLOV5_INPUT)?;
+        expect_tensor(
+            "output",
+            YOLOV5_OUTPUT,
+            outputs(&session),
+            "a `[N, boxes, 5 + classes]` f32 tensor",
+            |dimensions| matches!(dimensions, [_, _, values] if dynamic_or(*values, |v| v > 5)),
+        )?;
+
+        Ok(Self { session })
+    }
+}
+
+impl Detector for YoloV5Detector {
+    fn detect(
+        &self,
+        images: Array4<f32>,
+        config: &DetectorConfig,
+    ) -> Result<Vec<Array2<f32>>, Error> {
+        let outputs = self
+            .session
+            .run(ort::inputs![YOLOV5_INPUT => Tensor::from_array(images)?]?)?;
+
+        let output = outputs[YOLOV5_OUTPUT]
+            .try_extract_tensor::<f32>()?
+            .into_dimensionality::<Ix3>()?
+            .into_owned();
+
+        non_max_suppression_with(output, config)
+    }
+}
+
+/// Detector with the anchor-free `[N, 4 + classes, boxes]` output of YOLOv8, YOLOv9 and
+/// MegaDetector v6.
+#[derive(Debug)]
+pub struct AnchorFreeDetector {
+    session: Session,
+    input_name: String,
+    output_name: String,
+}
+
+impl AnchorFreeDetector {
+    /// Loads the model with the given session options, the images go into the first input of the
+    /// model and the boxes are read from its first output.
+    pub fn with_options<P>(model_path: P, options: &SessionOptions) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let session = load_session(model_path, options)?;
+
+        let (Some(input), Some(output)) = (session.inputs.first(), session.outputs.first()) else {
+            return Err(Error::InvalidModel(
+                "the detector model must have at least one input and one output".to_string(),
+            ));
+        };
+        let (input_name, output_name) = (input.name.clone(), output.name.clone());
+
+        expect_image_input(inputs(&session), &input_name)?;
+        expect_tensor(
+            "output",
+            &output_name,
+            outputs(&session),
+            "a `[N, 4 + classes, boxes]` f32 tensor",
+            |dimensions| matches!(dimensions, [_, values, _] if dynamic_or(*values, |v| v > 4)),
+        )?;
+
+        Ok(Self {
+            session,
+            input_name,
+            output_name,
+        })
+    }
+}
+
+impl Detector for AnchorFreeDetector {
+    fn detect(
+        &self,
+        images: Array4<f32>,
+        config: &DetectorConfig,
+    ) -> Result<Vec<Array2<f32>>, Error> {
+        let outputs = self.session.run(ort::inputs![
+            self.input_name.as_str() => Tensor::from_array(images)?
+        ]?)?;
+
+        let output = outputs[self.output_name.as_str()]
+            .try_extract_tensor::<f32>()?
+            .into_dimensionality::<Ix3>()?
+            .into_owned();
+
+        non_max_suppression_anchor_free(output, config)
+    }
+}
+
+/// The output layouts of the detectors which can be loaded from an ONNX file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DetectorKind {
+    /// See [`YoloV5Detector`].
+    #[default]
+    YoloV5,
+    /// See [`AnchorFreeDetector`].
+    AnchorFree,
+}
+
+impl DetectorKind {
+    /// Loads the ONNX model of this kind with the given session options.
+    pub fn load<P>(
+        &self,
+        model_path: P,
+        options: &SessionOptions,
+    ) -> Result<Arc<dyn Detector>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        info!("Loading the {:?} detector.", self);
+
+        Ok(match self {
+            Self::YoloV5 => Arc::new(YoloV5Detector::with_options(model_path, options)?),
+            Self::AnchorFree => Arc::new(AnchorFreeDetector::with_options(model_path, options)?),
+        })
+    }
+}
+
+/// Returns the names and types of the inputs of the model.
+fn inputs(session: &Session) -> impl Iterator<Item = (&str, &ValueType)> {
+    session
+        .inputs
+        .iter()
+        .map(|input| (input.name.as_str(), &input.input_type))
+}
+
+/// Returns the names and types of the outputs of the model.
+fn outputs(session: &Session) -> impl Iterator<Item = (&str, &ValueType)> {
+    session
+        .outputs
+        .iter()
+        .map(|output| (output.name.as_str(), &output.output_type))
+}
+
+/// Checks that the model takes its images as a `[N, 3, H, W]` tensor from the given input.
+fn expect_image_input<'a>(
+    inputs: impl Iterator<Item = (&'a str, &'a ValueType)>,
+    name: &str,
+) -> Result<(), Error> {
+    expect_tensor(
+        "input",
+        name,
+        inputs,
+        "a `[N, 3, H, W]` f32 tensor",
+        |dimensions| matches!(dimensions, [_, channels, _, _] if dynamic_or(*channels, |c| c == 3)),
+    )?;
+
+    Ok(())
+}
+
+/// Returns whether the dimension is dynamic, which onnxruntime reports as `-1`, or passes the
+/// check.
+fn dynamic_or(dimension: i64, check: impl Fn(i64) -> bool) -> bool {
+    dimension < 0 || check(dimension)
+}
+
+/// Builds the onnxruntime session of the model with the given session options.
+fn load_session<P>(model_path: P, options: &SessionOptions) -> Result<Session, Error>
+where
+    P: AsRef<Path>,
+{
+    Ok(Session::builder()?
+        .with_optimization_level(graph_optimization_level(options.optimization_level()))?
+        .with_intra_threads(options.intra_threads())?
+        .commit_from_file(model_path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use ort::tensor::TensorElementType;
+
+    use super::*;
+
+    fn tensor(ty: TensorElementType, dimensions: &[i64]) -> ValueType {
+        ValueType::Tensor {
+            ty,
+            dimensions: dimensions.to_vec(),
+            dimension_symbols: vec![None; dimensions.len()],
+        }
+    }
+
+    #[test]
+    fn expect_image_input_accepts_dynamic_batches() {
+        let images = tensor(TensorElementType::Float32, &[-1, 3, 640, 640]);
+
+        assert!(expect_image_input([("images", &images)].into_iter(), "images").is_ok());
+    }
+
+    #[test]
+    fn expect_tensor_describes_the_mismatch() {
+        let images = tensor(TensorElementType::Float32, &[1, 640, 640, 3]);
+        let output = tensor(TensorElementType::Int64, &[1, 25200, 8]);
+
+        let error = expect_image_input([("images", &images)].into_iter(), "input").unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::MissingTensor { name, found, .. } if name == "input" && found == &["images"]
+        ));
+
+        let error = expect_image_input([("images", &images)].into_iter(), "images").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected input `images` of the detector model to be a `[N, 3, H, W]` f32 tensor, \
+             model has Tensor<f32>(1, 640, 640, 3)"
+        );
+
+        let error = expect_tensor(
+            "output",
+            "output",
+            [("output", &output)].into_iter(),
+            "a `[N, boxes, 5 + classes]` f32 tensor",
+            |_| true,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            Error::from(error),
+            Error::UnexpectedTensor { kind: "output", .. }
+        ));
+    }
+}
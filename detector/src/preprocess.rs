@@ -1,8 +1,8 @@
 use std::path::{Path, PathBuf};
 
 use image::{
-    DynamicImage, GenericImageView, Rgb, RgbImage,
-    imageops::{FilterType, replace},
+    Rgb, RgbImage,
+    imageops::{FilterType, replace, resize},
 };
 use ndarray::Array4;
 use speciesnet_core::{load_image, shape::Shape};
@@ -47,28 +47,41 @@ impl PreprocessedImage {
         self.inner.resized_size
     }
 
-    fn fill_array_with_image_content(array: &mut Array4<f32>, image: &RgbImage) {
+    /// Fills the image content into the given batch index of the `[N, 3, H, W]` tensor.
+    fn fill_array_with_image_content(array: &mut Array4<f32>, index: usize, image: &RgbImage) {
         for pixel in image.enumerate_pixels() {
             let x = pixel.0 as _;
             let y = pixel.1 as _;
             let [r, g, b] = pixel.2.0;
-            array[[0, 0, y, x]] = (r as f32) / 255.;
-            array[[0, 1, y, x]] = (g as f32) / 255.;
-            array[[0, 2, y, x]] = (b as f32) / 255.;
+            array[[index, 0, y, x]] = (r as f32) / 255.;
+            array[[index, 1, y, x]] = (g as f32) / 255.;
+            array[[index, 2, y, x]] = (b as f32) / 255.;
         }
     }
 
     pub fn into_tensor(self) -> Array4<f32> {
-        let inner_image = self.inner.image;
+        Self::batch_tensor(&[&self])
+    }
 
-        let mut tensor: Array4<f32> = Array4::zeros([
-            1usize,
-            3usize,
-            inner_image.height() as usize,
-            inner_image.width() as usize,
-        ]);
+    /// Stacks the given images into one `[N, 3, H, W]` tensor, the images must all have the same
+    /// dimensions as the first image.
+    ///
+    /// # Panics
+    ///
+    /// The function panics when the images have different dimensions.
+    pub fn batch_tensor(images: &[&PreprocessedImage]) -> Array4<f32> {
+        let (width, height) = images
+            .first()
+            .map(|image| image.image().dimensions())
+            .unwrap_or_default();
+
+        let mut tensor: Array4<f32> =
+            Array4::zeros([images.len(), 3usize, height as usize, width as usize]);
+
+        for (index, image) in images.iter().enumerate() {
+            Self::fill_array_with_image_content(&mut tensor, index, image.image());
+        }
 
-        Self::fill_array_with_image_content(&mut tensor, &inner_image);
         tensor
     }
 }
@@ -213,18 +226,20 @@ where
         .build();
 
     info!("Resizing and letterboxing the image.");
-    let preprocessed_image = letterbox(loaded_image.into(), options)?;
+    let preprocessed_image = letterbox(&loaded_image, options)?;
 
     Ok(PreprocessedImage::new(preprocessed_image, image_path))
 }
 
 /// Resize an image while meeting stride-multiple constraints.
+///
+/// The input image is only borrowed, so the decoded image can still be used after letterboxing
+/// without cloning it.
 pub fn letterbox(
-    input_image: DynamicImage, // TODO: Change to RgbImage
+    input_image: &RgbImage,
     options: LetterboxOptions,
 ) -> Result<PreprocessedImageInner, Error> {
     let input_image_dimensions = input_image.dimensions();
-    let mut input_image = input_image;
 
     debug!("im.shape shape is {:?}", input_image.dimensions());
 
@@ -275,14 +290,20 @@ pub fn letterbox(
     padded.0 /= 2.0;
     padded.1 /= 2.0;
 
-    if input_image.dimensions().0 != new_unpad.0.round() as u32
+    let resized_image = if input_image.dimensions().0 != new_unpad.0.round() as u32
         || input_image.dimensions().1 != new_unpad.1.round() as u32
     {
         debug!("The image needs a resize.");
 
-        input_image =
-            input_image.resize_exact(new_unpad.0 as u32, new_unpad.1 as u32, FilterType::Triangle);
-    }
+        Some(resize(
+            input_image,
+            new_unpad.0 as u32,
+            new_unpad.1 as u32,
+            FilterType::Triangle,
+        ))
+    } else {
+        None
+    };
 
     debug!("Calculating the border to patch the input image.");
     let (top, left, bottom, right): (u32, u32, u32, u32) = (
@@ -295,7 +316,7 @@ pub fn letterbox(
     if top == 0 && left == 0 && bottom == 0 && right == 0 {
         debug!("The image does not need resizing anymore, returning the image.");
         let preprocessed_image = PreprocessedImageInner::new(
-            input_image.into(),
+            resized_image.unwrap_or_else(|| input_image.clone()),
             input_image_dimensions,
             (new_unpad.0 as u32, new_unpad.1 as u32),
         );
@@ -324,7 +345,7 @@ pub fn letterbox(
     {
         replace(
             &mut blank_image,
-            &input_image.to_rgb8(),
+            resized_image.as_ref().unwrap_or(input_image),
             left.into(),
             top.into(),
         );
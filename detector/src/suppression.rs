@@ -0,0 +1,294 @@
+use ndarray::{Array2, ArrayView1, ArrayView2, Axis};
+use tracing::debug;
+
+use crate::{
+    config::DetectorConfig,
+    fusion::weighted_box_fusion,
+    torchvision::{iou, nms},
+};
+
+/// Default sigma of the Gaussian Soft-NMS, the same as the paper.
+pub const DEFAULT_SOFT_NMS_SIGMA: f32 = 0.5;
+
+/// How the overlapping boxes of an image are suppressed after the confidence filtering.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Suppression {
+    /// Greedy non-max suppression, the same as YOLOv5 and torchvision. The boxes overlapping a more
+    /// confident box by more than the IoU threshold are removed.
+    #[default]
+    Nms,
+    /// Soft-NMS, see [`soft_nms`]. The boxes which overlap a more confident box get their
+    /// confidence decayed instead of being removed, so the animals standing close together are
+    /// less likely to be merged into one.
+    SoftNms(SoftNmsDecay),
+    /// Weighted Boxes Fusion, see [`weighted_box_fusion`].
+    WeightedBoxFusion,
+}
+
+/// How Soft-NMS decays the confidence of a box overlapping a more confident box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoftNmsDecay {
+    /// Multiplies the confidence by `1 - IoU` when the IoU is above the IoU threshold.
+    Linear,
+    /// Multiplies the confidence by `exp(-IoU² / sigma)` regardless of the IoU threshold.
+    Gaussian { sigma: f32 },
+}
+
+impl Default for SoftNmsDecay {
+    fn default() -> Self {
+        Self::Gaussian {
+            sigma: DEFAULT_SOFT_NMS_SIGMA,
+        }
+    }
+}
+
+impl Suppression {
+    /// Returns the reason when the parameters of the suppression are out of their range.
+    pub(crate) fn invalid_reason(&self) -> Option<String> {
+        match self {
+            Self::SoftNms(SoftNmsDecay::Gaussian { sigma })
+                if !sigma.is_finite() || *sigma <= 0.0 =>
+            {
+                Some(format!(
+                    "the Soft-NMS `sigma` must be greater than 0, but found {sigma}"
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Runs [Soft-NMS] on the `(x1, y1, x2, y2)` boxes and their scores.
+///
+/// The most confident box is kept and the scores of the other boxes are decayed by how much they
+/// overlap it, over and over until no box is left. Boxes whose decayed score is not above
+/// `score_threshold` are dropped. Returns the index of each kept box along with its decayed
+/// score, from the most confident one.
+///
+/// [Soft-NMS]: https://arxiv.org/abs/1704.04503
+pub fn soft_nms(
+    detections: ArrayView2<f32>,
+    scores: ArrayView1<f32>,
+    decay: SoftNmsDecay,
+    iou_threshold: f32,
+    score_threshold: f32,
+) -> Vec<(usize, f32)> {
+    let boxes: Vec<[f32; 4]> = detections
+        .rows()
+        .into_iter()
+        .map(|row| [row[0], row[1], row[2], row[3]])
+        .collect();
+    let mut scores: Vec<f32> = scores.to_vec();
+
+    let mut remaining: Vec<usize> = (0..scores.len())
+        .filter(|&i| scores[i] > score_threshold)
+        .collect();
+    let mut keep: Vec<(usize, f32)> = Vec::new();
+
+    while let Some((position, _)) = remaining
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| scores[**a].total_cmp(&scores[**b]).then(b.cmp(a)))
+    {
+        let i = remaining.swap_remove(position);
+        keep.push((i, scores[i]));
+
+        for &j in &remaining {
+            let iou = iou(&boxes[i], &boxes[j]);
+
+            scores[j] *= match decay {
+                SoftNmsDecay::Linear if iou > iou_threshold => 1.0 - iou,
+                SoftNmsDecay::Linear => 1.0,
+                SoftNmsDecay::Gaussian { sigma } => (-(iou * iou) / sigma).exp(),
+            };
+        }
+
+        remaining.retain(|&j| scores[j] > score_threshold);
+    }
+
+    debug!("soft-nms kept {} of {} boxes", keep.len(), boxes.len());
+
+    keep
+}
+
+/// Suppresses the overlapping `(x1, y1, x2, y2, confidence, class)` detections of an image with
+/// the suppression of the config, capped at its maximum number of detections.
+///
+/// `boxes` are the coordinates the overlaps are computed on, which are offset by the class of
+/// each detection unless the suppression is class agnostic.
+pub(crate) fn suppress(
+    detections: Array2<f32>,
+    boxes: ArrayView2<f32>,
+    config: &DetectorConfig,
+) -> Array2<f32> {
+    let scores = detections.column(4);
+
+    let mut suppressed = match config.suppression {
+        Suppression::Nms => {
+            debug!("Running LibTorch's Non-max suppression.");
+            let keep = nms(boxes, scores, config.iou_threshold);
+            detections.select(Axis(0), &keep)
+        }
+        Suppression::SoftNms(decay) => {
+            debug!("Running Soft-NMS.");
+            let keep = soft_nms(
+                boxes,
+                scores,
+                decay,
+                config.iou_threshold,
+                config.confidence_threshold,
+            );
+
+            let indexes: Vec<usize> = keep.iter().map(|(index, _)| *index).collect();
+            let mut kept = detections.select(Axis(0), &indexes);
+
+            for (mut row, (_, score)) in kept.rows_mut().into_iter().zip(keep) {
+                row[4] = score;
+            }
+
+            kept
+        }
+        Suppression::WeightedBoxFusion => {
+            debug!("Running Weighted Boxes Fusion.");
+            weighted_box_fusion(
+                detections.view(),
+                config.iou_threshold,
+                config.class_agnostic,
+            )
+        }
+    };
+
+    if suppressed.nrows() > config.max_detections {
+        suppressed =
+            suppressed.select(Axis(0), &(0..config.max_detections).collect::<Vec<usize>>());
+    }
+
+    suppressed
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{Array1, array};
+
+    use super::*;
+
+    fn candidates() -> (Array2<f32>, Array1<f32>) {
+        let boxes: Array2<f32> = array![
+            [0.0, 0.0, 10.0, 10.0],
+            [1.0, 1.0, 11.0, 11.0],
+            [20.0, 20.0, 30.0, 30.0],
+        ];
+        let scores: Array1<f32> = array![0.9, 0.8, 0.7];
+
+        (boxes, scores)
+    }
+
+    #[test]
+    fn linear_soft_nms_decays_overlapping_boxes() {
+        let (boxes, scores) = candidates();
+
+        let keep = soft_nms(boxes.view(), scores.view(), SoftNmsDecay::Linear, 0.5, 0.01);
+
+        // The IoU of the boxes 0 and 1 is 81 / 119, so the box 1 goes down to 0.8 * 38 / 119.
+        let indexes: Vec<usize> = keep.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indexes, vec![0, 2, 1]);
+        assert!((keep[2].1 - 0.8 * 38.0 / 119.0).abs() < 1e-6);
+        assert_eq!(keep[1].1, 0.7);
+    }
+
+    #[test]
+    fn gaussian_soft_nms_drops_boxes_below_the_threshold() {
+        let (boxes, scores) = candidates();
+        let decay = SoftNmsDecay::Gaussian { sigma: 0.5 };
+
+        let keep = soft_nms(boxes.view(), scores.view(), decay, 0.5, 0.01);
+        let expected = 0.8 * (-(81.0f32 / 119.0).powi(2) / 0.5).exp();
+
+        assert_eq!(keep.len(), 3);
+        assert!((keep[2].1 - expected).abs() < 1e-6);
+
+        // The decayed score of the box 1 is around 0.32, under the threshold it is dropped.
+        let keep = soft_nms(boxes.view(), scores.view(), decay, 0.5, 0.4);
+
+        assert_eq!(keep.len(), 2);
+    }
+
+    // The expected indexes and scores are the output of `cpu_soft_nms_float` of `ensemble_boxes`
+    // on the same boxes, generated by `detector/scripts/nms_fixtures.py`. Rerun it and update them
+    // when changing the cases.
+
+    fn reference_candidates() -> (Array2<f32>, Array1<f32>) {
+        let boxes: Array2<f32> = array![
+            [0.0, 0.0, 10.0, 10.0],
+            [1.0, 1.0, 11.0, 11.0],
+            [2.0, 0.0, 12.0, 10.0],
+            [20.0, 20.0, 30.0, 30.0],
+            [22.0, 22.0, 31.0, 33.0],
+            [5.0, 5.0, 15.0, 15.0],
+        ];
+        let scores: Array1<f32> = array![0.9, 0.8, 0.75, 0.7, 0.65, 0.3];
+
+        (boxes, scores)
+    }
+
+    fn assert_keeps(keep: &[(usize, f32)], expected: &[(usize, f32)]) {
+        let indexes: Vec<usize> = keep.iter().map(|(index, _)| *index).collect();
+        let expected_indexes: Vec<usize> = expected.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indexes, expected_indexes);
+
+        for ((_, score), (_, expected)) in keep.iter().zip(expected) {
+            assert!(
+                (score - expected).abs() < 1e-5,
+                "expected {expected}, got {score}"
+            );
+        }
+    }
+
+    #[test]
+    fn linear_soft_nms_matches_ensemble_boxes() {
+        let (boxes, scores) = reference_candidates();
+        let decay = SoftNmsDecay::Linear;
+
+        let keep = soft_nms(boxes.view(), scores.view(), decay, 0.5, 0.01);
+        assert_keeps(
+            &keep,
+            &[
+                (0, 0.9),
+                (3, 0.7),
+                (4, 0.65),
+                (5, 0.3),
+                (1, 0.255462),
+                (2, 0.079832),
+            ],
+        );
+
+        let keep = soft_nms(boxes.view(), scores.view(), decay, 0.3, 0.2);
+        assert_keeps(
+            &keep,
+            &[(0, 0.9), (3, 0.7), (4, 0.341852), (5, 0.3), (1, 0.255462)],
+        );
+    }
+
+    #[test]
+    fn gaussian_soft_nms_matches_ensemble_boxes() {
+        let (boxes, scores) = reference_candidates();
+
+        let decay = SoftNmsDecay::Gaussian { sigma: 0.5 };
+        let keep = soft_nms(boxes.view(), scores.view(), decay, 0.5, 0.001);
+        assert_keeps(
+            &keep,
+            &[
+                (0, 0.9),
+                (3, 0.7),
+                (4, 0.414669),
+                (1, 0.316709),
+                (5, 0.261542),
+                (2, 0.11156),
+            ],
+        );
+
+        let decay = SoftNmsDecay::Gaussian { sigma: 0.1 };
+        let keep = soft_nms(boxes.view(), scores.view(), decay, 0.5, 0.3);
+        assert_keeps(&keep, &[(0, 0.9), (3, 0.7)]);
+    }
+}
@@ -0,0 +1,258 @@
+use ndarray::{Array2, Axis, s};
+
+use crate::{error::Error, fusion::weighted_box_fusion, torchvision::nms};
+
+/// Default width and height of a tile in pixels of the original image.
+pub const DEFAULT_TILE_SIZE: u32 = 1280;
+
+/// Default fraction of a tile which overlaps its neighbours.
+pub const DEFAULT_TILE_OVERLAP: f32 = 0.2;
+
+/// Default IoU above which the boxes of different tiles are merged.
+pub const DEFAULT_MERGE_IOU_THRESHOLD: f32 = 0.5;
+
+/// Default number of tiles run through the detector together.
+pub const DEFAULT_TILE_BATCH_SIZE: usize = 4;
+
+/// How the boxes found in the overlapping tiles are merged together.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TileMerge {
+    /// Keeps the most confident of the overlapping boxes.
+    #[default]
+    Nms,
+    /// Averages the overlapping boxes weighted by their confidences.
+    WeightedBoxFusion,
+}
+
+/// Options of the sliced inference, where the image is cut into overlapping tiles which are each
+/// run through the detector on their own.
+///
+/// Each tile is letterboxed like a whole image would be, so the animals which are too small to
+/// survive the downscaling of the whole image keep their details. The boxes of the tiles are then
+/// mapped back onto the image and merged with [`TileMerge`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TilingOptions {
+    /// Width and height of a tile in pixels of the original image, at least 32.
+    pub tile_size: u32,
+    /// Fraction of a tile which overlaps its neighbours, between 0 and 0.9.
+    pub overlap: f32,
+    /// How the boxes of the overlapping tiles are merged.
+    pub merge: TileMerge,
+    /// IoU above which the boxes of different tiles are merged, between 0 and 1.
+    pub merge_iou_threshold: f32,
+    /// Also runs the whole image through the detector, so the animals larger than a tile are not
+    /// cut into pieces.
+    pub include_full_image: bool,
+    /// Number of tiles run through the detector together, at least 1.
+    pub batch_size: usize,
+}
+
+impl Default for TilingOptions {
+    fn default() -> Self {
+        Self {
+            tile_size: DEFAULT_TILE_SIZE,
+            overlap: DEFAULT_TILE_OVERLAP,
+            merge: TileMerge::default(),
+            merge_iou_threshold: DEFAULT_MERGE_IOU_THRESHOLD,
+            include_full_image: true,
+            batch_size: DEFAULT_TILE_BATCH_SIZE,
+        }
+    }
+}
+
+/// A tile of the image, in pixels of the original image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TilingOptions {
+    /// Checks that every option is inside of its range.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.tile_size < 32 {
+            return Err(Error::InvalidConfig(format!(
+                "`tile_size` must be at least 32 pixels, but found {}",
+                self.tile_size
+            )));
+        }
+
+        if !(0.0..=0.9).contains(&self.overlap) {
+            return Err(Error::InvalidConfig(format!(
+                "`overlap` must be between 0 and 0.9, but found {}",
+                self.overlap
+            )));
+        }
+
+        if !(0.0..=1.0).contains(&self.merge_iou_threshold) {
+            return Err(Error::InvalidConfig(format!(
+                "`merge_iou_threshold` must be between 0 and 1, but found {}",
+                self.merge_iou_threshold
+            )));
+        }
+
+        if self.batch_size == 0 {
+            return Err(Error::InvalidConfig(
+                "`batch_size` must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Cuts an image of the given size into overlapping tiles, row by row.
+    ///
+    /// The last tile of each row and column is moved back to end on the edge of the image instead
+    /// of sticking out of it, so every tile has the same size unless the image is smaller than a
+    /// tile.
+    pub fn tiles(&self, width: u32, height: u32) -> Vec<Tile> {
+        let xs = self.tile_starts(width);
+        let ys = self.tile_starts(height);
+
+        ys.iter()
+            .flat_map(|&y| {
+                xs.iter().map(move |&x| Tile {
+                    x,
+                    y,
+                    width: self.tile_size.min(width),
+                    height: self.tile_size.min(height),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the start of each tile along one side of the image.
+    fn tile_starts(&self, length: u32) -> Vec<u32> {
+        if length <= self.tile_size {
+            return vec![0];
+        }
+
+        let stride = ((self.tile_size as f32 * (1.0 - self.overlap)).round() as u32).max(1);
+        let last = length - self.tile_size;
+
+        let mut starts: Vec<u32> = (0..last).step_by(stride as usize).collect();
+
+        // A stepped start only a few pixels short of the last one would give an almost duplicate
+        // tile, the last one replaces it as long as the tile before still reaches the last one.
+        if let [.., previous, start] = starts[..]
+            && last - start < stride / 4
+            && previous + self.tile_size >= last
+        {
+            starts.pop();
+        }
+
+        starts.push(last);
+        starts
+    }
+
+    /// Merges the `(x1, y1, x2, y2, confidence, class)` boxes of all the tiles, which are already
+    /// mapped onto the original image. The merged boxes are sorted from the most confident one.
+    pub(crate) fn merge(&self, detections: Array2<f32>, class_agnostic: bool) -> Array2<f32> {
+        if detections.nrows() == 0 {
+            return detections;
+        }
+
+        match self.merge {
+            TileMerge::Nms => {
+                // Same trick as the batched NMS of YOLOv5, the boxes of each class are offset so
+                // they never overlap the boxes of the other classes.
+                let offset = if class_agnostic {
+                    0.0
+                } else {
+                    detections
+                        .slice(s![.., ..4])
+                        .fold(0.0f32, |max, &value| max.max(value))
+                        + 1.0
+                };
+                let class = detections.slice(s![.., 5..6]).mapv(|e| e * offset);
+                let boxes = &detections.slice(s![.., ..4]) + &class;
+
+                let keep = nms(boxes.view(), detections.column(4), self.merge_iou_threshold);
+                detections.select(Axis(0), &keep)
+            }
+            TileMerge::WeightedBoxFusion => {
+                weighted_box_fusion(detections.view(), self.merge_iou_threshold, class_agnostic)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn tiles_cover_the_image() {
+        let options = TilingOptions {
+            tile_size: 1000,
+            overlap: 0.2,
+            ..TilingOptions::default()
+        };
+
+        let tiles = options.tiles(2500, 900);
+        let xs: Vec<u32> = tiles.iter().map(|tile| tile.x).collect();
+
+        assert_eq!(xs, vec![0, 800, 1500]);
+        assert!(tiles.iter().all(|tile| tile.y == 0));
+        assert!(tiles.iter().all(|tile| tile.width == 1000));
+        assert!(tiles.iter().all(|tile| tile.height == 900));
+        assert_eq!(options.tiles(640, 480).len(), 1);
+    }
+
+    #[test]
+    fn tiles_do_not_nearly_duplicate_the_last_tile() {
+        let options = TilingOptions {
+            tile_size: 640,
+            overlap: 0.2,
+            ..TilingOptions::default()
+        };
+
+        // The stride is 512 and the last tile starts at 513, right after the stepped one at 512.
+        let xs: Vec<u32> = options.tiles(1153, 480).iter().map(|tile| tile.x).collect();
+        assert_eq!(xs, vec![0, 513]);
+
+        // Without overlap, the tile at 0 does not reach 513, so the tile at 512 is kept.
+        let options = TilingOptions {
+            tile_size: 512,
+            overlap: 0.0,
+            ..TilingOptions::default()
+        };
+
+        let xs: Vec<u32> = options.tiles(1025, 480).iter().map(|tile| tile.x).collect();
+        assert_eq!(xs, vec![0, 512, 513]);
+    }
+
+    #[test]
+    fn tiling_options_rejects_invalid_values() {
+        let options = TilingOptions {
+            overlap: 0.95,
+            ..TilingOptions::default()
+        };
+
+        assert!(matches!(options.validate(), Err(Error::InvalidConfig(_))));
+        assert!(TilingOptions::default().validate().is_ok());
+    }
+
+    #[test]
+    fn merge_keeps_the_most_confident_box_of_each_class() {
+        let detections: Array2<f32> = array![
+            [100.0, 100.0, 200.0, 200.0, 0.6, 0.0],
+            [102.0, 101.0, 201.0, 203.0, 0.9, 0.0],
+            [101.0, 100.0, 200.0, 201.0, 0.5, 1.0],
+        ];
+
+        let merged = TilingOptions::default().merge(detections.clone(), false);
+
+        assert_eq!(merged.nrows(), 2);
+        assert_eq!(merged[[0, 4]], 0.9);
+        assert_eq!(merged[[1, 5]], 1.0);
+
+        let merged = TilingOptions::default().merge(detections, true);
+
+        assert_eq!(merged.nrows(), 1);
+    }
+}
@@ -3,23 +3,45 @@ use tracing::debug;
 
 /// A function to perform Non-max suppression on a detection tensor and scores tensor.
 ///
-/// This function is mimicked from [LibTorch's Non-max suppression]. and the implementation is taken from
-/// [this issue comment in tch].
+/// This function follows [LibTorch's Non-max suppression] step by step, the boxes are visited from
+/// the most confident one and each kept box marks the boxes it overlaps by more than
+/// `iou_threshold` as suppressed. The areas are computed once up front and the suppressed boxes
+/// are only flagged, so an image with thousands of candidates does not pay for shifting the
+/// remaining boxes around on every suppression. Boxes with the same score keep their original
+/// order.
 ///
 /// [LibTorch's Non-max suppression]: https://github.com/pytorch/vision/blob/124dfa404f395db90280e6dd84a51c50c742d5fd/torchvision/csrc/ops/cpu/nms_kernel.cpp
-/// [this issue comment in tch]: https://github.com/laurentmazare/tch-rs/issues/833#issuecomment-1905027185
 pub fn nms(detections: ArrayView2<f32>, scores: ArrayView1<f32>, iou_threshold: f32) -> Vec<usize> {
-    let mut sorting: Vec<usize> = (0..scores.len()).collect();
-    sorting.sort_unstable_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+    let boxes: Vec<[f32; 4]> = detections
+        .rows()
+        .into_iter()
+        .map(|row| [row[0], row[1], row[2], row[3]])
+        .collect();
+    let areas: Vec<f32> = boxes.iter().map(area).collect();
 
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+
+    let mut suppressed = vec![false; order.len()];
     let mut keep: Vec<usize> = Vec::new();
 
-    while let Some(idx) = sorting.pop() {
-        keep.push(idx);
+    for (position, &i) in order.iter().enumerate() {
+        if suppressed[position] {
+            continue;
+        }
+
+        keep.push(i);
+
+        for (other, &j) in order.iter().enumerate().skip(position + 1) {
+            if suppressed[other] {
+                continue;
+            }
+
+            let intersection = intersection(&boxes[i], &boxes[j]);
+            let iou = intersection / (areas[i] + areas[j] - intersection);
 
-        for i in (0..sorting.len()).rev() {
-            if iou(detections.row(idx), detections.row(sorting[i])) > iou_threshold {
-                _ = sorting.remove(i);
+            if iou > iou_threshold {
+                suppressed[other] = true;
             }
         }
     }
@@ -29,26 +51,149 @@ pub fn nms(detections: ArrayView2<f32>, scores: ArrayView1<f32>, iou_threshold:
     keep
 }
 
-/// Performs the calculation of Intersection Over Union value that's being used in Non-max
-/// suppression function.
-///
-/// This function's implementation is taken from [this issue comment in tch]
+/// Performs the calculation of Intersection Over Union of two `(x1, y1, x2, y2)` boxes, the same
+/// way as [torchvision's box_iou].
 ///
-/// [this issue comment in tch]: https://github.com/laurentmazare/tch-rs/issues/833#issuecomment-1905027185.
-fn iou(a: ArrayView1<f32>, b: ArrayView1<f32>) -> f32 {
-    let zero: f32 = 0.0;
+/// [torchvision's box_iou]: https://github.com/pytorch/vision/blob/124dfa404f395db90280e6dd84a51c50c742d5fd/torchvision/ops/boxes.py#L271-L294
+pub fn iou(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let intersection = intersection(a, b);
+    let union = area(a) + area(b) - intersection;
+
+    if union <= 0.0 {
+        return 0.0;
+    }
+
+    intersection / union
+}
+
+fn area(bbox: &[f32; 4]) -> f32 {
+    (bbox[2] - bbox[0]) * (bbox[3] - bbox[1])
+}
+
+fn intersection(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let width = (a[2].min(b[2]) - a[0].max(b[0])).max(0.0);
+    let height = (a[3].min(b[3]) - a[1].max(b[1])).max(0.0);
+
+    width * height
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{Array1, Array2, array};
 
-    let a_area = (a[2] - a[0] + 1.0) * (a[3] - a[1] + 1.0);
-    let b_area = (b[2] - b[0] + 1.0) * (b[3] - b[1] + 1.0);
+    use super::*;
 
-    let i_x1 = a[0].max(b[0]);
-    let i_y1 = a[1].max(b[1]);
-    let i_x2 = a[2].min(b[2]);
-    let i_y2 = a[3].min(b[3]);
+    // The expected indexes are the output of `torchvision.ops.nms` and `batched_nms` on the same
+    // boxes, generated by `detector/scripts/nms_fixtures.py`. Rerun it and update the indexes when
+    // changing the cases.
 
-    let i_width = (i_x2 - i_x1 + 1.0).max(zero);
-    let i_height = (i_y2 - i_y1 + 1.0).max(zero);
-    let i_area = i_width * i_height;
+    /// Same as `torchvision.ops.batched_nms` on CPU, the boxes of each class are offset by the
+    /// largest coordinate plus 1 so the classes never overlap, before running `nms` on them all.
+    fn batched_nms(
+        boxes: &Array2<f32>,
+        scores: &Array1<f32>,
+        classes: &[usize],
+        iou_threshold: f32,
+    ) -> Vec<usize> {
+        let offset = boxes.fold(f32::MIN, |max, &e| max.max(e)) + 1.0;
+        let mut offset_boxes = boxes.clone();
 
-    i_area / (a_area + b_area - i_area)
+        for (mut row, &class) in offset_boxes.rows_mut().into_iter().zip(classes) {
+            row += class as f32 * offset;
+        }
+
+        nms(offset_boxes.view(), scores.view(), iou_threshold)
+    }
+
+    #[test]
+    fn nms_matches_torchvision() {
+        let boxes: Array2<f32> = array![
+            [0.0, 0.0, 10.0, 10.0],
+            [1.0, 1.0, 11.0, 11.0],
+            [20.0, 20.0, 30.0, 30.0],
+            [0.0, 0.0, 10.0, 5.0],
+            [21.0, 21.0, 29.0, 31.0],
+        ];
+        let scores: Array1<f32> = array![0.9, 0.8, 0.7, 0.95, 0.6];
+
+        // The IoU of the boxes 3 and 0 is exactly 0.5, which is only suppressed above it.
+        assert_eq!(nms(boxes.view(), scores.view(), 0.5), vec![3, 0, 2]);
+        assert_eq!(nms(boxes.view(), scores.view(), 0.7), vec![3, 0, 1, 2, 4]);
+        assert_eq!(nms(boxes.view(), scores.view(), 0.49), vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn nms_keeps_the_order_of_ties_as_torchvision() {
+        // All the scores are the same, the box 3 is the same as the box 0.
+        let boxes: Array2<f32> = array![
+            [0.0, 0.0, 10.0, 10.0],
+            [5.0, 0.0, 15.0, 10.0],
+            [10.0, 0.0, 20.0, 10.0],
+            [0.0, 0.0, 10.0, 10.0],
+        ];
+        let scores: Array1<f32> = array![0.8, 0.8, 0.8, 0.8];
+
+        assert_eq!(nms(boxes.view(), scores.view(), 0.3), vec![0, 2]);
+        assert_eq!(nms(boxes.view(), scores.view(), 0.5), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn nms_keeps_zero_area_boxes_as_torchvision() {
+        // The IoU of two boxes of zero area is 0 / 0, which is never above the threshold.
+        let boxes: Array2<f32> = array![
+            [0.0, 0.0, 10.0, 10.0],
+            [5.0, 5.0, 5.0, 5.0],
+            [5.0, 5.0, 5.0, 5.0],
+            [2.0, 0.0, 2.0, 10.0],
+            [0.0, 0.0, 10.0, 10.0],
+        ];
+        let scores: Array1<f32> = array![0.9, 0.8, 0.7, 0.6, 0.5];
+
+        assert_eq!(nms(boxes.view(), scores.view(), 0.0), vec![0, 1, 2, 3]);
+        assert_eq!(nms(boxes.view(), scores.view(), 0.5), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn batched_nms_matches_torchvision() {
+        let boxes: Array2<f32> = array![
+            [0.0, 0.0, 10.0, 10.0],
+            [1.0, 1.0, 11.0, 11.0],
+            [1.0, 1.0, 11.0, 11.0],
+            [0.0, 0.0, 10.0, 10.0],
+            [20.0, 20.0, 30.0, 30.0],
+        ];
+        let scores: Array1<f32> = array![0.9, 0.8, 0.7, 0.9, 0.6];
+        let classes = [0, 1, 0, 1, 0];
+
+        assert_eq!(batched_nms(&boxes, &scores, &classes, 0.5), vec![0, 3, 4]);
+        assert_eq!(
+            batched_nms(&boxes, &scores, &classes, 0.7),
+            vec![0, 3, 1, 2, 4]
+        );
+    }
+
+    #[test]
+    fn nms_touching_boxes_do_not_overlap() {
+        // torchvision does not add a pixel to the width and height of the boxes, so boxes which
+        // only share an edge have an IoU of 0.
+        let boxes: Array2<f32> = array![[0.0, 0.0, 1.0, 1.0], [1.0, 0.0, 2.0, 1.0]];
+        let scores: Array1<f32> = array![0.9, 0.8];
+
+        assert_eq!(nms(boxes.view(), scores.view(), 0.0), vec![0, 1]);
+        assert_eq!(iou(&[0.0, 0.0, 1.0, 1.0], &[1.0, 0.0, 2.0, 1.0]), 0.0);
+    }
+
+    #[test]
+    fn nms_scales_to_many_candidates() {
+        // A grid of 5000 disjoint boxes, none of them is suppressed.
+        let boxes = Array2::from_shape_fn((5000, 4), |(i, j)| {
+            let (x, y) = ((i % 100) as f32 * 10.0, (i / 100) as f32 * 10.0);
+            [x, y, x + 5.0, y + 5.0][j]
+        });
+        let scores = Array1::from_shape_fn(5000, |i| 1.0 - i as f32 / 5000.0);
+
+        let keep = nms(boxes.view(), scores.view(), 0.45);
+
+        assert_eq!(keep, (0..5000).collect::<Vec<usize>>());
+    }
 }
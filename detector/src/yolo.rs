@@ -3,12 +3,10 @@ use core::f32;
 use ndarray::{Array2, Array3, ArrayView2, Axis, array, concatenate, s, stack};
 use tracing::{debug, info};
 
-use crate::{error::Error, torchvision::nms};
+use crate::{config::DetectorConfig, error::Error, suppression::suppress};
 
 const DEFAULT_CONF_THRESHOLD: f32 = 0.25;
 
-const IOU_THRESHOLD: f32 = 0.45;
-const MAX_DETECTIONS: i32 = 300;
 const NUMBER_OF_MASKS: i32 = 0;
 
 const MAX_BOUNDING_BOX_HEIGHT: i32 = 7680;
@@ -32,38 +30,77 @@ pub fn xywh_to_xyxy(tensor: ArrayView2<f32>) -> Result<Array2<f32>, Error> {
     )?)
 }
 
+/// Runs non-max suppression on the raw `[N, boxes, 5 + classes]` output of the detector, returns
+/// the filtered `(x1, y1, x2, y2, confidence, class)` detections of each image in the batch.
+///
+/// The rest of the options are the defaults of [`DetectorConfig`], a confidence threshold outside
+/// of `0.0..=1.0` is rejected.
 pub fn non_max_suppression(
     predictions: Array3<f32>,
     conf_threshold: Option<f32>,
-) -> Result<Array2<f32>, Error> {
-    let conf_threshold = conf_threshold.map_or(DEFAULT_CONF_THRESHOLD, |v| {
-        if (0.0..1.0).contains(&v) {
-            v
-        } else {
-            DEFAULT_CONF_THRESHOLD
-        }
-    });
+) -> Result<Vec<Array2<f32>>, Error> {
+    let config = DetectorConfig {
+        confidence_threshold: conf_threshold.unwrap_or(DEFAULT_CONF_THRESHOLD),
+        ..DetectorConfig::default()
+    };
+
+    non_max_suppression_with(predictions, &config)
+}
+
+/// Same as [`non_max_suppression`], with the thresholds, the maximum number of detections, the
+/// suppression and whether it is class agnostic taken from the given config.
+pub fn non_max_suppression_with(
+    predictions: Array3<f32>,
+    config: &DetectorConfig,
+) -> Result<Vec<Array2<f32>>, Error> {
+    config.validate()?;
 
     // checks
     let shapes = predictions.shape();
     let batch_size = shapes.first().unwrap();
     let number_of_classes = *shapes.get(2).unwrap() - (NUMBER_OF_MASKS as usize) - 5;
-    let candidates = predictions
-        .slice(s![.., .., 4])
-        .mapv(|e| e > conf_threshold);
 
     debug!("output ndarray's shape: {:?}", shapes);
     debug!("batch size: {}", batch_size,);
     debug!("number of classes: {}", number_of_classes);
 
-    // There's only 1 batch size, until we can implement batch size we can change it back to for
-    // loop.
-    let view = predictions.index_axis(Axis(0), 0);
-    let indices = candidates
-        .clone()
-        .remove_axis(Axis(0))
+    predictions
+        .outer_iter()
+        .map(|view| non_max_suppression_single(view, config))
+        .collect()
+}
+
+/// Runs non-max suppression on the raw `[N, 4 + classes, boxes]` output of an anchor-free
+/// detector such as YOLOv8, YOLOv9 or MegaDetector v6, returns the filtered
+/// `(x1, y1, x2, y2, confidence, class)` detections of each image in the batch.
+///
+/// Unlike YOLOv5, the boxes are laid out along the last axis and there is no objectness, the
+/// confidence of a box is its highest class score.
+pub fn non_max_suppression_anchor_free(
+    predictions: Array3<f32>,
+    config: &DetectorConfig,
+) -> Result<Vec<Array2<f32>>, Error> {
+    config.validate()?;
+
+    debug!("output ndarray's shape: {:?}", predictions.shape());
+
+    predictions
+        .outer_iter()
+        .map(|view| anchor_free_single(view.t(), config))
+        .collect()
+}
+
+/// Runs non-max suppression on the output of a single image of the batch.
+fn non_max_suppression_single(
+    view: ArrayView2<f32>,
+    config: &DetectorConfig,
+) -> Result<Array2<f32>, Error> {
+    let conf_threshold = config.confidence_threshold;
+
+    let indices = view
+        .slice(s![.., 4])
         .indexed_iter()
-        .filter_map(|(i, val)| if *val { Some(i) } else { None })
+        .filter_map(|(i, val)| if *val > conf_threshold { Some(i) } else { None })
         .collect::<Vec<usize>>();
 
     debug!("Filtering the tensor with candidates tensor by minimum confidence threshold.");
@@ -80,19 +117,42 @@ pub fn non_max_suppression(
 
     let object_conf = &object_conf * &class_conf;
 
-    // Reconstructing the whole 8 columns from
-    // (first original 5 columns, last 3 columns that gets multiplied).
+    // Reconstructing the whole columns from
+    // (first original 5 columns, class columns that gets multiplied).
     let tensor = concatenate(Axis(1), &[tensor.slice(s![.., ..5]), object_conf.view()])?;
 
     let bbox = xywh_to_xyxy(tensor.view())?;
-    let conf_flat = tensor.slice(s![.., 5..8]).map_axis(Axis(1), |m| {
+
+    candidates(bbox, tensor.slice(s![.., 5..]), config)
+}
+
+/// Runs non-max suppression on the `[boxes, 4 + classes]` output of a single image of an
+/// anchor-free detector.
+fn anchor_free_single(
+    view: ArrayView2<f32>,
+    config: &DetectorConfig,
+) -> Result<Array2<f32>, Error> {
+    let bbox = xywh_to_xyxy(view.slice(s![.., ..4]))?;
+
+    candidates(bbox, view.slice(s![.., 4..]), config)
+}
+
+/// Picks the best class of each `(x1, y1, x2, y2)` box from its class scores, then suppresses the
+/// boxes which are confident enough with the suppression of the config.
+fn candidates(
+    bbox: Array2<f32>,
+    class_scores: ArrayView2<f32>,
+    config: &DetectorConfig,
+) -> Result<Array2<f32>, Error> {
+    let conf_threshold = config.confidence_threshold;
+
+    let conf_flat = class_scores.map_axis(Axis(1), |m| {
         *m.iter().max_by(|a, b| a.total_cmp(b)).unwrap()
     });
 
     let conf = conf_flat.clone().insert_axis(Axis(1));
 
-    let j = tensor
-        .slice(s![.., 5..8])
+    let j = class_scores
         .map_axis(Axis(1), |m| {
             m.iter()
                 .enumerate()
@@ -125,21 +185,18 @@ pub fn non_max_suppression(
     confidence_argsort.truncate(MAX_NMS_BOXES as usize);
     let tensor = tensor.select(Axis(0), &confidence_argsort);
 
-    // Batched NMS
-    let class = tensor
-        .slice(s![.., 5..6])
-        .mapv(|e| e * MAX_BOUNDING_BOX_HEIGHT as f32);
+    // Batched NMS, the boxes of each class are offset so they never overlap the other classes,
+    // unless the suppression is class agnostic.
+    let offset = if config.class_agnostic {
+        0.0
+    } else {
+        MAX_BOUNDING_BOX_HEIGHT as f32
+    };
+    let class = tensor.slice(s![.., 5..6]).mapv(|e| e * offset);
 
     let boxes = &tensor.slice(s![.., ..4]) + &class;
-    let scores = tensor.column(4);
 
-    debug!("Running LibTorch's Non-max suppression.");
-
-    let mut nms_indexes = nms(boxes.view(), scores, IOU_THRESHOLD);
-    nms_indexes.truncate(MAX_DETECTIONS as usize);
-
-    let filtered_results = tensor.select(Axis(0), &nms_indexes);
-    Ok(filtered_results)
+    Ok(suppress(tensor, boxes.view(), config))
 }
 
 #[cfg(test)]
@@ -157,4 +214,118 @@ mod tests {
         assert_eq!(xyxy_tensor[[0, 2]], 13f32);
         assert_eq!(xyxy_tensor[[0, 3]], 14f32);
     }
+
+    #[test]
+    fn non_max_suppression_per_batch_element() {
+        // Two images with two boxes each, in (cx, cy, w, h, objectness, animal, person, vehicle).
+        let predictions: Array3<f32> = array![
+            [
+                [50.0, 50.0, 20.0, 20.0, 0.9, 0.9, 0.05, 0.05],
+                [51.0, 51.0, 20.0, 20.0, 0.8, 0.9, 0.05, 0.05],
+            ],
+            [
+                [10.0, 10.0, 4.0, 4.0, 0.001, 0.9, 0.05, 0.05],
+                [90.0, 90.0, 10.0, 10.0, 0.002, 0.05, 0.9, 0.05],
+            ],
+        ];
+
+        let results = non_max_suppression(predictions, Some(0.01)).unwrap();
+
+        assert_eq!(results.len(), 2);
+
+        // The overlapping boxes of the first image are suppressed into the most confident one.
+        assert_eq!(results[0].nrows(), 1);
+        assert_eq!(results[0][[0, 0]], 40.0);
+        assert_eq!(results[0][[0, 1]], 40.0);
+        assert_eq!(results[0][[0, 2]], 60.0);
+        assert_eq!(results[0][[0, 3]], 60.0);
+        assert_eq!(results[0][[0, 5]], 0.0);
+
+        // Every box of the second image is below the confidence threshold.
+        assert!(results[1].is_empty());
+    }
+
+    #[test]
+    fn non_max_suppression_class_agnostic() {
+        // Two overlapping boxes of different classes.
+        let predictions: Array3<f32> = array![[
+            [50.0, 50.0, 20.0, 20.0, 0.9, 0.9, 0.05, 0.05],
+            [51.0, 51.0, 20.0, 20.0, 0.8, 0.05, 0.9, 0.05],
+        ]];
+
+        let per_class =
+            non_max_suppression_with(predictions.clone(), &DetectorConfig::default()).unwrap();
+        assert_eq!(per_class[0].nrows(), 2);
+
+        let config = DetectorConfig {
+            class_agnostic: true,
+            ..DetectorConfig::default()
+        };
+        let agnostic = non_max_suppression_with(predictions.clone(), &config).unwrap();
+        assert_eq!(agnostic[0].nrows(), 1);
+        assert_eq!(agnostic[0][[0, 5]], 0.0);
+
+        let config = DetectorConfig {
+            max_detections: 1,
+            ..DetectorConfig::default()
+        };
+        let capped = non_max_suppression_with(predictions, &config).unwrap();
+        assert_eq!(capped[0].nrows(), 1);
+    }
+
+    #[test]
+    fn non_max_suppression_anchor_free_layout() {
+        // The same boxes as `non_max_suppression_per_batch_element`, laid out along the last axis
+        // in (cx, cy, w, h, animal, person, vehicle) without any objectness.
+        let predictions: Array3<f32> = array![[
+            [50.0, 51.0, 90.0],
+            [50.0, 51.0, 90.0],
+            [20.0, 20.0, 10.0],
+            [20.0, 20.0, 10.0],
+            [0.8, 0.7, 0.05],
+            [0.05, 0.05, 0.6],
+            [0.05, 0.05, 0.05],
+        ]];
+
+        let results =
+            non_max_suppression_anchor_free(predictions, &DetectorConfig::default()).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].nrows(), 2);
+        assert_eq!(
+            results[0].row(0).to_vec(),
+            vec![40.0, 40.0, 60.0, 60.0, 0.8, 0.0]
+        );
+        assert_eq!(
+            results[0].row(1).to_vec(),
+            vec![85.0, 85.0, 95.0, 95.0, 0.6, 1.0]
+        );
+    }
+
+    #[test]
+    fn non_max_suppression_rejects_invalid_config() {
+        let predictions: Array3<f32> = array![[[50.0, 50.0, 20.0, 20.0, 0.9, 0.9, 0.05, 0.05]]];
+
+        assert!(non_max_suppression(predictions.clone(), Some(1.5)).is_err());
+
+        for config in [
+            DetectorConfig {
+                iou_threshold: -0.1,
+                ..DetectorConfig::default()
+            },
+            DetectorConfig {
+                max_detections: 0,
+                ..DetectorConfig::default()
+            },
+            DetectorConfig {
+                min_box_area: f64::NAN,
+                ..DetectorConfig::default()
+            },
+        ] {
+            assert!(matches!(
+                non_max_suppression_with(predictions.clone(), &config),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+    }
 }
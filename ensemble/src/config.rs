@@ -0,0 +1,192 @@
+use std::{fs::read_to_string, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+#[cfg(test)]
+mod tests;
+
+/// Taxonomy levels which the labels can be rolled up to.
+const ROLLUP_LEVELS: [&str; 6] = ["species", "genus", "family", "order", "class", "kingdom"];
+
+/// Decision thresholds of the ensemble.
+///
+/// Every field is optional when loading the config from a file, the missing fields take the
+/// default values which are the same as the thresholds of the original cameratrapai ensemble.
+///
+/// ```toml
+/// human_detection_threshold = 0.8
+/// rollup_levels = ["genus", "family"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnsembleConfig {
+    /// Threshold #1a, minimum score of a HUMAN detection to be labeled as human.
+    pub human_detection_threshold: f64,
+    /// Threshold #1b, minimum score of a HUMAN detection to be labeled as human by a HUMAN or
+    /// VEHICLE classification.
+    pub human_mid_detection_threshold: f64,
+    /// Threshold #1b and #2a, minimum score of a HUMAN classification to label the image as
+    /// human.
+    pub human_classification_threshold: f64,
+    /// Threshold #2b, minimum score of a VEHICLE detection to be labeled as vehicle.
+    pub vehicle_detection_threshold: f64,
+    /// Threshold #2a and #2c, minimum score of a VEHICLE detection to be labeled by the
+    /// classification.
+    pub vehicle_mid_detection_threshold: f64,
+    /// Threshold #2c, minimum score of a VEHICLE classification to label the image as vehicle.
+    pub vehicle_classification_threshold: f64,
+    /// Threshold #3a, the top detection score has to be below this for a BLANK classification.
+    pub blank_detection_threshold: f64,
+    /// Threshold #3a, minimum score of a BLANK classification when the detections are blank.
+    pub blank_classification_threshold: f64,
+    /// Threshold #3b, minimum score of a BLANK classification regardless of the detections.
+    pub blank_high_classification_threshold: f64,
+    /// Threshold #4a, minimum score of an ANIMAL classification to be used regardless of the
+    /// detections.
+    pub animal_high_classification_threshold: f64,
+    /// Threshold #4b, minimum score of an ANIMAL classification when there is an ANIMAL
+    /// detection.
+    pub animal_classification_threshold: f64,
+    /// Threshold #4b, minimum score of the ANIMAL detection.
+    pub animal_detection_threshold: f64,
+    /// Threshold #5a, minimum rolled up score of a taxonomy level.
+    pub rollup_threshold: f64,
+    /// Threshold #5a, taxonomy levels to roll the labels up to, in order.
+    pub rollup_levels: Vec<String>,
+    /// Threshold #5b, minimum score of an ANIMAL detection to be labeled as animal.
+    pub animal_detector_fallback_threshold: f64,
+}
+
+impl Default for EnsembleConfig {
+    fn default() -> Self {
+        Self {
+            human_detection_threshold: 0.7,
+            human_mid_detection_threshold: 0.2,
+            human_classification_threshold: 0.5,
+            vehicle_detection_threshold: 0.7,
+            vehicle_mid_detection_threshold: 0.2,
+            vehicle_classification_threshold: 0.4,
+            blank_detection_threshold: 0.2,
+            blank_classification_threshold: 0.5,
+            blank_high_classification_threshold: 0.99,
+            animal_high_classification_threshold: 0.8,
+            animal_classification_threshold: 0.65,
+            animal_detection_threshold: 0.2,
+            rollup_threshold: 0.65,
+            rollup_levels: vec![
+                "genus".to_string(),
+                "family".to_string(),
+                "order".to_string(),
+                "class".to_string(),
+                "kingdom".to_string(),
+            ],
+            animal_detector_fallback_threshold: 0.5,
+        }
+    }
+}
+
+impl EnsembleConfig {
+    /// Loads the config from a JSON string.
+    pub fn from_json_str(json: &str) -> Result<Self, Error> {
+        let config: Self = serde_json::from_str(json)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads the config from a TOML string.
+    pub fn from_toml_str(toml: &str) -> Result<Self, Error> {
+        let config: Self = toml::from_str(toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads the config from a `.json` or `.toml` file, picked by the extension of the file.
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let content = read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("json") => Self::from_json_str(&content),
+            Some(e) if e.eq_ignore_ascii_case("toml") => Self::from_toml_str(&content),
+            _ => Err(Error::InvalidConfig(format!(
+                "expected a `.json` or `.toml` file, but found {}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Checks that every threshold is between 0 and 1, and every rollup level is a valid taxonomy
+    /// level.
+    pub fn validate(&self) -> Result<(), Error> {
+        let thresholds = [
+            ("human_detection_threshold", self.human_detection_threshold),
+            (
+                "human_mid_detection_threshold",
+                self.human_mid_detection_threshold,
+            ),
+            (
+                "human_classification_threshold",
+                self.human_classification_threshold,
+            ),
+            (
+                "vehicle_detection_threshold",
+                self.vehicle_detection_threshold,
+            ),
+            (
+                "vehicle_mid_detection_threshold",
+                self.vehicle_mid_detection_threshold,
+            ),
+            (
+                "vehicle_classification_threshold",
+                self.vehicle_classification_threshold,
+            ),
+            ("blank_detection_threshold", self.blank_detection_threshold),
+            (
+                "blank_classification_threshold",
+                self.blank_classification_threshold,
+            ),
+            (
+                "blank_high_classification_threshold",
+                self.blank_high_classification_threshold,
+            ),
+            (
+                "animal_high_classification_threshold",
+                self.animal_high_classification_threshold,
+            ),
+            (
+                "animal_classification_threshold",
+                self.animal_classification_threshold,
+            ),
+            (
+                "animal_detection_threshold",
+                self.animal_detection_threshold,
+            ),
+            ("rollup_threshold", self.rollup_threshold),
+            (
+                "animal_detector_fallback_threshold",
+                self.animal_detector_fallback_threshold,
+            ),
+        ];
+
+        for (name, threshold) in thresholds {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err(Error::InvalidConfig(format!(
+                    "`{name}` must be between 0 and 1, but found {threshold}"
+                )));
+            }
+        }
+
+        for level in &self.rollup_levels {
+            if !ROLLUP_LEVELS.contains(&level.as_str()) {
+                return Err(Error::InvalidTaxonomyLevel(level.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
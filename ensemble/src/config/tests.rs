@@ -0,0 +1,94 @@
+use super::EnsembleConfig;
+use crate::error::Error;
+
+#[test]
+fn test_default_config_fn() {
+    let config = EnsembleConfig::default();
+
+    assert_eq!(config.human_detection_threshold, 0.7);
+    assert_eq!(config.human_mid_detection_threshold, 0.2);
+    assert_eq!(config.human_classification_threshold, 0.5);
+    assert_eq!(config.vehicle_detection_threshold, 0.7);
+    assert_eq!(config.vehicle_mid_detection_threshold, 0.2);
+    assert_eq!(config.vehicle_classification_threshold, 0.4);
+    assert_eq!(config.blank_detection_threshold, 0.2);
+    assert_eq!(config.blank_classification_threshold, 0.5);
+    assert_eq!(config.blank_high_classification_threshold, 0.99);
+    assert_eq!(config.animal_high_classification_threshold, 0.8);
+    assert_eq!(config.animal_classification_threshold, 0.65);
+    assert_eq!(config.animal_detection_threshold, 0.2);
+    assert_eq!(config.rollup_threshold, 0.65);
+    assert_eq!(
+        config.rollup_levels,
+        vec!["genus", "family", "order", "class", "kingdom"]
+    );
+    assert_eq!(config.animal_detector_fallback_threshold, 0.5);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_config_from_toml_fn() {
+    let config = EnsembleConfig::from_toml_str(
+        r#"
+        human_detection_threshold = 0.8
+        rollup_levels = ["genus", "family"]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.human_detection_threshold, 0.8);
+    assert_eq!(config.rollup_levels, vec!["genus", "family"]);
+
+    // Missing fields are kept as the defaults.
+    assert_eq!(
+        config,
+        EnsembleConfig {
+            human_detection_threshold: 0.8,
+            rollup_levels: vec!["genus".to_string(), "family".to_string()],
+            ..EnsembleConfig::default()
+        }
+    );
+}
+
+#[test]
+fn test_config_from_json_fn() {
+    let config = EnsembleConfig::from_json_str(
+        r#"{"rollup_threshold": 0.5, "blank_detection_threshold": 0.1}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config,
+        EnsembleConfig {
+            rollup_threshold: 0.5,
+            blank_detection_threshold: 0.1,
+            ..EnsembleConfig::default()
+        }
+    );
+
+    // An empty config is the default config.
+    assert_eq!(
+        EnsembleConfig::from_json_str("{}").unwrap(),
+        EnsembleConfig::default()
+    );
+}
+
+#[test]
+fn test_invalid_config_fn() {
+    assert!(matches!(
+        EnsembleConfig::from_toml_str("rollup_threshold = 1.5"),
+        Err(Error::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        EnsembleConfig::from_json_str(r#"{"human_detection_threshold": -0.1}"#),
+        Err(Error::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        EnsembleConfig::from_toml_str(r#"rollup_levels = ["genus", "phylum"]"#),
+        Err(Error::InvalidTaxonomyLevel(_))
+    ));
+    assert!(matches!(
+        EnsembleConfig::from_toml_str("unknown_threshold = 0.5"),
+        Err(Error::TomlError(_))
+    ));
+}
@@ -3,6 +3,8 @@ pub enum Error {
     // Geofence errors
     #[error("{0}")]
     GeofenceInvalidValue(String),
+    #[error("The label is not in the taxonomy: {0}.")]
+    GeofenceUnknownLabel(String),
 
     // Taxonomy errors.
     #[error("Expected lable made of 7 parts, but found only {0}: {1}.")]
@@ -12,6 +14,10 @@ pub enum Error {
     )]
     InvalidTaxonomyLevel(String),
 
+    // Config errors
+    #[error("invalid ensemble config: {0}")]
+    InvalidConfig(String),
+
     // Ensemble errors
     #[error("`detections` cannot be empty")]
     EmptyDetections,
@@ -33,4 +39,6 @@ pub enum Error {
     CsvError(#[from] csv::Error),
     #[error("Deserialize error: {0}")]
     DeserializeError(#[from] serde_json::error::Error),
+    #[error("TOML error: {0}")]
+    TomlError(#[from] toml::de::Error),
 }
@@ -0,0 +1,108 @@
+use speciesnet_core::{
+    detector::Category,
+    ensemble::{
+        EnsembleTrace,
+        trace::{Condition, Operator, RuleStep},
+    },
+};
+
+/// Evaluates the conditions of a rule of the ensemble, recording them into the trace when there
+/// is one. Nothing is allocated when there is no trace, so the rules are written the same way
+/// whether the explain mode is enabled or not.
+pub(crate) struct Rule<'a> {
+    trace: Option<&'a mut EnsembleTrace>,
+    conditions: Vec<Condition>,
+    passed: bool,
+}
+
+impl<'a> Rule<'a> {
+    pub(crate) fn new(trace: Option<&'a mut EnsembleTrace>) -> Self {
+        Self {
+            trace,
+            conditions: Vec::new(),
+            passed: true,
+        }
+    }
+
+    /// Compares a score with a threshold of the config.
+    pub(crate) fn score(
+        mut self,
+        name: &str,
+        value: f64,
+        operator: Operator,
+        threshold_name: &str,
+        threshold: f64,
+    ) -> Self {
+        let passed = operator.compare(value, threshold);
+        self.passed &= passed;
+
+        if self.trace.is_some() {
+            self.conditions.push(Condition::Score {
+                name: name.to_string(),
+                value,
+                operator,
+                threshold_name: threshold_name.to_string(),
+                threshold,
+                passed,
+            });
+        }
+
+        self
+    }
+
+    /// Checks whether a label is one of the labels, or none of them.
+    pub(crate) fn label(mut self, name: &str, value: &str, one_of: bool, labels: &[&str]) -> Self {
+        let passed = labels.contains(&value) == one_of;
+        self.passed &= passed;
+
+        if self.trace.is_some() {
+            self.conditions.push(Condition::Label {
+                name: name.to_string(),
+                value: value.to_string(),
+                one_of,
+                values: labels.iter().map(|label| label.to_string()).collect(),
+                passed,
+            });
+        }
+
+        self
+    }
+
+    /// Checks whether the category of the top detection is the given one.
+    pub(crate) fn category(mut self, value: Category, category: Category) -> Self {
+        let passed = value == category;
+        self.passed &= passed;
+
+        if self.trace.is_some() {
+            self.conditions.push(Condition::Label {
+                name: "top_detection_class".to_string(),
+                value: value.to_string(),
+                one_of: true,
+                values: vec![category.to_string()],
+                passed,
+            });
+        }
+
+        self
+    }
+
+    /// Adds a condition whose details are recorded elsewhere in the trace, like the rollups.
+    pub(crate) fn require(mut self, passed: bool) -> Self {
+        self.passed &= passed;
+        self
+    }
+
+    /// Records the rule into the trace, returns whether all of its conditions passed.
+    pub(crate) fn check(self, rule: &str, description: &str) -> bool {
+        if let Some(trace) = self.trace {
+            trace.rules.push(RuleStep {
+                rule: rule.to_string(),
+                description: description.to_string(),
+                conditions: self.conditions,
+                fired: self.passed,
+            });
+        }
+
+        self.passed
+    }
+}
@@ -6,16 +6,22 @@ use std::path::Path;
 
 use csv::Reader;
 use serde::Deserialize;
-use speciesnet_core::constants;
-use speciesnet_core::ensemble::GeofenceResult;
+use speciesnet_core::ensemble::trace::{
+    EnsembleTrace, GeofenceEntry, GeofenceLookup, GeofenceReason, RollupScore, RollupStep,
+};
+use speciesnet_core::ensemble::{GeofenceResult, PredictionSource};
+use speciesnet_core::taxonomy::{Label, Rank};
 
 use crate::error::Error;
-use crate::geofence::taxonomy::get_ancestor_at_level;
+use crate::geofence::taxonomy::{ancestor_at_rank, parse_label, parse_rank};
 
+pub mod map;
 pub mod taxonomy;
 #[cfg(test)]
 mod tests;
 
+pub use map::Geofence;
+
 #[derive(Debug, Deserialize)]
 pub struct GeofenceFix {
     species: String,
@@ -24,8 +30,28 @@ pub struct GeofenceFix {
     admin1_region_code: Option<String>,
 }
 
+/// Returns whether the label is geofenced, see [`geofence_lookup`].
+#[cfg(test)]
+fn should_geofence(
+    label: &str,
+    country: Option<&str>,
+    admin1_region: Option<&str>,
+    geofence_map: &HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>,
+    enable_geofence: bool,
+) -> Result<bool, Error> {
+    Ok(geofence_lookup(
+        &parse_label(label)?,
+        country,
+        admin1_region,
+        geofence_map,
+        enable_geofence,
+    )?
+    .geofenced)
+}
+
 ///
-/// Check if label is allowed withing a given country
+/// Check if label is allowed withing a given country, along with why it is or is not and the
+/// entry of the geofence which decided it
 ///
 /// # Parameters:
 ///   - label:
@@ -39,37 +65,56 @@ pub struct GeofenceFix {
 ///   - enable_geofence:
 ///       Whether geofencing is enabled
 ///
-fn should_geofence(
-    label: &str,
+pub(crate) fn geofence_lookup(
+    label: &Label,
     country: Option<&str>,
     admin1_region: Option<&str>,
     geofence_map: &HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>,
     enable_geofence: bool,
-) -> Result<bool, Error> {
+) -> Result<GeofenceLookup, Error> {
+    let lookup = |geofenced, reason, entry| GeofenceLookup {
+        label: label.clone(),
+        country: country.map(str::to_string),
+        admin1_region: admin1_region.map(str::to_string),
+        geofenced,
+        reason,
+        entry,
+    };
+    let entry = |rule: &str, country: &str, regions: &[String]| {
+        Some(GeofenceEntry {
+            rule: rule.to_string(),
+            country: country.to_string(),
+            regions: regions.to_vec(),
+        })
+    };
+
     // Do not geofence if not enabled
     if !enable_geofence {
-        return Ok(false);
+        return Ok(lookup(false, GeofenceReason::Disabled, None));
     }
 
     // Do not geofence if country not given
     let _country = match country {
         Some(c) => c,
-        None => return Ok(false),
+        None => return Ok(lookup(false, GeofenceReason::NoCountry, None)),
     };
 
     // Get full string, exclude uuid and scientific name
-    let full_string_class = taxonomy::get_full_class_string(label)?;
+    let full_string_class = label.full_class_string();
     let geofence_from_full_string = match geofence_map.get(&full_string_class) {
         Some(g) => g,
-        None => return Ok(false),
+        None => return Ok(lookup(false, GeofenceReason::NoRules, None)),
     };
 
+    // The allow entry of the country, which lets the label through unless it is blocked.
+    let mut allowed_entry = None;
+
     // Get `allow` countries from given geofence_map
     if let Some(allowed_countries) = geofence_from_full_string.get("allow") {
         // Do geofence if given country not in allowed country
         if !allowed_countries.is_empty() {
             if !allowed_countries.contains_key(_country) {
-                return Ok(true);
+                return Ok(lookup(true, GeofenceReason::CountryNotAllowed, None));
             } else {
                 // Get states from given country
                 if let Some(allowed_admin1_region) = allowed_countries.get(_country) {
@@ -78,9 +123,15 @@ fn should_geofence(
                         if !allowed_admin1_region.is_empty()
                             && !allowed_admin1_region.contains(&ar.to_string())
                         {
-                            return Ok(true);
+                            return Ok(lookup(
+                                true,
+                                GeofenceReason::RegionNotAllowed,
+                                entry("allow", _country, allowed_admin1_region),
+                            ));
                         }
                     };
+
+                    allowed_entry = entry("allow", _country, allowed_admin1_region);
                 }
             }
         }
@@ -92,18 +143,26 @@ fn should_geofence(
         if !blocked_countries.is_empty() && blocked_countries.contains_key(_country) {
             if let Some(blocked_admin1_regions) = blocked_countries.get(_country) {
                 if blocked_admin1_regions.is_empty() {
-                    return Ok(true);
+                    return Ok(lookup(
+                        true,
+                        GeofenceReason::CountryBlocked,
+                        entry("block", _country, blocked_admin1_regions),
+                    ));
                 }
                 if let Some(ar) = admin1_region {
                     // Do geofence if given admin1_region in blocked admin1_region
                     if blocked_admin1_regions.contains(&ar.to_string()) {
-                        return Ok(true);
+                        return Ok(lookup(
+                            true,
+                            GeofenceReason::RegionBlocked,
+                            entry("block", _country, blocked_admin1_regions),
+                        ));
                     };
                 };
             }
         }
     }
-    Ok(false)
+    Ok(lookup(false, GeofenceReason::Allowed, allowed_entry))
 }
 
 ///
@@ -142,10 +201,41 @@ pub fn roll_up_labels_to_first_matching_level(
     admin1_region: Option<&str>,
     target_taxonomy_levels: &Vec<String>,
     non_blank_threshold: &f64,
-    taxonomy_map: &HashMap<String, String>,
+    taxonomy_map: &HashMap<String, Label>,
     geofence_map: &HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>,
     enable_geofence: bool,
-) -> Result<Option<(String, f64, String)>, Error> {
+) -> Result<Option<(Label, f64, PredictionSource)>, Error> {
+    Ok(roll_up_labels_with_trace(
+        labels,
+        scores,
+        country,
+        admin1_region,
+        target_taxonomy_levels,
+        non_blank_threshold,
+        taxonomy_map,
+        geofence_map,
+        enable_geofence,
+        None,
+    )?
+    .map(|(label, score, rank)| (label, score, PredictionSource::Rollup(rank))))
+}
+
+/// Same as [`roll_up_labels_to_first_matching_level`], returning the rank the labels were rolled
+/// up to instead of the source. The rolled up scores of each level and the geofence lookups are
+/// recorded into the trace when one is given.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn roll_up_labels_with_trace(
+    labels: &[String],
+    scores: &[f64],
+    country: Option<&str>,
+    admin1_region: Option<&str>,
+    target_taxonomy_levels: &Vec<String>,
+    non_blank_threshold: &f64,
+    taxonomy_map: &HashMap<String, Label>,
+    geofence_map: &HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>,
+    enable_geofence: bool,
+    mut trace: Option<&mut EnsembleTrace>,
+) -> Result<Option<(Label, f64, Rank)>, Error> {
     // Find if there is invalid taxonomy level
     let expected_target_taxonomy_levels = vec![
         "species".to_string(),
@@ -168,39 +258,75 @@ pub fn roll_up_labels_to_first_matching_level(
         )));
     };
 
+    let labels = labels
+        .iter()
+        .map(|label| parse_label(label))
+        .collect::<Result<Vec<Label>, Error>>()?;
+
     for taxonomy_level in target_taxonomy_levels {
-        let mut accumulated_scores = HashMap::new();
+        let rank = parse_rank(taxonomy_level)?;
+        let mut accumulated_scores: HashMap<&Label, f64> = HashMap::new();
         for (label, score) in labels.iter().zip(scores.iter()) {
-            let roll_up_label = get_ancestor_at_level(label, taxonomy_level, taxonomy_map)?;
-            if let Some(r_label) = roll_up_label {
-                let new_score = accumulated_scores.get(&r_label).unwrap_or(&0.0) + score;
-                accumulated_scores.insert(r_label, new_score);
+            if let Some(r_label) = ancestor_at_rank(label, rank, taxonomy_map) {
+                *accumulated_scores.entry(r_label).or_insert(0.0) += score;
             }
         }
 
-        let mut max_rollup_label = "";
+        // Goes from the highest score down, ties are broken by the label so the result does not
+        // depend on the order of the map.
+        let mut accumulated_scores: Vec<(&Label, f64)> = accumulated_scores.into_iter().collect();
+        accumulated_scores.sort_by(|(a_label, a_score), (b_label, b_score)| {
+            b_score
+                .total_cmp(a_score)
+                .then_with(|| a_label.cmp(b_label))
+        });
+
+        let mut max_rollup_label = None;
         let mut max_rollup_score = 0.0;
         for (r_label, r_score) in &accumulated_scores {
-            if r_score > &max_rollup_score
-                && !should_geofence(
+            if r_score > &max_rollup_score {
+                let lookup = geofence_lookup(
                     r_label,
                     country,
                     admin1_region,
                     geofence_map,
                     enable_geofence,
-                )?
-            {
-                max_rollup_label = r_label;
-                max_rollup_score = *r_score;
+                )?;
+                let geofenced = lookup.geofenced;
+
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.geofence_lookups.push(lookup);
+                }
+
+                if !geofenced {
+                    max_rollup_label = Some(*r_label);
+                    max_rollup_score = *r_score;
+                }
             }
         }
 
-        if max_rollup_score > *non_blank_threshold && !max_rollup_label.is_empty() {
-            return Ok(Some((
-                max_rollup_label.to_string(),
-                max_rollup_score,
-                format!("classifier+rollup_to_{}", taxonomy_level),
-            )));
+        let passed = max_rollup_score > *non_blank_threshold && max_rollup_label.is_some();
+
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.rollups.push(RollupStep {
+                level: taxonomy_level.clone(),
+                threshold: *non_blank_threshold,
+                scores: accumulated_scores
+                    .iter()
+                    .map(|(label, score)| RollupScore {
+                        label: (*label).clone(),
+                        score: *score,
+                    })
+                    .collect(),
+                label: max_rollup_label.cloned(),
+                passed,
+            });
+        }
+
+        if let Some(label) = max_rollup_label
+            && passed
+        {
+            return Ok(Some((label.clone(), max_rollup_score, rank)));
         }
     }
 
@@ -236,18 +362,51 @@ pub fn geofence_animal_classification(
     scores: &[f64],
     country: Option<&str>,
     admin1_region: Option<&str>,
-    taxonomy_map: &HashMap<String, String>,
+    taxonomy_map: &HashMap<String, Label>,
     geofence_map: &HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>,
     enable_geofence: bool,
 ) -> Result<GeofenceResult, Error> {
-    if should_geofence(
-        &labels[0],
+    geofence_animal_classification_with_trace(
+        labels,
+        scores,
         country,
         admin1_region,
+        taxonomy_map,
         geofence_map,
         enable_geofence,
-    )? {
-        let rollup = roll_up_labels_to_first_matching_level(
+        None,
+    )
+}
+
+/// Same as [`geofence_animal_classification`], recording the geofence lookups and the rollups
+/// into the trace when one is given.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn geofence_animal_classification_with_trace(
+    labels: &[String],
+    scores: &[f64],
+    country: Option<&str>,
+    admin1_region: Option<&str>,
+    taxonomy_map: &HashMap<String, Label>,
+    geofence_map: &HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>,
+    enable_geofence: bool,
+    mut trace: Option<&mut EnsembleTrace>,
+) -> Result<GeofenceResult, Error> {
+    let label = parse_label(&labels[0])?;
+    let lookup = geofence_lookup(
+        &label,
+        country,
+        admin1_region,
+        geofence_map,
+        enable_geofence,
+    )?;
+    let geofenced = lookup.geofenced;
+
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.geofence_lookups.push(lookup);
+    }
+
+    if geofenced {
+        let rollup = roll_up_labels_with_trace(
             labels,
             scores,
             country,
@@ -262,25 +421,26 @@ pub fn geofence_animal_classification(
             taxonomy_map,
             geofence_map,
             enable_geofence,
+            trace,
         )?;
-        if let Some((r_label, r_score, r_source)) = rollup {
+        if let Some((r_label, r_score, r_rank)) = rollup {
             Ok(GeofenceResult::new(
                 r_label,
                 r_score,
-                format!("classifier+geofence+{}", &r_source[11..]),
+                PredictionSource::GeofenceRollup(r_rank),
             ))
         } else {
             Ok(GeofenceResult::new(
-                constants::classification::UNKNOWN.to_string(),
+                Label::unknown(),
                 scores[0],
-                "classifier+geofence+rollup_failed".to_string(),
+                PredictionSource::GeofenceRollupFailed,
             ))
         }
     } else {
         Ok(GeofenceResult::new(
-            labels[0].to_string(),
+            label,
             scores[0],
-            "classifier".to_string(),
+            PredictionSource::Classifier,
         ))
     }
 }
@@ -309,120 +469,135 @@ pub fn fix_geofence_base<P: AsRef<Path>>(
                 "Rule types should be either `allow` or `block`.".to_string(),
             ));
         }
-        let country = fix.country_code;
-        let state = fix.admin1_region_code.unwrap_or("".to_string());
-        if rule == "allow" {
-            // If the label does not exist in geofence map, do not update.
-            if !geofence.contains_key(&label) {
-                continue;
-            }
+        apply_fix(
+            &mut geofence,
+            label,
+            &rule,
+            fix.country_code,
+            fix.admin1_region_code,
+        );
+    }
 
-            // If the label exists but there are no allowed countries, do not update.
-            if geofence.get(&label).and_then(|v| v.get("allow")).is_none() {
-                continue;
-            }
+    Ok(geofence)
+}
 
-            // If allow block list only has a country, insert the country into the allow list.
-            if state.is_empty() {
-                if let Some(map) = geofence.get_mut(&label).and_then(|v| v.get_mut("allow")) {
-                    map.entry(country).or_insert_with(|| vec![]);
-                }
-            } else {
-                // If admin1_region exists with country, check whether there is a previously
-                // allowed country and admin1_region before
-                let allow_map = geofence
-                    .get_mut(&label)
-                    .and_then(|v| v.get_mut("allow"))
-                    .and_then(|v| v.get_mut(&country));
-                match allow_map {
-                    Some(rule) => {
-                        if rule.is_empty() {
-                            // If the country is already allowed, but admin1 region list is
-                            // empty, then do nothing.
-                            //
-                            // if country is USA and admin1 is NY, but inside allow map is only
-                            // USA, then we skip if there are no USA/PH or something like that
-                            // before.
-                            continue;
-                        } else {
-                            // If the country is already allowed and there was admin1
-                            // region inside the geofence list, then we add one more to the
-                            // list.
-                            //
-                            // if country is USA and admin1 is NY, and inside allow map there
-                            // exists USA and USA/PH, then we can add in the USA/NY along.
-                            let set: HashSet<String> = rule.clone().into_iter().collect();
-                            let new_set: HashSet<String> = vec![state].into_iter().collect();
-                            *rule = set.union(&new_set).cloned().collect();
-                        }
-                    }
-                    // If the country key inside the allow list has never existed before, then we
-                    // insert the new country along with its admin1 region.
-                    //
-                    // If country is USA and admin1 is NY, but the list does not even have USA,
-                    // then we add USA, and USA/NY
-                    None => {
-                        geofence
-                            .entry(label)
-                            .or_default()
-                            .entry("allow".to_string())
-                            .or_default()
-                            .entry(country.clone())
-                            .or_insert_with(|| vec![state]);
-                    }
-                }
+/// Applies an `allow` or `block` rule of a country, or of one of its admin1 regions, to the label
+/// of the given full class string, following the fixes of the Python implementation.
+///
+/// Allowing only extends an existing allow list, since the labels without one are allowed
+/// everywhere already, and does not lift the blocks of the label.
+pub(crate) fn apply_fix(
+    geofence: &mut HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>,
+    label: String,
+    rule: &str,
+    country: String,
+    admin1_region: Option<String>,
+) {
+    let state = admin1_region.unwrap_or("".to_string());
+    if rule == "allow" {
+        // If the label does not exist in geofence map, do not update.
+        if !geofence.contains_key(&label) {
+            return;
+        }
+
+        // If the label exists but there are no allowed countries, do not update.
+        if geofence.get(&label).and_then(|v| v.get("allow")).is_none() {
+            return;
+        }
+
+        // If allow block list only has a country, insert the country into the allow list.
+        if state.is_empty() {
+            if let Some(map) = geofence.get_mut(&label).and_then(|v| v.get_mut("allow")) {
+                map.entry(country).or_insert_with(|| vec![]);
             }
         } else {
-            // When blocking, we will add a species in, if there is not a block before.
-            if !geofence.contains_key(&label)
-                || geofence.get(&label).and_then(|v| v.get("block")).is_none()
-            {
-                geofence
-                    .entry(label.clone())
-                    .or_default()
-                    .entry("block".to_string())
-                    .or_default()
-                    .entry(country.clone())
-                    .or_insert_with(|| {
-                        if state.is_empty() {
-                            vec![]
-                        } else {
-                            vec![state.clone()]
-                        }
-                    });
-            }
-            if state.is_empty() {
-                if let Some(map) = geofence.get_mut(&label).and_then(|v| v.get_mut("block")) {
-                    map.entry(country).or_insert_with(|| vec![]);
+            // If admin1_region exists with country, check whether there is a previously
+            // allowed country and admin1_region before
+            let allow_map = geofence
+                .get_mut(&label)
+                .and_then(|v| v.get_mut("allow"))
+                .and_then(|v| v.get_mut(&country));
+            match allow_map {
+                // If the country is already allowed, but admin1 region list is empty, then do
+                // nothing.
+                //
+                // if country is USA and admin1 is NY, but inside allow map is only USA, then we
+                // skip if there are no USA/PH or something like that before.
+                Some(rule) => {
+                    if !rule.is_empty() {
+                        // If the country is already allowed and there was admin1
+                        // region inside the geofence list, then we add one more to the
+                        // list.
+                        //
+                        // if country is USA and admin1 is NY, and inside allow map there
+                        // exists USA and USA/PH, then we can add in the USA/NY along.
+                        let set: HashSet<String> = rule.clone().into_iter().collect();
+                        let new_set: HashSet<String> = vec![state].into_iter().collect();
+                        *rule = set.union(&new_set).cloned().collect();
+                    }
                 }
-            } else {
-                let allow_map = geofence
-                    .get_mut(&label)
-                    .and_then(|v| v.get_mut("block"))
-                    .and_then(|v| v.get_mut(&country));
-                match allow_map {
-                    Some(rule) => {
-                        if rule.is_empty() {
-                            continue;
-                        } else {
-                            let set: HashSet<String> = rule.clone().into_iter().collect();
-                            let new_set: HashSet<String> = vec![state].into_iter().collect();
-                            *rule = set.union(&new_set).cloned().collect();
-                        }
+                // If the country key inside the allow list has never existed before, then we
+                // insert the new country along with its admin1 region.
+                //
+                // If country is USA and admin1 is NY, but the list does not even have USA,
+                // then we add USA, and USA/NY
+                None => {
+                    geofence
+                        .entry(label)
+                        .or_default()
+                        .entry("allow".to_string())
+                        .or_default()
+                        .entry(country.clone())
+                        .or_insert_with(|| vec![state]);
+                }
+            }
+        }
+    } else {
+        // When blocking, we will add a species in, if there is not a block before.
+        if !geofence.contains_key(&label)
+            || geofence.get(&label).and_then(|v| v.get("block")).is_none()
+        {
+            geofence
+                .entry(label.clone())
+                .or_default()
+                .entry("block".to_string())
+                .or_default()
+                .entry(country.clone())
+                .or_insert_with(|| {
+                    if state.is_empty() {
+                        vec![]
+                    } else {
+                        vec![state.clone()]
                     }
-                    None => {
-                        geofence
-                            .entry(label)
-                            .or_default()
-                            .entry("block".to_string())
-                            .or_default()
-                            .entry(country.clone())
-                            .or_insert_with(|| vec![state]);
+                });
+        }
+        if state.is_empty() {
+            if let Some(map) = geofence.get_mut(&label).and_then(|v| v.get_mut("block")) {
+                map.entry(country).or_insert_with(|| vec![]);
+            }
+        } else {
+            let allow_map = geofence
+                .get_mut(&label)
+                .and_then(|v| v.get_mut("block"))
+                .and_then(|v| v.get_mut(&country));
+            match allow_map {
+                Some(rule) => {
+                    if !rule.is_empty() {
+                        let set: HashSet<String> = rule.clone().into_iter().collect();
+                        let new_set: HashSet<String> = vec![state].into_iter().collect();
+                        *rule = set.union(&new_set).cloned().collect();
                     }
                 }
+                None => {
+                    geofence
+                        .entry(label)
+                        .or_default()
+                        .entry("block".to_string())
+                        .or_default()
+                        .entry(country.clone())
+                        .or_insert_with(|| vec![state]);
+                }
             }
         }
     }
-
-    Ok(geofence)
 }
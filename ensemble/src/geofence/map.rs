@@ -0,0 +1,319 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use speciesnet_core::{
+    ensemble::trace::GeofenceLookup,
+    geography::{COUNTRY_CODES, admin1_region_code, validate_location},
+    taxonomy::{Label, Rank},
+};
+
+use crate::{
+    error::Error,
+    geofence::{apply_fix, fix_geofence_base, geofence_lookup},
+    taxonomy::Taxonomy,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// The geofence as written in `geofence_release.json`, full class strings to the `allow` and
+/// `block` rules of the label, which map the countries to their admin1 regions.
+pub type GeofenceMap = HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>;
+
+/// The geofence of a model, which tells in which countries and admin1 regions the labels can be
+/// found. The labels without any rules are allowed everywhere.
+///
+/// The geofence serializes to the format of `geofence_release.json`, with its keys sorted so the
+/// exported files can be diffed.
+#[derive(Debug, Clone, Default)]
+pub struct Geofence {
+    map: GeofenceMap,
+}
+
+/// A country where a label is allowed, see [`Geofence::allowed_countries`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AllowedCountry {
+    pub country: String,
+    /// The only admin1 regions of the country the label is allowed in, empty when it is allowed
+    /// in all of them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_regions: Vec<String>,
+    /// The admin1 regions of the country the label is blocked in.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocked_regions: Vec<String>,
+}
+
+impl Geofence {
+    pub fn new(map: GeofenceMap) -> Self {
+        Self { map }
+    }
+
+    /// Loads the geofence from a `geofence_release.json` file, applying the fixes of a csv file
+    /// on top of it when one is given, see [`fix_geofence_base`].
+    pub fn from_file<P: AsRef<Path>>(
+        geofence_base_path: P,
+        geofence_fix_path: Option<P>,
+    ) -> Result<Self, Error> {
+        let reader = BufReader::new(File::open(geofence_base_path)?);
+        let mut geofence: Self = serde_json::from_reader(reader)?;
+
+        if let Some(geofence_fix_path) = geofence_fix_path {
+            geofence.apply_fixes(geofence_fix_path)?;
+        }
+
+        Ok(geofence)
+    }
+
+    /// Loads the geofence from a `geofence_release.json` file, applying the fixes of each of the
+    /// csv files on top of it in order, e.g. the fixes of a project after the ones of a release.
+    pub fn from_files<P: AsRef<Path>>(
+        geofence_base_path: P,
+        geofence_fix_paths: &[P],
+    ) -> Result<Self, Error> {
+        let mut geofence = Self::from_file(geofence_base_path, None)?;
+
+        for geofence_fix_path in geofence_fix_paths {
+            geofence.apply_fixes(geofence_fix_path)?;
+        }
+
+        Ok(geofence)
+    }
+
+    /// Applies the fixes of a csv file on top of the geofence, see [`fix_geofence_base`].
+    pub fn apply_fixes<P: AsRef<Path>>(
+        &mut self,
+        geofence_fix_path: P,
+    ) -> Result<&mut Self, Error> {
+        self.map = fix_geofence_base(&self.map, geofence_fix_path)?;
+        Ok(self)
+    }
+
+    /// Allows a species, a genus or a family of the taxonomy in a country, or in one of its admin1
+    /// regions, along with the labels of the taxonomy under it.
+    ///
+    /// Like the fixes, this only extends the labels which already have an allow list since the
+    /// others are allowed everywhere, and it does not lift their blocks.
+    pub fn allow(
+        &mut self,
+        taxonomy: &Taxonomy,
+        taxon: &Label,
+        country: &str,
+        admin1_region: Option<&str>,
+    ) -> Result<&mut Self, Error> {
+        self.edit("allow", taxonomy, taxon, country, admin1_region)
+    }
+
+    /// Blocks a species, a genus or a family of the taxonomy in a country, or in one of its admin1
+    /// regions, along with the labels of the taxonomy under it.
+    pub fn block(
+        &mut self,
+        taxonomy: &Taxonomy,
+        taxon: &Label,
+        country: &str,
+        admin1_region: Option<&str>,
+    ) -> Result<&mut Self, Error> {
+        self.edit("block", taxonomy, taxon, country, admin1_region)
+    }
+
+    fn edit(
+        &mut self,
+        rule: &str,
+        taxonomy: &Taxonomy,
+        taxon: &Label,
+        country: &str,
+        admin1_region: Option<&str>,
+    ) -> Result<&mut Self, Error> {
+        if !taxonomy.labels().contains(taxon) {
+            return Err(Error::GeofenceUnknownLabel(taxon.to_string()));
+        }
+
+        if !matches!(
+            taxon.rank(),
+            Some(Rank::Species | Rank::Genus | Rank::Family)
+        ) {
+            return Err(Error::GeofenceInvalidValue(format!(
+                "Geofence rules are set on species, genera or families, but found: {}.",
+                taxon
+            )));
+        }
+
+        validate_location(Some(country), admin1_region)
+            .map_err(|e| Error::GeofenceInvalidValue(e.to_string()))?;
+
+        // The geofence has the admin1 regions without the prefix of their country, e.g. `CA`.
+        let admin1_region = admin1_region
+            .map(|admin1_region| admin1_region_code(country, admin1_region).to_string());
+
+        for label in taxonomy
+            .labels()
+            .iter()
+            .filter(|label| label.is_under(taxon))
+        {
+            apply_fix(
+                &mut self.map,
+                label.full_class_string(),
+                rule,
+                country.to_string(),
+                admin1_region.clone(),
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// Writes the geofence as json in the format of `geofence_release.json`.
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), Error> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+
+    /// Saves the geofence to a json file in the format of `geofence_release.json`, which can be
+    /// loaded back with [`Geofence::from_file`].
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.to_writer(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns the rules of every label, keyed by their full class strings.
+    pub fn map(&self) -> &GeofenceMap {
+        &self.map
+    }
+
+    /// Returns whether the geofence has rules for the label.
+    pub fn has_rules(&self, label: &Label) -> bool {
+        self.map.contains_key(&label.full_class_string())
+    }
+
+    /// Looks the label up in the country and admin1 region, with why it is or is not geofenced.
+    /// The admin1 region can be given with or without the prefix of its country, e.g. `CA` or
+    /// `US-CA` for California.
+    pub fn lookup(
+        &self,
+        label: &Label,
+        country: &str,
+        admin1_region: Option<&str>,
+    ) -> Result<GeofenceLookup, Error> {
+        // The geofence has the admin1 regions without the prefix of their country, e.g. `CA`.
+        let admin1_region =
+            admin1_region.map(|admin1_region| admin1_region_code(country, admin1_region));
+
+        geofence_lookup(label, Some(country), admin1_region, &self.map, true)
+    }
+
+    /// Returns whether the label is allowed in the country and admin1 region.
+    pub fn is_allowed(
+        &self,
+        label: &Label,
+        country: &str,
+        admin1_region: Option<&str>,
+    ) -> Result<bool, Error> {
+        Ok(!self.lookup(label, country, admin1_region)?.geofenced)
+    }
+
+    /// Looks each of the labels up in the country and admin1 region, e.g. to split the species of
+    /// the model between the allowed and the blocked ones before a deployment.
+    pub fn lookup_all<'a>(
+        &self,
+        labels: impl IntoIterator<Item = &'a Label>,
+        country: &str,
+        admin1_region: Option<&str>,
+    ) -> Result<Vec<GeofenceLookup>, Error> {
+        labels
+            .into_iter()
+            .map(|label| self.lookup(label, country, admin1_region))
+            .collect()
+    }
+
+    /// Returns the countries where the label is allowed, sorted by their codes. A label with an
+    /// `allow` rule is only allowed in its countries, the others are allowed in every country of
+    /// ISO 3166-1 which is not blocked.
+    pub fn allowed_countries(&self, label: &Label) -> Vec<AllowedCountry> {
+        let empty = HashMap::new();
+        let rules = self.map.get(&label.full_class_string());
+        let allowed = rules.and_then(|rules| rules.get("allow")).unwrap_or(&empty);
+        let blocked = rules.and_then(|rules| rules.get("block")).unwrap_or(&empty);
+
+        let countries: BTreeSet<&str> = if allowed.is_empty() {
+            COUNTRY_CODES.iter().map(|(alpha_3, _)| *alpha_3).collect()
+        } else {
+            allowed.keys().map(String::as_str).collect()
+        };
+
+        countries
+            .into_iter()
+            .filter_map(|country| {
+                let blocked_regions = match blocked.get(country) {
+                    Some(regions) if regions.is_empty() => return None,
+                    Some(regions) => sorted(regions),
+                    None => Vec::new(),
+                };
+
+                Some(AllowedCountry {
+                    country: country.to_string(),
+                    allowed_regions: allowed
+                        .get(country)
+                        .map(|regions| sorted(regions))
+                        .unwrap_or_default(),
+                    blocked_regions,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the labels the geofence has no rules for, which are allowed everywhere.
+    pub fn without_rules<'a>(&self, labels: impl IntoIterator<Item = &'a Label>) -> Vec<&'a Label> {
+        labels
+            .into_iter()
+            .filter(|label| !self.has_rules(label))
+            .collect()
+    }
+}
+
+impl Serialize for Geofence {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let sorted: BTreeMap<&String, BTreeMap<&String, BTreeMap<&String, Vec<String>>>> = self
+            .map
+            .iter()
+            .map(|(label, rules)| {
+                let rules = rules
+                    .iter()
+                    .map(|(rule, countries)| {
+                        let countries = countries
+                            .iter()
+                            .map(|(country, regions)| (country, sorted(regions)))
+                            .collect();
+                        (rule, countries)
+                    })
+                    .collect();
+                (label, rules)
+            })
+            .collect();
+
+        sorted.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Geofence {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self::new(GeofenceMap::deserialize(deserializer)?))
+    }
+}
+
+fn sorted(regions: &[String]) -> Vec<String> {
+    let mut regions = regions.to_vec();
+    regions.sort();
+    regions
+}
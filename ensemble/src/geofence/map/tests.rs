@@ -0,0 +1,215 @@
+use std::{env::temp_dir, fs};
+
+use serde_json::json;
+use speciesnet_core::{ensemble::trace::GeofenceReason, geography::COUNTRY_CODES, taxonomy::Label};
+
+use super::{AllowedCountry, Geofence};
+use crate::{error::Error, taxonomy::Taxonomy};
+
+const LION: &str =
+    "ddf59264-185a-4d35-b647-2785792bdf54;mammalia;carnivora;felidae;panthera;leo;lion";
+const LION_FC: &str = "mammalia;carnivora;felidae;panthera;leo";
+const PUMA: &str =
+    "9c564562-9429-405c-8529-04cff7752282;mammalia;carnivora;felidae;puma;concolor;puma";
+const PUMA_FC: &str = "mammalia;carnivora;felidae;puma;concolor";
+const SAND_CAT: &str =
+    "e588253d-d61d-4149-a96c-8c245927a80f;mammalia;carnivora;felidae;felis;margarita;sand cat";
+const SAND_CAT_FC: &str = "mammalia;carnivora;felidae;felis;margarita";
+const FELIDAE_FAMILY: &str =
+    "df8514b0-10a5-411f-8ed6-0f415e8153a3;mammalia;carnivora;felidae;;;cat family";
+const MAMMALIA_CLASS: &str = "f2d233e3-80e3-433d-9687-e29ecc7a467a;mammalia;;;;;mammal";
+const BROWN_BEAR: &str =
+    "330bb1e9-84d6-4e41-afa9-938aee17ea29;mammalia;carnivora;ursidae;ursus;arctos;brown bear";
+
+fn label(label: &str) -> Label {
+    label.parse().unwrap()
+}
+
+fn geofence() -> Geofence {
+    let json = json!(
+        {
+            LION_FC: {
+                "allow": {
+                    "KEN": [],
+                    "TZA": [],
+                }
+            },
+            PUMA_FC: {
+                "allow": {
+                    "CAN": [],
+                    "USA": ["CA", "AZ"],
+                },
+                "block": {
+                    "CAN": ["QC"],
+                },
+            },
+            SAND_CAT_FC: {
+                "block": {
+                    "AUS": [],
+                },
+            },
+        }
+    );
+
+    Geofence::new(serde_json::from_value(json).unwrap())
+}
+
+#[test]
+fn test_lookup_all() -> Result<(), Error> {
+    let geofence = geofence();
+    let labels = [LION, PUMA, SAND_CAT, BROWN_BEAR].map(label);
+
+    let lookups = geofence.lookup_all(&labels, "USA", Some("CA"))?;
+    let reasons: Vec<(bool, GeofenceReason)> = lookups
+        .iter()
+        .map(|lookup| (lookup.geofenced, lookup.reason))
+        .collect();
+    assert_eq!(
+        reasons,
+        vec![
+            (true, GeofenceReason::CountryNotAllowed),
+            (false, GeofenceReason::Allowed),
+            (false, GeofenceReason::Allowed),
+            (false, GeofenceReason::NoRules),
+        ]
+    );
+
+    // The admin1 regions can have the prefix of their country, as the geofence has them without.
+    let lookups = geofence.lookup_all(&labels, "USA", Some("US-CA"))?;
+    assert!(!lookups[1].geofenced);
+    assert_eq!(lookups[1].admin1_region.as_deref(), Some("CA"));
+    assert!(geofence.is_allowed(&label(PUMA), "USA", Some("US-AZ"))?);
+    assert!(!geofence.is_allowed(&label(PUMA), "CAN", Some("CA-QC"))?);
+    assert!(geofence.is_allowed(&label(PUMA), "CAN", Some("CA-ON"))?);
+
+    assert!(!geofence.is_allowed(&label(PUMA), "USA", Some("NY"))?);
+    assert!(!geofence.is_allowed(&label(PUMA), "USA", Some("US-NY"))?);
+    assert!(!geofence.is_allowed(&label(SAND_CAT), "AUS", None)?);
+    assert!(geofence.is_allowed(&label(BROWN_BEAR), "AUS", None)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_allowed_countries() {
+    let geofence = geofence();
+
+    assert_eq!(
+        geofence.allowed_countries(&label(PUMA)),
+        vec![
+            AllowedCountry {
+                country: "CAN".to_string(),
+                allowed_regions: vec![],
+                blocked_regions: vec!["QC".to_string()],
+            },
+            AllowedCountry {
+                country: "USA".to_string(),
+                allowed_regions: vec!["AZ".to_string(), "CA".to_string()],
+                blocked_regions: vec![],
+            },
+        ]
+    );
+
+    // Only the blocked country is left out for the labels without an allow list.
+    let countries = geofence.allowed_countries(&label(SAND_CAT));
+    assert_eq!(countries.len(), COUNTRY_CODES.len() - 1);
+    assert!(!countries.iter().any(|country| country.country == "AUS"));
+    assert_eq!(
+        geofence.allowed_countries(&label(BROWN_BEAR)).len(),
+        COUNTRY_CODES.len()
+    );
+}
+
+#[test]
+fn test_without_rules() {
+    let geofence = geofence();
+    let labels = [LION, PUMA, SAND_CAT, BROWN_BEAR].map(label);
+
+    assert_eq!(geofence.without_rules(&labels), vec![&label(BROWN_BEAR)]);
+}
+
+#[test]
+fn test_allow_and_block() -> Result<(), Error> {
+    let mut geofence = geofence();
+    let taxonomy = Taxonomy::new(
+        [MAMMALIA_CLASS, FELIDAE_FAMILY, LION, PUMA, SAND_CAT]
+            .map(label)
+            .to_vec(),
+        Vec::new(),
+    );
+
+    geofence
+        .allow(&taxonomy, &label(LION), "USA", None)?
+        .block(&taxonomy, &label(FELIDAE_FAMILY), "MEX", Some("MX-SON"))?;
+
+    assert!(geofence.is_allowed(&label(LION), "USA", None)?);
+    // The block of the family applies to every label under it, with the prefix of the admin1
+    // region removed.
+    for cat in [FELIDAE_FAMILY, LION, PUMA, SAND_CAT] {
+        assert_eq!(
+            geofence.map()[&label(cat).full_class_string()]["block"]["MEX"],
+            vec!["SON"]
+        );
+    }
+
+    assert!(matches!(
+        geofence.block(&taxonomy, &label(BROWN_BEAR), "USA", None),
+        Err(Error::GeofenceUnknownLabel(_))
+    ));
+    assert!(matches!(
+        geofence.block(&taxonomy, &label(MAMMALIA_CLASS), "USA", None),
+        Err(Error::GeofenceInvalidValue(_))
+    ));
+    assert!(matches!(
+        geofence.block(&taxonomy, &label(LION), "XXX", None),
+        Err(Error::GeofenceInvalidValue(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_layered_fixes_and_export() -> Result<(), Error> {
+    let folder = temp_dir().join(format!("speciesnet-geofence-{}", std::process::id()));
+    fs::create_dir_all(&folder)?;
+
+    let base_path = folder.join("geofence_release.json");
+    let release_fixes_path = folder.join("release_fixes.csv");
+    let project_fixes_path = folder.join("project_fixes.csv");
+
+    geofence().save(&base_path)?;
+    fs::write(
+        &release_fixes_path,
+        format!("species,rule,country_code,admin1_region_code\n{LION_FC},allow,USA,\n"),
+    )?;
+    fs::write(
+        &project_fixes_path,
+        format!("species,rule,country_code,admin1_region_code\n{LION_FC},block,USA,TX\n"),
+    )?;
+
+    let geofence = Geofence::from_files(&base_path, &[&release_fixes_path, &project_fixes_path])?;
+
+    assert!(geofence.is_allowed(&label(LION), "USA", Some("CA"))?);
+    assert!(!geofence.is_allowed(&label(LION), "USA", Some("TX"))?);
+
+    // The merged geofence is written in the format it is read from, with sorted keys.
+    let merged_path = folder.join("merged.json");
+    geofence.save(&merged_path)?;
+
+    let merged = fs::read_to_string(&merged_path)?;
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&merged)?[LION_FC],
+        json!({
+            "allow": { "KEN": [], "TZA": [], "USA": [] },
+            "block": { "USA": ["TX"] },
+        })
+    );
+    assert_eq!(
+        Geofence::from_file(&merged_path, None)?.map(),
+        geofence.map()
+    );
+
+    fs::remove_dir_all(&folder)?;
+
+    Ok(())
+}
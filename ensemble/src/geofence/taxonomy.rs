@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use speciesnet_core::constants::classification;
+use speciesnet_core::{
+    error::Error as CoreError,
+    taxonomy::{Label, Rank},
+};
 
 use crate::error::Error;
 
@@ -27,102 +30,21 @@ mod tests;
 pub fn get_ancestor_at_level(
     label: &str,
     taxonomy_level: &str,
-    taxonomy_map: &HashMap<String, String>,
-) -> Result<Option<String>, Error> {
-    let label_parts = label.split(';').collect::<Vec<_>>();
-    if label_parts.len() != 7 {
-        return Err(Error::InvalidLabel(
-            label_parts.len().to_string(),
-            label.to_string(),
-        ));
-    }
+    taxonomy_map: &HashMap<String, Label>,
+) -> Result<Option<Label>, Error> {
+    let label = parse_label(label)?;
+    let rank = parse_rank(taxonomy_level)?;
 
-    let mut ancestor_parts: Vec<&str>;
-    match taxonomy_level {
-        "species" => {
-            ancestor_parts = label_parts[1..6].to_vec();
-            match ancestor_parts.get(4) {
-                Some(ancestor) => {
-                    if ancestor.is_empty() {
-                        return Ok(None);
-                    }
-                }
-                None => return Ok(None),
-            }
-        }
-        "genus" => {
-            ancestor_parts = label_parts[1..5].to_vec();
-            let mut blank_vec = vec![""];
-            ancestor_parts.append(&mut blank_vec);
-            match ancestor_parts.get(3) {
-                Some(ancestor) => {
-                    if ancestor.is_empty() {
-                        return Ok(None);
-                    }
-                }
-                None => return Ok(None),
-            }
-        }
-        "family" => {
-            ancestor_parts = label_parts[1..4].to_vec();
-            let mut blank_vec = vec!["", ""];
-            ancestor_parts.append(&mut blank_vec);
-            match ancestor_parts.get(2) {
-                Some(ancestor) => {
-                    if ancestor.is_empty() {
-                        return Ok(None);
-                    }
-                }
-                None => return Ok(None),
-            }
-        }
-        "order" => {
-            ancestor_parts = label_parts[1..3].to_vec();
-            let mut blank_vec = vec!["", "", ""];
-            ancestor_parts.append(&mut blank_vec);
-            match ancestor_parts.get(1) {
-                Some(ancestor) => {
-                    if ancestor.is_empty() {
-                        return Ok(None);
-                    }
-                }
-                None => return Ok(None),
-            }
-        }
-        "class" => {
-            ancestor_parts = label_parts[1..2].to_vec();
-            let mut blank_vec = vec!["", "", "", ""];
-            ancestor_parts.append(&mut blank_vec);
-            match ancestor_parts.first() {
-                Some(ancestor) => {
-                    if ancestor.is_empty() {
-                        return Ok(None);
-                    }
-                }
-                None => return Ok(None),
-            }
-        }
-        "kingdom" => {
-            ancestor_parts = vec!["", "", "", "", ""];
-            match label_parts.get(1) {
-                Some(p) => {
-                    if p.is_empty() && label != classification::ANIMAL {
-                        return Ok(None);
-                    }
-                }
-                None => return Ok(None),
-            }
-        }
-        _ => {
-            return Err(Error::InvalidTaxonomyLevel(taxonomy_level.to_string()));
-        }
-    }
+    Ok(ancestor_at_rank(&label, rank, taxonomy_map).cloned())
+}
 
-    let ancestor = ancestor_parts.join(";");
-    match taxonomy_map.get(&ancestor) {
-        Some(taxonomy) => Ok(Some(taxonomy.to_string())),
-        None => Ok(None),
-    }
+/// Same as [`get_ancestor_at_level`] on a parsed label.
+pub fn ancestor_at_rank<'a>(
+    label: &Label,
+    rank: Rank,
+    taxonomy_map: &'a HashMap<String, Label>,
+) -> Option<&'a Label> {
+    taxonomy_map.get(&label.ancestor_class_string(rank)?)
 }
 
 ///
@@ -138,12 +60,20 @@ pub fn get_ancestor_at_level(
 ///       String slice label for to extract the full class string.
 ///
 pub fn get_full_class_string(label: &str) -> Result<String, Error> {
-    let label_parts = label.split(';').collect::<Vec<_>>();
-    if label_parts.len() != 7 {
-        return Err(Error::InvalidLabel(
-            label_parts.len().to_string(),
-            label.to_string(),
-        ));
-    }
-    Ok(label_parts[1..6].join(";").to_string())
+    Ok(parse_label(label)?.full_class_string())
+}
+
+/// Parses a label of the classifier, see [`Label`].
+pub fn parse_label(label: &str) -> Result<Label, Error> {
+    label.parse().map_err(|e| match e {
+        CoreError::InvalidLabel(parts, label) => Error::InvalidLabel(parts.to_string(), label),
+        e => Error::GeofenceInvalidValue(e.to_string()),
+    })
+}
+
+/// Parses one of `species`, `genus`, `family`, `order`, `class` or `kingdom`.
+pub fn parse_rank(taxonomy_level: &str) -> Result<Rank, Error> {
+    taxonomy_level
+        .parse()
+        .map_err(|_| Error::InvalidTaxonomyLevel(taxonomy_level.to_string()))
 }
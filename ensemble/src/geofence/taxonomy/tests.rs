@@ -2,6 +2,8 @@ use std::collections::HashMap;
 use std::sync::LazyLock;
 
 use serde_json::json;
+use speciesnet_core::ensemble::PredictionSource;
+use speciesnet_core::taxonomy::{Label, Rank};
 
 use super::{get_ancestor_at_level, get_full_class_string};
 use crate::error::Error;
@@ -50,7 +52,7 @@ const SAND_CAT: &str =
     "e588253d-d61d-4149-a96c-8c245927a80f;mammalia;carnivora;felidae;felis;margarita;sand cat";
 const SAND_CAT_FC: &str = "mammalia;carnivora;felidae;felis;margarita";
 
-static TAXONOMY_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+static TAXONOMY_MAP: LazyLock<HashMap<String, Label>> = LazyLock::new(|| {
     let json = json!(
         {
             BLANK_FC: BLANK,
@@ -73,6 +75,10 @@ static TAXONOMY_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     serde_json::from_value(json).unwrap()
 });
 
+fn label(label: &str) -> Label {
+    label.parse().unwrap()
+}
+
 #[test]
 fn test_get_full_class_string_fn() -> Result<(), Error> {
     // Test BLANK/HUMAN/VEHICLE.
@@ -123,27 +129,27 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
     // Test all ancestors of LION
     assert_eq!(
         get_ancestor_at_level(LION, "species", &TAXONOMY_MAP)?,
-        Some(LION.to_string())
+        Some(label(LION))
     );
     assert_eq!(
         get_ancestor_at_level(LION, "genus", &TAXONOMY_MAP)?,
-        Some(PANTHERA_GENUS.to_string())
+        Some(label(PANTHERA_GENUS))
     );
     assert_eq!(
         get_ancestor_at_level(LION, "family", &TAXONOMY_MAP)?,
-        Some(FELIDAE_FAMILY.to_string())
+        Some(label(FELIDAE_FAMILY))
     );
     assert_eq!(
         get_ancestor_at_level(LION, "order", &TAXONOMY_MAP)?,
-        Some(CARNIVORA_ORDER.to_string())
+        Some(label(CARNIVORA_ORDER))
     );
     assert_eq!(
         get_ancestor_at_level(LION, "class", &TAXONOMY_MAP)?,
-        Some(MAMMALIA_CLASS.to_string())
+        Some(label(MAMMALIA_CLASS))
     );
     assert_eq!(
         get_ancestor_at_level(LION, "kingdom", &TAXONOMY_MAP)?,
-        Some(ANIMAL_KINGDOM.to_string())
+        Some(label(ANIMAL_KINGDOM))
     );
 
     // Test all ancestors of PANTHERA_GENUS
@@ -153,23 +159,23 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
     );
     assert_eq!(
         get_ancestor_at_level(PANTHERA_GENUS, "genus", &TAXONOMY_MAP)?,
-        Some(PANTHERA_GENUS.to_string())
+        Some(label(PANTHERA_GENUS))
     );
     assert_eq!(
         get_ancestor_at_level(PANTHERA_GENUS, "family", &TAXONOMY_MAP)?,
-        Some(FELIDAE_FAMILY.to_string())
+        Some(label(FELIDAE_FAMILY))
     );
     assert_eq!(
         get_ancestor_at_level(PANTHERA_GENUS, "order", &TAXONOMY_MAP)?,
-        Some(CARNIVORA_ORDER.to_string())
+        Some(label(CARNIVORA_ORDER))
     );
     assert_eq!(
         get_ancestor_at_level(PANTHERA_GENUS, "class", &TAXONOMY_MAP)?,
-        Some(MAMMALIA_CLASS.to_string())
+        Some(label(MAMMALIA_CLASS))
     );
     assert_eq!(
         get_ancestor_at_level(PANTHERA_GENUS, "kingdom", &TAXONOMY_MAP)?,
-        Some(ANIMAL_KINGDOM.to_string())
+        Some(label(ANIMAL_KINGDOM))
     );
 
     // Test all ancestors of FELIDAE_FAMILY
@@ -183,19 +189,19 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
     );
     assert_eq!(
         get_ancestor_at_level(FELIDAE_FAMILY, "family", &TAXONOMY_MAP)?,
-        Some(FELIDAE_FAMILY.to_string())
+        Some(label(FELIDAE_FAMILY))
     );
     assert_eq!(
         get_ancestor_at_level(FELIDAE_FAMILY, "order", &TAXONOMY_MAP)?,
-        Some(CARNIVORA_ORDER.to_string())
+        Some(label(CARNIVORA_ORDER))
     );
     assert_eq!(
         get_ancestor_at_level(FELIDAE_FAMILY, "class", &TAXONOMY_MAP)?,
-        Some(MAMMALIA_CLASS.to_string())
+        Some(label(MAMMALIA_CLASS))
     );
     assert_eq!(
         get_ancestor_at_level(FELIDAE_FAMILY, "kingdom", &TAXONOMY_MAP)?,
-        Some(ANIMAL_KINGDOM.to_string())
+        Some(label(ANIMAL_KINGDOM))
     );
 
     // Test all ancestors of CARNIVORA_ORDER
@@ -213,15 +219,15 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
     );
     assert_eq!(
         get_ancestor_at_level(CARNIVORA_ORDER, "order", &TAXONOMY_MAP)?,
-        Some(CARNIVORA_ORDER.to_string())
+        Some(label(CARNIVORA_ORDER))
     );
     assert_eq!(
         get_ancestor_at_level(CARNIVORA_ORDER, "class", &TAXONOMY_MAP)?,
-        Some(MAMMALIA_CLASS.to_string())
+        Some(label(MAMMALIA_CLASS))
     );
     assert_eq!(
         get_ancestor_at_level(CARNIVORA_ORDER, "kingdom", &TAXONOMY_MAP)?,
-        Some(ANIMAL_KINGDOM.to_string())
+        Some(label(ANIMAL_KINGDOM))
     );
 
     // Test all ancestors of MAMMALIA_CLASS
@@ -243,11 +249,11 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
     );
     assert_eq!(
         get_ancestor_at_level(MAMMALIA_CLASS, "class", &TAXONOMY_MAP)?,
-        Some(MAMMALIA_CLASS.to_string())
+        Some(label(MAMMALIA_CLASS))
     );
     assert_eq!(
         get_ancestor_at_level(MAMMALIA_CLASS, "kingdom", &TAXONOMY_MAP)?,
-        Some(ANIMAL_KINGDOM.to_string())
+        Some(label(ANIMAL_KINGDOM))
     );
 
     // Test all ancestors of ANIMAL_KINGDOM
@@ -273,7 +279,7 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
     );
     assert_eq!(
         get_ancestor_at_level(ANIMAL_KINGDOM, "kingdom", &TAXONOMY_MAP)?,
-        Some(ANIMAL_KINGDOM.to_string())
+        Some(label(ANIMAL_KINGDOM))
     );
 
     // Test all ancestors of BLANK
@@ -294,18 +300,18 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
     // the mock taxonomy mapping
     assert_eq!(
         get_ancestor_at_level(HUMAN, "species", &TAXONOMY_MAP)?,
-        Some(HUMAN.to_string())
+        Some(label(HUMAN))
     );
     assert_eq!(get_ancestor_at_level(HUMAN, "genus", &TAXONOMY_MAP)?, None);
     assert_eq!(get_ancestor_at_level(HUMAN, "family", &TAXONOMY_MAP)?, None);
     assert_eq!(get_ancestor_at_level(HUMAN, "order", &TAXONOMY_MAP)?, None);
     assert_eq!(
         get_ancestor_at_level(HUMAN, "class", &TAXONOMY_MAP)?,
-        Some(MAMMALIA_CLASS.to_string())
+        Some(label(MAMMALIA_CLASS))
     );
     assert_eq!(
         get_ancestor_at_level(HUMAN, "kingdom", &TAXONOMY_MAP)?,
-        Some(ANIMAL_KINGDOM.to_string())
+        Some(label(ANIMAL_KINGDOM))
     );
 
     // Test all ancestors of VEHICLE
@@ -358,7 +364,7 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
     );
     assert_eq!(
         get_ancestor_at_level(unseen_species, "kingdom", &TAXONOMY_MAP)?,
-        Some(ANIMAL_KINGDOM.to_string())
+        Some(label(ANIMAL_KINGDOM))
     );
 
     // Test invalid labels
@@ -404,3 +410,39 @@ fn test_get_ancestor_at_level_fn() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_label_and_source_round_trip() -> Result<(), Error> {
+    assert_eq!(label(LION).rank(), Some(Rank::Species));
+    assert_eq!(label(PANTHERA_GENUS).rank(), Some(Rank::Genus));
+    assert_eq!(label(MAMMALIA_CLASS).rank(), Some(Rank::Class));
+    assert_eq!(label(ANIMAL_KINGDOM).rank(), Some(Rank::Kingdom));
+    assert_eq!(label(BLANK).rank(), None);
+    assert_eq!(label(LION).common_name(), "lion");
+    assert!(label(LION).uuid().is_some());
+    assert!(label("unknown;unknown;abc;def;;;").uuid().is_none());
+
+    // Labels and sources are written exactly as they were read.
+    let labels = json!([LION, BLANK, "unknown;unknown;abc;def;;;"]);
+    let parsed: Vec<Label> = serde_json::from_value(labels.clone())?;
+    assert_eq!(serde_json::to_value(&parsed)?, labels);
+    assert_eq!(parsed[0], LION);
+
+    let sources = json!([
+        "detector",
+        "classifier",
+        "classifier+rollup_to_genus",
+        "classifier+geofence+rollup_to_family",
+        "classifier+geofence+rollup_failed",
+    ]);
+    let parsed: Vec<PredictionSource> = serde_json::from_value(sources.clone())?;
+    assert_eq!(parsed[2], PredictionSource::Rollup(Rank::Genus));
+    assert_eq!(serde_json::to_value(&parsed)?, sources);
+    assert!(
+        "classifier+rollup_to_tribe"
+            .parse::<PredictionSource>()
+            .is_err()
+    );
+
+    Ok(())
+}
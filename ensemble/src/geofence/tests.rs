@@ -6,10 +6,14 @@ use std::sync::LazyLock;
 
 use serde_json::json;
 use speciesnet_core::constants::classification;
+use speciesnet_core::ensemble::trace::{GeofenceEntry, GeofenceReason};
+use speciesnet_core::ensemble::{EnsembleTrace, PredictionSource};
+use speciesnet_core::taxonomy::Label;
 
 use super::{
     GeofenceResult, fix_geofence_base, geofence_animal_classification,
-    roll_up_labels_to_first_matching_level, should_geofence,
+    geofence_animal_classification_with_trace, roll_up_labels_to_first_matching_level,
+    should_geofence,
 };
 use crate::error::Error;
 
@@ -109,7 +113,7 @@ static GEOFENCE_MAP: LazyLock<HashMap<String, HashMap<String, HashMap<String, Ve
         serde_json::from_value(json).unwrap()
     });
 
-static TAXONOMY_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+static TAXONOMY_MAP: LazyLock<HashMap<String, Label>> = LazyLock::new(|| {
     let json = json!(
         {
             BLANK_FC: BLANK,
@@ -132,6 +136,14 @@ static TAXONOMY_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     serde_json::from_value(json).unwrap()
 });
 
+fn label(label: &str) -> Label {
+    label.parse().unwrap()
+}
+
+fn source(source: &str) -> PredictionSource {
+    source.parse().unwrap()
+}
+
 #[test]
 fn test_should_geofence_fn() -> Result<(), Error> {
     // Test disable geofencing
@@ -253,9 +265,9 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     assert_eq!(
         rollup_fn(&[1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0])?,
         Some((
-            BROWN_BEAR.to_string(),
+            label(BROWN_BEAR),
             1.0,
-            "classifier+rollup_to_species".to_string()
+            source("classifier+rollup_to_species")
         ))
     );
 
@@ -277,9 +289,9 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     assert_eq!(
         rollup_fn(&[0.7, 0.25, 0.01, 0.01, 0.01, 0.01, 0.01])?,
         Some((
-            URSUS_GENUS.to_string(),
+            label(URSUS_GENUS),
             0.95,
-            "classifier+rollup_to_genus".to_string()
+            source("classifier+rollup_to_genus")
         ))
     );
 
@@ -301,9 +313,9 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     assert_eq!(
         rollup_fn(&[0.4, 0.21, 0.2, 0.0, 0.0, 0.0, 0.0])?,
         Some((
-            URSIDAE_FAMILY.to_string(),
+            label(URSIDAE_FAMILY),
             0.81,
-            "classifier+rollup_to_family".to_string()
+            source("classifier+rollup_to_family")
         ))
     );
 
@@ -326,9 +338,9 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     assert_eq!(
         rollup_fn(&[0.3, 0.2, 0.1, 0.1, 0.23, 0.0, 0.0])?,
         Some((
-            CARNIVORA_ORDER.to_string(),
+            label(CARNIVORA_ORDER),
             [0.3, 0.2, 0.1, 0.23].iter().sum(),
-            "classifier+rollup_to_order".to_string()
+            source("classifier+rollup_to_order")
         ))
     );
 
@@ -351,9 +363,9 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     assert_eq!(
         rollup_fn(&[0.2, 0.2, 0.1, 0.1, 0.22, 0.1, 0.0])?,
         Some((
-            MAMMALIA_CLASS.to_string(),
+            label(MAMMALIA_CLASS),
             [0.2, 0.2, 0.1, 0.1, 0.22].iter().sum(),
-            "classifier+rollup_to_class".to_string()
+            source("classifier+rollup_to_class")
         ))
     );
 
@@ -376,9 +388,9 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     assert_eq!(
         rollup_fn(&[0.2, 0.2, 0.1, 0.1, 0.23, 0.1, 0.1])?,
         Some((
-            ANIMAL_KINGDOM.to_string(),
+            label(ANIMAL_KINGDOM),
             [0.2, 0.2, 0.1, 0.1, 0.23, 0.1].iter().sum(),
-            "classifier+rollup_to_kingdom".to_string()
+            source("classifier+rollup_to_kingdom")
         ))
     );
 
@@ -406,9 +418,9 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     assert_eq!(
         rollup_fn(&[0.6, 0.1, 0.1, 0.1, 0.1, 0.0, 0.0])?,
         Some((
-            URSIDAE_FAMILY.to_string(),
+            label(URSIDAE_FAMILY),
             [0.6, 0.1, 0.1].iter().sum(),
-            "classifier+rollup_to_family".to_string()
+            source("classifier+rollup_to_family")
         ))
     );
 
@@ -428,11 +440,7 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     };
     assert_eq!(
         rollup_fn(&[0.2, 0.3, 0.15, 0.0, 0.35, 0.0, 0.0])?,
-        Some((
-            LION.to_string(),
-            0.35,
-            "classifier+rollup_to_species".to_string()
-        ))
+        Some((label(LION), 0.35, source("classifier+rollup_to_species")))
     );
 
     // Test rollups with geofencing
@@ -459,9 +467,9 @@ fn test_roll_up_labels_to_first_matching_level_fn() -> Result<(), Error> {
     assert_eq!(
         rollup_fn(&[0.1, 0.2, 0.2, 0.45, 0.0, 0.0, 0.0])?,
         Some((
-            CARNIVORA_ORDER.to_string(),
+            label(CARNIVORA_ORDER),
             [0.1, 0.2, 0.2].iter().sum(),
-            "classifier+rollup_to_order".to_string()
+            source("classifier+rollup_to_order")
         ))
     );
 
@@ -508,7 +516,7 @@ fn test_geofence_animal_classification_fn() -> Result<(), Error> {
     };
     assert_eq!(
         geofence_classification_fn(&[0.4, 0.3, 0.2, 0.1])?,
-        GeofenceResult::new(LION.to_string(), 0.4, "classifier".to_string())
+        GeofenceResult::new(label(LION), 0.4, source("classifier"))
     );
 
     //Test with geofencing and rollup to family level or above
@@ -526,9 +534,9 @@ fn test_geofence_animal_classification_fn() -> Result<(), Error> {
     assert_eq!(
         geofence_classification_fn(&[0.4, 0.3, 0.2, 0.1])?,
         GeofenceResult::new(
-            FELIDAE_FAMILY.to_string(),
+            label(FELIDAE_FAMILY),
             0.5,
-            "classifier+geofence+rollup_to_family".to_string()
+            source("classifier+geofence+rollup_to_family")
         )
     );
     let geofence_classification_fn = |scores| {
@@ -545,9 +553,9 @@ fn test_geofence_animal_classification_fn() -> Result<(), Error> {
     assert_eq!(
         geofence_classification_fn(&[0.4, 0.3, 0.2, 0.1])?,
         GeofenceResult::new(
-            CARNIVORA_ORDER.to_string(),
+            label(CARNIVORA_ORDER),
             [0.4, 0.3, 0.1].iter().sum(),
-            "classifier+geofence+rollup_to_order".to_string()
+            source("classifier+geofence+rollup_to_order")
         )
     );
 
@@ -566,15 +574,73 @@ fn test_geofence_animal_classification_fn() -> Result<(), Error> {
     assert_eq!(
         geofence_classification_fn(&[0.4, 0.3, 0.2, 0.1])?,
         GeofenceResult::new(
-            classification::UNKNOWN.to_string(),
+            label(classification::UNKNOWN),
             0.4,
-            "classifier+geofence+rollup_failed".to_string()
+            source("classifier+geofence+rollup_failed")
         )
     );
 
     Ok(())
 }
 
+#[test]
+fn test_geofence_animal_classification_trace() -> Result<(), Error> {
+    let labels = vec![
+        LION.to_string(),
+        POLAR_BEAR.to_string(),
+        BLANK.to_string(),
+        FELIDAE_FAMILY.to_string(),
+    ];
+    let mut trace = EnsembleTrace::default();
+
+    let result = geofence_animal_classification_with_trace(
+        &labels,
+        &[0.4, 0.3, 0.2, 0.1],
+        Some("USA"),
+        Some("NY"),
+        &TAXONOMY_MAP,
+        &GEOFENCE_MAP,
+        true,
+        Some(&mut trace),
+    )?;
+    assert_eq!(*result.label(), CARNIVORA_ORDER);
+
+    // The lion is not allowed in the USA, the cat family is blocked in NY, and the bear family
+    // has no say on the USA but falls below the threshold.
+    let lookups: Vec<(String, GeofenceReason)> = trace
+        .geofence_lookups
+        .iter()
+        .map(|lookup| (lookup.label.to_string(), lookup.reason))
+        .collect();
+    assert_eq!(
+        lookups,
+        [
+            (LION.to_string(), GeofenceReason::CountryNotAllowed),
+            (FELIDAE_FAMILY.to_string(), GeofenceReason::RegionBlocked),
+            (URSIDAE_FAMILY.to_string(), GeofenceReason::Allowed),
+            (CARNIVORA_ORDER.to_string(), GeofenceReason::NoRules),
+        ]
+    );
+    assert_eq!(
+        trace.geofence_lookups[1].entry,
+        Some(GeofenceEntry {
+            rule: "block".to_string(),
+            country: "USA".to_string(),
+            regions: vec!["NY".to_string()],
+        })
+    );
+
+    assert_eq!(trace.rollups.len(), 2);
+    assert_eq!(trace.rollups[0].level, "family");
+    assert_eq!(trace.rollups[0].scores[0].label, FELIDAE_FAMILY);
+    assert_eq!(trace.rollups[0].label, Some(label(URSIDAE_FAMILY)));
+    assert!(!trace.rollups[0].passed);
+    assert_eq!(trace.rollups[1].level, "order");
+    assert!(trace.rollups[1].passed);
+
+    Ok(())
+}
+
 #[test]
 fn test_fix_geofence_base_fn() -> Result<(), Error> {
     let fix_path = current_dir()?
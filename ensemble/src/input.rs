@@ -7,7 +7,7 @@ use serde::Deserialize;
 use speciesnet_core::{
     classifier::ClassificationBundle,
     detector::Detection,
-    io::{Instances, Predictions},
+    io::{Instance, Instances, Predictions},
 };
 
 use crate::error::Error;
@@ -33,6 +33,20 @@ impl EnsembleInput {
         let instance_file = BufReader::new(File::open(instances_path)?);
         let instance_outputs: Instances = serde_json::from_reader(instance_file)?;
 
+        Self::from_instances(
+            instance_outputs.instances(),
+            detector_output_path,
+            classifier_output_path,
+        )
+    }
+
+    /// Same as [`EnsembleInput::from`] with instances which have already been read, e.g. after
+    /// filling in their default locations.
+    pub fn from_instances<P: AsRef<Path>>(
+        instances: &[Instance],
+        detector_output_path: P,
+        classifier_output_path: P,
+    ) -> Result<Vec<Self>, Error> {
         let detector_file = BufReader::new(File::open(detector_output_path)?);
         let detector_outputs: Predictions = serde_json::from_reader(detector_file)?;
 
@@ -72,10 +86,15 @@ impl EnsembleInput {
             let path_value = path_map
                 .entry(prediction.file_path().to_path_buf())
                 .or_insert((None, None, &None, &None));
-            path_value.3 = prediction.classifications()
+            path_value.3 = prediction.classifications();
+
+            // The classifier keeps the detections when they have been classified on their own.
+            if prediction.detections().is_some() {
+                path_value.2 = prediction.detections();
+            }
         }
 
-        for instance in instance_outputs.instances() {
+        for instance in instances {
             let path_value = path_map
                 .entry(instance.file_path().to_path_buf())
                 .or_insert((None, None, &None, &None));
@@ -1,33 +1,38 @@
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-    path::Path,
-};
+use std::path::Path;
 
 use speciesnet_core::{
     classifier::ClassificationBundle,
-    constants::{classification, source},
+    constants::classification,
     detector::{Category, Detection},
-    ensemble::GeofenceResult,
+    ensemble::{EnsembleTrace, GeofenceResult, PredictionSource, trace::Operator},
+    taxonomy::Label,
 };
 
 use crate::{
+    config::EnsembleConfig,
     error::Error,
-    geofence::{
-        fix_geofence_base, geofence_animal_classification, roll_up_labels_to_first_matching_level,
-        taxonomy::get_full_class_string,
-    },
+    explain::Rule,
+    geofence::{Geofence, geofence_animal_classification_with_trace, roll_up_labels_with_trace},
+    taxonomy::Taxonomy,
 };
 
+pub mod config;
 pub mod error;
+mod explain;
 pub mod geofence;
 pub mod input;
+pub mod taxonomy;
+
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug, Clone)]
 pub struct SpeciesNetEnsemble {
-    geofence_map: HashMap<String, HashMap<String, HashMap<String, Vec<String>>>>,
-    taxonomy_map: HashMap<String, String>,
+    geofence: Geofence,
+    taxonomy: Taxonomy,
+    config: EnsembleConfig,
+    enable_geofence: bool,
+    explain: bool,
 }
 
 impl SpeciesNetEnsemble {
@@ -35,56 +40,202 @@ impl SpeciesNetEnsemble {
         geofence_base_path: P,
         taxonomy_path: P,
         geofence_fix_path: Option<P>,
+        config: EnsembleConfig,
     ) -> Result<Self, Error> {
+        config.validate()?;
+
         // Load geofence and fix
-        let geofence_file = File::open(geofence_base_path)?;
-        let geofence_reader = BufReader::new(geofence_file);
-        let geofence_map: HashMap<String, HashMap<String, HashMap<String, Vec<String>>>> =
-            serde_json::from_reader(geofence_reader)?;
-
-        let fixed_geofence_map = match geofence_fix_path {
-            Some(p) => fix_geofence_base(&geofence_map, p)?,
-            None => geofence_map,
-        };
+        let geofence = Geofence::from_file(geofence_base_path, geofence_fix_path)?;
 
         // Load taxonomy
-        let taxonomy_file = File::open(taxonomy_path)?;
-        let taxonomy_reader = BufReader::new(taxonomy_file);
-        let taxonomies: Vec<String> = taxonomy_reader.lines().map_while(Result::ok).collect();
-        let mut taxonomy_map: HashMap<String, String> = HashMap::new();
-        for t in taxonomies {
-            if ![
-                classification::BLANK.to_string(),
-                classification::VEHICLE.to_string(),
-                classification::UNKNOWN.to_string(),
-            ]
-            .contains(&t)
-            {
-                taxonomy_map.insert(get_full_class_string(&t)?, t.clone());
-            }
-        }
+        let taxonomy = Taxonomy::from_file(taxonomy_path)?;
 
         Ok(Self {
-            geofence_map: fixed_geofence_map,
-            taxonomy_map,
+            geofence,
+            taxonomy,
+            config,
+            enable_geofence: true,
+            explain: false,
         })
     }
 
+    /// Sets whether the animal classifications are geofenced by the country and admin1 region of
+    /// the images. Geofencing is enabled by default, following the Python implementation.
+    pub fn set_enable_geofence(&mut self, enable_geofence: bool) -> &mut Self {
+        self.enable_geofence = enable_geofence;
+        self
+    }
+
+    /// Returns whether geofencing is enabled when running [`SpeciesNetEnsemble::ensemble`].
+    pub fn enable_geofence(&self) -> bool {
+        self.enable_geofence
+    }
+
+    /// Sets whether the results of [`SpeciesNetEnsemble::ensemble`] carry an [`EnsembleTrace`] of
+    /// the rules which were evaluated, the geofence lookups and the rollups which led to them.
+    /// Disabled by default.
+    pub fn set_explain(&mut self, explain: bool) -> &mut Self {
+        self.explain = explain;
+        self
+    }
+
+    /// Returns whether the explain mode is enabled.
+    pub fn explain(&self) -> bool {
+        self.explain
+    }
+
+    /// Returns the geofence the animal classifications are checked against, with the fixes
+    /// applied.
+    pub fn geofence(&self) -> &Geofence {
+        &self.geofence
+    }
+
+    /// Returns the geofence for editing, e.g. to apply more fixes or to allow and block labels
+    /// before running the ensemble.
+    pub fn geofence_mut(&mut self) -> &mut Geofence {
+        &mut self.geofence
+    }
+
+    /// Returns the taxonomy the classifications are rolled up with. It does not know the labels
+    /// of the classifier, see [`Taxonomy::from_files`] to load them as well.
+    pub fn taxonomy(&self) -> &Taxonomy {
+        &self.taxonomy
+    }
+
+    /// Returns the thresholds used by the ensemble.
+    pub fn config(&self) -> &EnsembleConfig {
+        &self.config
+    }
+
+    /// Runs the ensemble on the given detections and classifications of an image, geofencing the
+    /// result when it is enabled on the ensemble.
     pub fn ensemble(
         &self,
         detections: &[Detection],
         classifications: &ClassificationBundle,
         country: Option<String>,
         admin1_region: Option<String>,
+    ) -> Result<GeofenceResult, Error> {
+        self.ensemble_with_geofence(
+            detections,
+            classifications,
+            country,
+            admin1_region,
+            self.enable_geofence,
+        )
+    }
+
+    /// Same as [`SpeciesNetEnsemble::ensemble`], with geofencing enabled or disabled for this call
+    /// only. The returned result records which one was used.
+    pub fn ensemble_with_geofence(
+        &self,
+        detections: &[Detection],
+        classifications: &ClassificationBundle,
+        country: Option<String>,
+        admin1_region: Option<String>,
+        enable_geofence: bool,
+    ) -> Result<GeofenceResult, Error> {
+        let mut result = self.run_ensemble(
+            detections,
+            classifications,
+            country,
+            admin1_region,
+            enable_geofence,
+            self.explain,
+        )?;
+        result.set_enable_geofence(Some(enable_geofence));
+
+        Ok(result)
+    }
+
+    /// Runs the ensemble on each detection which has been classified on its own, as if its crop
+    /// was an image with that single detection, and returns the distinct labels found in the order
+    /// of the detections. Blank and unknown labels are left out, so mixed-species images list every
+    /// species which is present.
+    pub fn species_present(
+        &self,
+        detections: &[Detection],
+        country: Option<String>,
+        admin1_region: Option<String>,
+    ) -> Result<Vec<String>, Error> {
+        let mut species: Vec<String> = Vec::new();
+
+        for detection in detections {
+            let Some(classifications) = detection.classifications() else {
+                continue;
+            };
+
+            // Only the label is kept, so the detections are not explained.
+            let result = self.run_ensemble(
+                std::slice::from_ref(detection),
+                classifications,
+                country.clone(),
+                admin1_region.clone(),
+                self.enable_geofence,
+                false,
+            )?;
+
+            if *result.label() == classification::BLANK
+                || *result.label() == classification::UNKNOWN
+            {
+                continue;
+            }
+
+            if !species.iter().any(|label| *result.label() == **label) {
+                species.push(result.label().to_string());
+            }
+        }
+
+        Ok(species)
+    }
+
+    fn run_ensemble(
+        &self,
+        detections: &[Detection],
+        classifications: &ClassificationBundle,
+        country: Option<String>,
+        admin1_region: Option<String>,
+        enable_geofence: bool,
+        explain: bool,
+    ) -> Result<GeofenceResult, Error> {
+        let mut trace = explain.then(EnsembleTrace::default);
+
+        let mut result = self.run_rules(
+            detections,
+            classifications,
+            country,
+            admin1_region,
+            enable_geofence,
+            trace.as_mut(),
+        )?;
+        result.set_trace(trace);
+
+        Ok(result)
+    }
+
+    /// Goes through the rules of the ensemble until one of them fires, recording them into the
+    /// trace when one is given.
+    fn run_rules(
+        &self,
+        detections: &[Detection],
+        classifications: &ClassificationBundle,
+        country: Option<String>,
+        admin1_region: Option<String>,
+        enable_geofence: bool,
+        mut trace: Option<&mut EnsembleTrace>,
     ) -> Result<GeofenceResult, Error> {
         if classifications.scores().is_empty() || classifications.labels().is_empty() {
             return Err(Error::EmptyClassifications);
         }
 
+        let config = &self.config;
+
         let top_classification_class = classifications.labels().first().unwrap();
-        let classes = classifications.labels();
         let top_classification_score = *classifications.scores().first().unwrap();
-        let scores = classifications.scores();
+        // The rollups sum over the retained scores when the classifier kept more than the top
+        // classifications.
+        let classes = classifications.rollup_classifications().labels();
+        let scores = classifications.rollup_classifications().scores();
         let top_detection_class = if detections.is_empty() {
             Category::Animal
         } else {
@@ -96,169 +247,325 @@ impl SpeciesNetEnsemble {
             detections.first().unwrap().confidence()
         };
 
-        if top_detection_class == Category::Human {
-            // Threshold #1a: high-confidence HUMAN detections.
-            if top_detection_score > 0.7 {
-                return Ok(GeofenceResult::new(
-                    classification::HUMAN.to_string(),
-                    top_detection_score,
-                    source::DETECTOR.to_string(),
-                ));
-            }
+        // Threshold #1a: high-confidence HUMAN detections.
+        if Rule::new(trace.as_deref_mut())
+            .category(top_detection_class, Category::Human)
+            .score(
+                "top_detection_score",
+                top_detection_score,
+                Operator::GreaterThan,
+                "human_detection_threshold",
+                config.human_detection_threshold,
+            )
+            .check("1a", "high-confidence HUMAN detections")
+        {
+            return Ok(GeofenceResult::new(
+                Label::human(),
+                top_detection_score,
+                PredictionSource::Detector,
+            ));
+        }
 
-            // Threshold #1b: mid-confidence HUMAN detections + high-confidence
-            // HUMAN/VEHICLE classifications.
-            if top_detection_score > 0.2
-                && [
-                    classification::HUMAN.to_string(),
-                    classification::VEHICLE.to_string(),
-                ]
-                .contains(top_classification_class)
-                && top_classification_score > 0.5
-            {
-                return Ok(GeofenceResult::new(
-                    classification::HUMAN.to_string(),
-                    top_classification_score,
-                    source::CLASSIFIER.to_string(),
-                ));
-            }
+        // Threshold #1b: mid-confidence HUMAN detections + high-confidence
+        // HUMAN/VEHICLE classifications.
+        if Rule::new(trace.as_deref_mut())
+            .category(top_detection_class, Category::Human)
+            .score(
+                "top_detection_score",
+                top_detection_score,
+                Operator::GreaterThan,
+                "human_mid_detection_threshold",
+                config.human_mid_detection_threshold,
+            )
+            .label(
+                "top_classification_class",
+                top_classification_class,
+                true,
+                &[classification::HUMAN, classification::VEHICLE],
+            )
+            .score(
+                "top_classification_score",
+                top_classification_score,
+                Operator::GreaterThan,
+                "human_classification_threshold",
+                config.human_classification_threshold,
+            )
+            .check(
+                "1b",
+                "mid-confidence HUMAN detections + high-confidence HUMAN/VEHICLE classifications",
+            )
+        {
+            return Ok(GeofenceResult::new(
+                Label::human(),
+                top_classification_score,
+                PredictionSource::Classifier,
+            ));
         }
 
-        if top_detection_class == Category::Vehicle {
-            // Threshold #2a: mid-confidence VEHICLE detections + high-confidence HUMAN
-            // classifications.
-            if top_detection_score > 0.2
-                && top_classification_class == &classification::HUMAN.to_string()
-                && top_classification_score > 0.5
-            {
-                return Ok(GeofenceResult::new(
-                    classification::HUMAN.to_string(),
-                    top_classification_score,
-                    source::CLASSIFIER.to_string(),
-                ));
-            }
+        // Threshold #2a: mid-confidence VEHICLE detections + high-confidence HUMAN
+        // classifications.
+        if Rule::new(trace.as_deref_mut())
+            .category(top_detection_class, Category::Vehicle)
+            .score(
+                "top_detection_score",
+                top_detection_score,
+                Operator::GreaterThan,
+                "vehicle_mid_detection_threshold",
+                config.vehicle_mid_detection_threshold,
+            )
+            .label(
+                "top_classification_class",
+                top_classification_class,
+                true,
+                &[classification::HUMAN],
+            )
+            .score(
+                "top_classification_score",
+                top_classification_score,
+                Operator::GreaterThan,
+                "human_classification_threshold",
+                config.human_classification_threshold,
+            )
+            .check(
+                "2a",
+                "mid-confidence VEHICLE detections + high-confidence HUMAN classifications",
+            )
+        {
+            return Ok(GeofenceResult::new(
+                Label::human(),
+                top_classification_score,
+                PredictionSource::Classifier,
+            ));
+        }
 
-            // Threshold #2b: high-confidence VEHICLE detections.
-            if top_detection_score > 0.7 {
-                return Ok(GeofenceResult::new(
-                    classification::VEHICLE.to_string(),
-                    top_detection_score,
-                    source::DETECTOR.to_string(),
-                ));
-            }
+        // Threshold #2b: high-confidence VEHICLE detections.
+        if Rule::new(trace.as_deref_mut())
+            .category(top_detection_class, Category::Vehicle)
+            .score(
+                "top_detection_score",
+                top_detection_score,
+                Operator::GreaterThan,
+                "vehicle_detection_threshold",
+                config.vehicle_detection_threshold,
+            )
+            .check("2b", "high-confidence VEHICLE detections")
+        {
+            return Ok(GeofenceResult::new(
+                Label::vehicle(),
+                top_detection_score,
+                PredictionSource::Detector,
+            ));
+        }
 
-            // Threshold #2c: mid-confidence VEHICLE detections + high-confidence VEHICLE
-            // classifications.
-            if top_detection_score > 0.2
-                && top_classification_class == &classification::VEHICLE.to_string()
-                && top_classification_score > 0.4
-            {
-                return Ok(GeofenceResult::new(
-                    classification::VEHICLE.to_string(),
-                    top_classification_score,
-                    source::CLASSIFIER.to_string(),
-                ));
-            }
+        // Threshold #2c: mid-confidence VEHICLE detections + high-confidence VEHICLE
+        // classifications.
+        if Rule::new(trace.as_deref_mut())
+            .category(top_detection_class, Category::Vehicle)
+            .score(
+                "top_detection_score",
+                top_detection_score,
+                Operator::GreaterThan,
+                "vehicle_mid_detection_threshold",
+                config.vehicle_mid_detection_threshold,
+            )
+            .label(
+                "top_classification_class",
+                top_classification_class,
+                true,
+                &[classification::VEHICLE],
+            )
+            .score(
+                "top_classification_score",
+                top_classification_score,
+                Operator::GreaterThan,
+                "vehicle_classification_threshold",
+                config.vehicle_classification_threshold,
+            )
+            .check(
+                "2c",
+                "mid-confidence VEHICLE detections + high-confidence VEHICLE classifications",
+            )
+        {
+            return Ok(GeofenceResult::new(
+                Label::vehicle(),
+                top_classification_score,
+                PredictionSource::Classifier,
+            ));
         }
 
         // Threshold #3a: high-confidence BLANK "detections" + high-confidence BLANK
         // classifications.
-        if top_detection_score < 0.2
-            && top_classification_class == &classification::BLANK.to_string()
-            && top_classification_score > 0.5
+        if Rule::new(trace.as_deref_mut())
+            .score(
+                "top_detection_score",
+                top_detection_score,
+                Operator::LessThan,
+                "blank_detection_threshold",
+                config.blank_detection_threshold,
+            )
+            .label(
+                "top_classification_class",
+                top_classification_class,
+                true,
+                &[classification::BLANK],
+            )
+            .score(
+                "top_classification_score",
+                top_classification_score,
+                Operator::GreaterThan,
+                "blank_classification_threshold",
+                config.blank_classification_threshold,
+            )
+            .check(
+                "3a",
+                "high-confidence BLANK \"detections\" + high-confidence BLANK classifications",
+            )
         {
             return Ok(GeofenceResult::new(
-                classification::BLANK.to_string(),
+                Label::blank(),
                 top_classification_score,
-                source::CLASSIFIER.to_string(),
+                PredictionSource::Classifier,
             ));
         }
 
         // Threshold #3b: extra-high-confidence BLANK classifications.
-        if top_classification_class == &classification::BLANK.to_string()
-            && top_classification_score > 0.99
+        if Rule::new(trace.as_deref_mut())
+            .label(
+                "top_classification_class",
+                top_classification_class,
+                true,
+                &[classification::BLANK],
+            )
+            .score(
+                "top_classification_score",
+                top_classification_score,
+                Operator::GreaterThan,
+                "blank_high_classification_threshold",
+                config.blank_high_classification_threshold,
+            )
+            .check("3b", "extra-high-confidence BLANK classifications")
         {
             return Ok(GeofenceResult::new(
-                classification::BLANK.to_string(),
+                Label::blank(),
                 top_classification_score,
-                source::CLASSIFIER.to_string(),
+                PredictionSource::Classifier,
             ));
         }
 
-        if ![
-            classification::BLANK.to_string(),
-            classification::HUMAN.to_string(),
-            classification::VEHICLE.to_string(),
-        ]
-        .contains(top_classification_class)
-        {
-            // Threshold #4a: extra-high-confidence ANIMAL classifications.
-            if top_classification_score > 0.8 {
-                return geofence_animal_classification(
-                    classes,
-                    scores,
-                    country.as_deref(),
-                    admin1_region.as_deref(),
-                    &self.taxonomy_map,
-                    &self.geofence_map,
-                    true,
-                );
-            }
+        let non_animal_classes = [
+            classification::BLANK,
+            classification::HUMAN,
+            classification::VEHICLE,
+        ];
 
-            // Threshold #4b: high-confidence ANIMAL classifications + mid-confidence
-            // ANIMAL detections.
-            if top_classification_score > 0.65
-                && top_detection_class == Category::Animal
-                && top_detection_score > 0.2
-            {
-                return geofence_animal_classification(
-                    classes,
-                    scores,
-                    country.as_deref(),
-                    admin1_region.as_deref(),
-                    &self.taxonomy_map,
-                    &self.geofence_map,
-                    true,
-                );
-            }
+        // Threshold #4a: extra-high-confidence ANIMAL classifications.
+        // Threshold #4b: high-confidence ANIMAL classifications + mid-confidence
+        // ANIMAL detections.
+        if Rule::new(trace.as_deref_mut())
+            .label(
+                "top_classification_class",
+                top_classification_class,
+                false,
+                &non_animal_classes,
+            )
+            .score(
+                "top_classification_score",
+                top_classification_score,
+                Operator::GreaterThan,
+                "animal_high_classification_threshold",
+                config.animal_high_classification_threshold,
+            )
+            .check("4a", "extra-high-confidence ANIMAL classifications")
+            || Rule::new(trace.as_deref_mut())
+                .label(
+                    "top_classification_class",
+                    top_classification_class,
+                    false,
+                    &non_animal_classes,
+                )
+                .score(
+                    "top_classification_score",
+                    top_classification_score,
+                    Operator::GreaterThan,
+                    "animal_classification_threshold",
+                    config.animal_classification_threshold,
+                )
+                .category(top_detection_class, Category::Animal)
+                .score(
+                    "top_detection_score",
+                    top_detection_score,
+                    Operator::GreaterThan,
+                    "animal_detection_threshold",
+                    config.animal_detection_threshold,
+                )
+                .check(
+                    "4b",
+                    "high-confidence ANIMAL classifications + mid-confidence ANIMAL detections",
+                )
+        {
+            return geofence_animal_classification_with_trace(
+                classes,
+                scores,
+                country.as_deref(),
+                admin1_region.as_deref(),
+                self.taxonomy.class_map(),
+                self.geofence.map(),
+                enable_geofence,
+                trace,
+            );
         }
 
         // Threshold #5a: high-confidence ANIMAL rollups.
-        let roll_up = roll_up_labels_to_first_matching_level(
+        let roll_up = roll_up_labels_with_trace(
             classes,
             scores,
             country.as_deref(),
             admin1_region.as_deref(),
-            &vec![
-                "genus".to_string(),
-                "family".to_string(),
-                "order".to_string(),
-                "class".to_string(),
-                "kingdom".to_string(),
-            ],
-            &0.65,
-            &self.taxonomy_map,
-            &self.geofence_map,
-            true,
+            &config.rollup_levels,
+            &config.rollup_threshold,
+            self.taxonomy.class_map(),
+            self.geofence.map(),
+            enable_geofence,
+            trace.as_deref_mut(),
         )?;
 
-        if let Some((label, score, source)) = roll_up {
-            return Ok(GeofenceResult::new(label, score, source));
+        if Rule::new(trace.as_deref_mut())
+            .require(roll_up.is_some())
+            .check("5a", "high-confidence ANIMAL rollups")
+            && let Some((label, score, rank)) = roll_up
+        {
+            return Ok(GeofenceResult::new(
+                label,
+                score,
+                PredictionSource::Rollup(rank),
+            ));
         }
 
         // Threshold #5b: mid-confidence ANIMAL detections.
-        if top_detection_class == Category::Animal && top_detection_score > 0.5 {
+        if Rule::new(trace.as_deref_mut())
+            .category(top_detection_class, Category::Animal)
+            .score(
+                "top_detection_score",
+                top_detection_score,
+                Operator::GreaterThan,
+                "animal_detector_fallback_threshold",
+                config.animal_detector_fallback_threshold,
+            )
+            .check("5b", "mid-confidence ANIMAL detections")
+        {
             return Ok(GeofenceResult::new(
-                classification::ANIMAL.to_string(),
+                Label::animal(),
                 top_detection_score,
-                source::DETECTOR.to_string(),
+                PredictionSource::Detector,
             ));
         }
 
+        Rule::new(trace).check("unknown", "none of the rules fired");
+
         Ok(GeofenceResult::new(
-            classification::UNKNOWN.to_string(),
+            Label::unknown(),
             top_classification_score,
-            source::CLASSIFIER.to_string(),
+            PredictionSource::Classifier,
         ))
     }
 }
@@ -0,0 +1,304 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use speciesnet_core::{
+    constants::classification,
+    taxonomy::{Label, Rank},
+};
+
+use crate::{error::Error, geofence::taxonomy::parse_label};
+
+#[cfg(test)]
+mod tests;
+
+/// How a label matched a query of [`Taxonomy::search`], from the best match to the worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The UUID, the common name or the scientific name is the query.
+    Exact,
+    /// The common name or the scientific name starts with the query.
+    Prefix,
+    /// The common name or the scientific name contains the query.
+    Contains,
+    /// The common name or the scientific name is the query with a few typos, the number of
+    /// characters to change being kept.
+    Fuzzy(usize),
+}
+
+/// A label found by [`Taxonomy::search`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaxonMatch<'a> {
+    pub label: &'a Label,
+    pub kind: MatchKind,
+}
+
+/// The taxonomy of a model, the labels of `taxonomy_release.txt` along with the labels the
+/// classifier can output.
+///
+/// The taxonomy has the labels of the classifier along with their ancestors, e.g. the cat family
+/// and the carnivores for the lion, which are what the ensemble rolls the classifications up to.
+#[derive(Debug, Clone, Default)]
+pub struct Taxonomy {
+    labels: Vec<Label>,
+    outputs: Vec<Label>,
+    class_map: HashMap<String, Label>,
+}
+
+impl Taxonomy {
+    /// Creates a taxonomy from its labels and the labels the classifier can output. The outputs
+    /// which are not in the taxonomy are added to it.
+    pub fn new(labels: Vec<Label>, outputs: Vec<Label>) -> Self {
+        let mut labels = labels;
+
+        for output in &outputs {
+            if !labels.contains(output) {
+                labels.push(output.clone());
+            }
+        }
+
+        // The non-taxonomic labels are left out of the rollups.
+        let class_map = labels
+            .iter()
+            .filter(|label| {
+                ![
+                    classification::BLANK,
+                    classification::VEHICLE,
+                    classification::UNKNOWN,
+                ]
+                .iter()
+                .any(|known| **label == *known)
+            })
+            .map(|label| (label.full_class_string(), label.clone()))
+            .collect();
+
+        Self {
+            labels,
+            outputs,
+            class_map,
+        }
+    }
+
+    /// Loads the taxonomy from a `taxonomy_release.txt` file, without the labels of the
+    /// classifier.
+    pub fn from_file<P: AsRef<Path>>(taxonomy_path: P) -> Result<Self, Error> {
+        Ok(Self::new(read_labels(taxonomy_path)?, Vec::new()))
+    }
+
+    /// Loads the taxonomy from a `taxonomy_release.txt` file along with the labels the classifier
+    /// can output from its labels file.
+    pub fn from_files<P: AsRef<Path>>(
+        taxonomy_path: P,
+        classifier_labels_path: P,
+    ) -> Result<Self, Error> {
+        Ok(Self::new(
+            read_labels(taxonomy_path)?,
+            read_labels(classifier_labels_path)?,
+        ))
+    }
+
+    /// Returns all of the labels of the taxonomy, in the order of the taxonomy file.
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    /// Returns the labels the classifier can output, empty when the taxonomy was loaded without
+    /// them.
+    pub fn outputs(&self) -> &[Label] {
+        &self.outputs
+    }
+
+    /// Returns whether the classifier can output the label.
+    pub fn is_output(&self, label: &Label) -> bool {
+        self.outputs.contains(label)
+    }
+
+    /// Returns the map of the full class strings to the labels which the rollups of the ensemble
+    /// look the ancestors up in. The blank, vehicle and unknown labels are not in it.
+    pub fn class_map(&self) -> &HashMap<String, Label> {
+        &self.class_map
+    }
+
+    /// Finds the label with the given UUID, ignoring the case.
+    pub fn find_by_id(&self, id: &str) -> Option<&Label> {
+        self.labels
+            .iter()
+            .find(|label| label.id().eq_ignore_ascii_case(id.trim()))
+    }
+
+    /// Finds the labels with the given common name, ignoring the case. Several labels can share a
+    /// common name.
+    pub fn find_by_common_name(&self, common_name: &str) -> Vec<&Label> {
+        let common_name = normalize(common_name);
+
+        self.labels
+            .iter()
+            .filter(|label| normalize(label.common_name()) == common_name)
+            .collect()
+    }
+
+    /// Finds the labels with the given scientific name, ignoring the case, see
+    /// [`Label::scientific_name`].
+    pub fn find_by_scientific_name(&self, scientific_name: &str) -> Vec<&Label> {
+        let scientific_name = normalize(scientific_name);
+
+        self.labels
+            .iter()
+            .filter(|label| {
+                label
+                    .scientific_name()
+                    .is_some_and(|name| normalize(&name) == scientific_name)
+            })
+            .collect()
+    }
+
+    /// Searches the labels by UUID, common name or scientific name, ignoring the case and allowing
+    /// a few typos. The matches are sorted from the best to the worst, see [`MatchKind`].
+    pub fn search(&self, query: &str) -> Vec<TaxonMatch<'_>> {
+        let query = normalize(query);
+
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        // About one typo every four characters, so short queries do not match everything.
+        let max_distance = (query.chars().count() / 4).max(1);
+
+        let mut matches: Vec<TaxonMatch<'_>> = self
+            .labels
+            .iter()
+            .filter_map(|label| {
+                let kind = if label.id().eq_ignore_ascii_case(&query) {
+                    MatchKind::Exact
+                } else {
+                    [
+                        Some(label.common_name().to_string()),
+                        label.scientific_name(),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .map(|name| normalize(&name))
+                    .filter(|name| !name.is_empty())
+                    .filter_map(|name| match_name(&name, &query, max_distance))
+                    .min()?
+                };
+
+                Some(TaxonMatch { label, kind })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| compare(a.label, b.label)));
+        matches
+    }
+
+    /// Returns the ancestors of the label found in the taxonomy, from its parent up to the
+    /// kingdom, e.g. the genus `panthera`, the family `felidae`, the order `carnivora`, the class
+    /// `mammalia` and the animal kingdom for the lion.
+    pub fn ancestors(&self, label: &Label) -> Vec<&Label> {
+        let Some(rank) = label.rank() else {
+            return Vec::new();
+        };
+
+        [
+            Rank::Genus,
+            Rank::Family,
+            Rank::Order,
+            Rank::Class,
+            Rank::Kingdom,
+        ]
+        .into_iter()
+        .filter(|ancestor_rank| *ancestor_rank < rank)
+        .filter_map(|ancestor_rank| {
+            self.class_map
+                .get(&label.ancestor_class_string(ancestor_rank)?)
+        })
+        .collect()
+    }
+
+    /// Returns the labels of the taxonomy at the given rank which are under the taxon, e.g. the
+    /// species of the cat family.
+    pub fn descendants(&self, taxon: &Label, rank: Rank) -> Vec<&Label> {
+        self.labels
+            .iter()
+            .filter(|label| label.rank() == Some(rank) && *label != taxon && label.is_under(taxon))
+            .collect()
+    }
+
+    /// Returns the labels the classifier can output which are the taxon itself or under it, at
+    /// any rank.
+    pub fn outputs_under(&self, taxon: &Label) -> Vec<&Label> {
+        self.outputs
+            .iter()
+            .filter(|label| label.is_under(taxon))
+            .collect()
+    }
+}
+
+/// Reads the labels of a taxonomy or a labels file, one label per line.
+fn read_labels<P: AsRef<Path>>(path: P) -> Result<Vec<Label>, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut labels = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if !line.trim().is_empty() {
+            labels.push(parse_label(line.trim())?);
+        }
+    }
+
+    Ok(labels)
+}
+
+/// Lowercases the name and collapses its whitespaces, so `Panthera  Leo` matches `panthera leo`.
+fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Matches a normalized name with a normalized query.
+fn match_name(name: &str, query: &str, max_distance: usize) -> Option<MatchKind> {
+    if name == query {
+        Some(MatchKind::Exact)
+    } else if name.starts_with(query) {
+        Some(MatchKind::Prefix)
+    } else if name.contains(query) {
+        Some(MatchKind::Contains)
+    } else {
+        let distance = levenshtein(name, query);
+        (distance <= max_distance).then_some(MatchKind::Fuzzy(distance))
+    }
+}
+
+/// Sorts the labels from the highest rank to the lowest, then by common name.
+fn compare(a: &Label, b: &Label) -> Ordering {
+    a.rank()
+        .cmp(&b.rank())
+        .then_with(|| a.common_name().cmp(b.common_name()))
+        .then_with(|| a.cmp(b))
+}
+
+/// Number of characters to insert, delete or substitute to turn one string into the other.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous + usize::from(a_char != *b_char);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
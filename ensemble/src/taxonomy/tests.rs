@@ -0,0 +1,148 @@
+use speciesnet_core::taxonomy::{Label, Rank};
+
+use super::{MatchKind, Taxonomy};
+
+const LION: &str =
+    "ddf59264-185a-4d35-b647-2785792bdf54;mammalia;carnivora;felidae;panthera;leo;lion";
+const TIGER: &str =
+    "e3f6b3a7-2a0c-4c2e-9b0e-5f0c1d1b9a52;mammalia;carnivora;felidae;panthera;tigris;tiger";
+const PANTHERA_GENUS: &str =
+    "fbb23d07-6677-43db-b650-f99ac452c50f;mammalia;carnivora;felidae;panthera;;panthera species";
+const FELIDAE_FAMILY: &str =
+    "df8514b0-10a5-411f-8ed6-0f415e8153a3;mammalia;carnivora;felidae;;;cat family";
+const CARNIVORA_ORDER: &str =
+    "eeeb5d26-2a47-4d01-a3de-10b33ec0aee4;mammalia;carnivora;;;;carnivorous mammal";
+const MAMMALIA_CLASS: &str = "f2d233e3-80e3-433d-9687-e29ecc7a467a;mammalia;;;;;mammal";
+const ANIMAL_KINGDOM: &str = "1f689929-883d-4dae-958c-3d57ab5b6c16;;;;;;animal";
+const BROWN_BEAR: &str =
+    "330bb1e9-84d6-4e41-afa9-938aee17ea29;mammalia;carnivora;ursidae;ursus;arctos;brown bear";
+const BLANK: &str = "f1856211-cfb7-4a5b-9158-c0f72fd09ee6;;;;;;blank";
+
+fn label(label: &str) -> Label {
+    label.parse().unwrap()
+}
+
+fn taxonomy() -> Taxonomy {
+    Taxonomy::new(
+        [
+            ANIMAL_KINGDOM,
+            MAMMALIA_CLASS,
+            CARNIVORA_ORDER,
+            FELIDAE_FAMILY,
+            PANTHERA_GENUS,
+            LION,
+            TIGER,
+            BROWN_BEAR,
+            BLANK,
+        ]
+        .map(label)
+        .to_vec(),
+        [LION, PANTHERA_GENUS, BROWN_BEAR, BLANK]
+            .map(label)
+            .to_vec(),
+    )
+}
+
+#[test]
+fn test_find() {
+    let taxonomy = taxonomy();
+
+    assert_eq!(
+        taxonomy.find_by_id("DDF59264-185A-4D35-B647-2785792BDF54"),
+        Some(&label(LION))
+    );
+    assert_eq!(
+        taxonomy.find_by_common_name("Brown  Bear"),
+        vec![&label(BROWN_BEAR)]
+    );
+    assert_eq!(
+        taxonomy.find_by_scientific_name("Panthera Leo"),
+        vec![&label(LION)]
+    );
+    assert_eq!(
+        taxonomy.find_by_scientific_name("felidae"),
+        vec![&label(FELIDAE_FAMILY)]
+    );
+    assert!(taxonomy.find_by_common_name("puma").is_empty());
+}
+
+#[test]
+fn test_search() {
+    let taxonomy = taxonomy();
+
+    let matches = taxonomy.search("LION");
+    assert_eq!(matches[0].label, &label(LION));
+    assert_eq!(matches[0].kind, MatchKind::Exact);
+
+    // `panthera` is the start of the genus and of the scientific names of its species.
+    let matches: Vec<&Label> = taxonomy
+        .search("panthera")
+        .into_iter()
+        .map(|m| m.label)
+        .collect();
+    assert_eq!(
+        matches,
+        vec![&label(PANTHERA_GENUS), &label(LION), &label(TIGER)]
+    );
+
+    let matches = taxonomy.search("brown baer");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].label, &label(BROWN_BEAR));
+    assert_eq!(matches[0].kind, MatchKind::Fuzzy(2));
+
+    assert!(taxonomy.search("zebra").is_empty());
+    assert!(taxonomy.search(" ").is_empty());
+}
+
+#[test]
+fn test_ancestors_and_descendants() {
+    let taxonomy = taxonomy();
+
+    assert_eq!(
+        taxonomy.ancestors(&label(LION)),
+        vec![
+            &label(PANTHERA_GENUS),
+            &label(FELIDAE_FAMILY),
+            &label(CARNIVORA_ORDER),
+            &label(MAMMALIA_CLASS),
+            &label(ANIMAL_KINGDOM),
+        ]
+    );
+    // The bear genus and family are not in the taxonomy.
+    assert_eq!(
+        taxonomy.ancestors(&label(BROWN_BEAR)),
+        vec![
+            &label(CARNIVORA_ORDER),
+            &label(MAMMALIA_CLASS),
+            &label(ANIMAL_KINGDOM),
+        ]
+    );
+    assert!(taxonomy.ancestors(&label(BLANK)).is_empty());
+
+    assert_eq!(
+        taxonomy.descendants(&label(FELIDAE_FAMILY), Rank::Species),
+        vec![&label(LION), &label(TIGER)]
+    );
+    assert_eq!(
+        taxonomy.descendants(&label(ANIMAL_KINGDOM), Rank::Family),
+        vec![&label(FELIDAE_FAMILY)]
+    );
+    assert!(taxonomy.descendants(&label(LION), Rank::Species).is_empty());
+}
+
+#[test]
+fn test_outputs_under() {
+    let taxonomy = taxonomy();
+
+    assert!(taxonomy.is_output(&label(LION)));
+    assert!(!taxonomy.is_output(&label(TIGER)));
+    assert_eq!(
+        taxonomy.outputs_under(&label(FELIDAE_FAMILY)),
+        vec![&label(LION), &label(PANTHERA_GENUS)]
+    );
+    assert_eq!(
+        taxonomy.outputs_under(&label(ANIMAL_KINGDOM)),
+        vec![&label(LION), &label(PANTHERA_GENUS), &label(BROWN_BEAR)]
+    );
+    assert_eq!(taxonomy.outputs_under(&label(BLANK)), vec![&label(BLANK)]);
+}
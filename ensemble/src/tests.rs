@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+
+use serde_json::json;
+use speciesnet_core::{
+    classifier::ClassificationBundle,
+    constants::classification::BLANK,
+    detector::{BoundingBox, Category, Detection},
+    io::Prediction,
+    taxonomy::Label,
+};
+
+use crate::{
+    SpeciesNetEnsemble, config::EnsembleConfig, error::Error, geofence::Geofence,
+    taxonomy::Taxonomy,
+};
+
+const LION: &str =
+    "ddf59264-185a-4d35-b647-2785792bdf54;mammalia;carnivora;felidae;panthera;leo;lion";
+const LION_FC: &str = "mammalia;carnivora;felidae;panthera;leo";
+const PANTHERA_GENUS: &str =
+    "fbb23d07-6677-43db-b650-f99ac452c50f;mammalia;carnivora;felidae;panthera;;panthera species";
+const PANTHERA_GENUS_FC: &str = "mammalia;carnivora;felidae;panthera;";
+const FELIDAE_FAMILY: &str =
+    "df8514b0-10a5-411f-8ed6-0f415e8153a3;mammalia;carnivora;felidae;;;cat family";
+const CARNIVORA_ORDER: &str =
+    "eeeb5d26-2a47-4d01-a3de-10b33ec0aee4;mammalia;carnivora;;;;carnivorous mammal";
+const MAMMALIA_CLASS: &str = "f2d233e3-80e3-433d-9687-e29ecc7a467a;mammalia;;;;;mammal";
+
+fn label(label: &str) -> Label {
+    label.parse().unwrap()
+}
+
+/// An ensemble where the lion and its genus are only allowed in Kenya.
+fn ensemble() -> SpeciesNetEnsemble {
+    let geofence = json!(
+        {
+            LION_FC: { "allow": { "KEN": [] } },
+            PANTHERA_GENUS_FC: { "allow": { "KEN": [] } },
+        }
+    );
+
+    SpeciesNetEnsemble {
+        geofence: Geofence::new(serde_json::from_value(geofence).unwrap()),
+        taxonomy: Taxonomy::new(
+            [
+                LION,
+                PANTHERA_GENUS,
+                FELIDAE_FAMILY,
+                CARNIVORA_ORDER,
+                MAMMALIA_CLASS,
+            ]
+            .map(label)
+            .to_vec(),
+            Vec::new(),
+        ),
+        config: EnsembleConfig::default(),
+        enable_geofence: true,
+        explain: false,
+    }
+}
+
+fn detections() -> Vec<Detection> {
+    vec![Detection::new(
+        Category::Animal,
+        0.9,
+        BoundingBox::new(0.1, 0.1, 0.5, 0.5),
+    )]
+}
+
+fn classifications() -> ClassificationBundle {
+    ClassificationBundle::new(vec![LION.to_string()], vec![0.95])
+}
+
+/// Runs the ensemble on a lion seen in the USA and returns its prediction as json.
+fn predict(ensemble: &SpeciesNetEnsemble, override_geofence: Option<bool>) -> serde_json::Value {
+    let country = Some("USA".to_string());
+    let result = match override_geofence {
+        Some(enable_geofence) => ensemble.ensemble_with_geofence(
+            &detections(),
+            &classifications(),
+            country.clone(),
+            None,
+            enable_geofence,
+        ),
+        None => ensemble.ensemble(&detections(), &classifications(), country.clone(), None),
+    }
+    .unwrap();
+
+    let prediction = Prediction::from_ensemble(
+        PathBuf::from("lion.jpg"),
+        country,
+        None,
+        result,
+        detections(),
+        classifications(),
+    );
+
+    serde_json::to_value(prediction).unwrap()
+}
+
+#[test]
+fn test_enable_geofence() {
+    let mut ensemble = ensemble();
+    assert!(ensemble.enable_geofence());
+
+    // The lion is not allowed in the USA, so it is rolled up past its genus.
+    let geofenced = predict(&ensemble, None);
+    assert_eq!(geofenced["prediction"], FELIDAE_FAMILY);
+    assert_eq!(
+        geofenced["prediction_source"],
+        "classifier+geofence+rollup_to_family"
+    );
+    assert_eq!(geofenced["geofence_enabled"], true);
+
+    ensemble.set_enable_geofence(false);
+    assert!(!ensemble.enable_geofence());
+
+    let raw = predict(&ensemble, None);
+    assert_eq!(raw["prediction"], LION);
+    assert_eq!(raw["prediction_source"], "classifier");
+    assert_eq!(raw["geofence_enabled"], false);
+}
+
+#[test]
+fn test_ensemble_with_geofence() {
+    let mut ensemble = ensemble();
+
+    // The override only applies to the call, whichever way the ensemble is set.
+    assert_eq!(predict(&ensemble, Some(false)), {
+        ensemble.set_enable_geofence(false);
+        predict(&ensemble, None)
+    });
+    assert_eq!(predict(&ensemble, Some(true)), {
+        ensemble.set_enable_geofence(true);
+        predict(&ensemble, None)
+    });
+
+    ensemble.set_enable_geofence(false);
+    let geofenced = predict(&ensemble, Some(true));
+    assert_eq!(geofenced["prediction"], FELIDAE_FAMILY);
+    assert_eq!(geofenced["geofence_enabled"], true);
+    assert!(!ensemble.enable_geofence());
+
+    ensemble.set_enable_geofence(true);
+    let raw = predict(&ensemble, Some(false));
+    assert_eq!(raw["prediction"], LION);
+    assert_eq!(raw["geofence_enabled"], false);
+    assert!(ensemble.enable_geofence());
+}
+
+/// A detection whose crop has been classified on its own with the given label.
+fn classified_detection(confidence: f64, label: &str, score: f64) -> Detection {
+    let mut detection = Detection::new(
+        Category::Animal,
+        confidence,
+        BoundingBox::new(0.1, 0.1, 0.5, 0.5),
+    );
+    detection.set_classifications(Some(ClassificationBundle::new(
+        vec![label.to_string()],
+        vec![score],
+    )));
+    detection
+}
+
+#[test]
+fn test_species_present() -> Result<(), Error> {
+    let ensemble = ensemble();
+    let detections = vec![
+        classified_detection(0.9, LION, 0.95),
+        // A blank crop, and a crop too unsure for any label, which give blank and unknown.
+        classified_detection(0.1, BLANK, 0.99),
+        classified_detection(0.05, LION, 0.05),
+        // A detection which has not been classified on its own.
+        detections().remove(0),
+        classified_detection(0.7, LION, 0.9),
+        classified_detection(0.6, CARNIVORA_ORDER, 0.9),
+    ];
+
+    // The second lion is the same label as the first one, so each label is only listed once.
+    assert_eq!(
+        ensemble.species_present(&detections, Some("KEN".to_string()), None)?,
+        vec![LION.to_string(), CARNIVORA_ORDER.to_string()]
+    );
+
+    // The lion is not allowed in the USA, so it is rolled up like the image-level label.
+    assert_eq!(
+        ensemble.species_present(&detections, Some("USA".to_string()), None)?,
+        vec![FELIDAE_FAMILY.to_string(), CARNIVORA_ORDER.to_string()]
+    );
+
+    Ok(())
+}
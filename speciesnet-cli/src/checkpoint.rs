@@ -0,0 +1,113 @@
+use std::{
+    collections::HashSet,
+    fs::{File, rename},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use speciesnet_core::io::{Instance, Prediction, Predictions};
+use tracing::{debug, info};
+
+#[cfg(test)]
+mod tests;
+
+/// Borrowed version of [`Predictions`] for writing the predictions out without cloning them.
+#[derive(Debug, Serialize)]
+struct PredictionsRef<'a> {
+    predictions: &'a [Prediction],
+}
+
+/// Reads the predictions which have already been written to the given `predictions.json` file by
+/// a previous (possibly interrupted) run.
+pub fn load_predictions<P>(path: P) -> anyhow::Result<Vec<Prediction>>
+where
+    P: AsRef<Path>,
+{
+    info!(
+        "Loading the existing predictions from {}.",
+        path.as_ref().display()
+    );
+
+    let reader = BufReader::new(File::open(path)?);
+    let predictions: Predictions = serde_json::from_reader(reader)?;
+
+    Ok(predictions.into_predictions())
+}
+
+/// Removes the instances which already have a prediction inside the given list of predictions, the
+/// instances are matched by their file paths.
+///
+/// The predictions with failures recorded are taken out of the list instead, so their instances are
+/// run again and their new predictions replace them.
+pub fn remove_finished_instances(
+    instances: Vec<Instance>,
+    predictions: &mut Vec<Prediction>,
+) -> Vec<Instance> {
+    let total_predictions = predictions.len();
+    predictions.retain(|p| p.failures().is_none());
+
+    let finished_paths: HashSet<&Path> = predictions.iter().map(|p| p.file_path()).collect();
+    let total_instances = instances.len();
+
+    let pending_instances: Vec<Instance> = instances
+        .into_iter()
+        .filter(|instance| !finished_paths.contains(instance.file_path()))
+        .collect();
+
+    info!(
+        "Skipping {} instances which were already predicted, {} instances left of which {} failed \
+        in the last run.",
+        total_instances - pending_instances.len(),
+        pending_instances.len(),
+        total_predictions - predictions.len()
+    );
+
+    pending_instances
+}
+
+/// Writes the predictions to the given path.
+///
+/// The predictions are written to a temporary file next to the given path first, then the
+/// temporary file gets renamed to the given path, so a crash in the middle of writing never
+/// leaves a half-written `predictions.json` behind.
+pub fn save_predictions<P>(path: P, predictions: &[Prediction], pretty: bool) -> anyhow::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let temporary_path = temporary_path_of(path);
+
+    debug!(
+        "Writing {} predictions to {}.",
+        predictions.len(),
+        temporary_path.display()
+    );
+
+    // This block forces a drop of the writer before renaming the file.
+    {
+        let predictions = PredictionsRef { predictions };
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+
+        if pretty {
+            serde_json::to_writer_pretty(&mut writer, &predictions)?;
+        } else {
+            serde_json::to_writer(&mut writer, &predictions)?;
+        }
+
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+    }
+
+    rename(&temporary_path, path)?;
+
+    Ok(())
+}
+
+/// Returns the path of the temporary file used while writing the predictions to the given path.
+fn temporary_path_of(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+
+    path.with_file_name(file_name)
+}
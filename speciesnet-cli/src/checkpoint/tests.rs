@@ -0,0 +1,79 @@
+use std::{env::temp_dir, fs, path::PathBuf};
+
+use speciesnet_core::io::{Failure, Instance, Prediction};
+
+use super::{load_predictions, remove_finished_instances, save_predictions, temporary_path_of};
+
+fn instance(path: &str) -> Instance {
+    Instance::new(PathBuf::from(path), None, None)
+}
+
+fn failed_prediction(path: &str) -> Prediction {
+    let mut prediction = Prediction::new(PathBuf::from(path));
+    prediction.add_failure(
+        Failure::Detector,
+        "image".to_string(),
+        "failed to load the image".to_string(),
+    );
+    prediction
+}
+
+#[test]
+fn test_remove_finished_instances() {
+    let instances = ["a.jpg", "b.jpg", "c.jpg", "d.jpg"].map(instance).to_vec();
+    let mut predictions = vec![
+        Prediction::from_detections(PathBuf::from("a.jpg"), Vec::new()),
+        failed_prediction("b.jpg"),
+        Prediction::from_detections(PathBuf::from("d.jpg"), Vec::new()),
+    ];
+
+    let pending = remove_finished_instances(instances, &mut predictions);
+
+    // The failed instance is run again and its old prediction is dropped so it gets replaced.
+    let pending_paths: Vec<&str> = pending
+        .iter()
+        .map(|instance| instance.file_path().to_str().unwrap())
+        .collect();
+    assert_eq!(pending_paths, vec!["b.jpg", "c.jpg"]);
+
+    let finished_paths: Vec<&str> = predictions
+        .iter()
+        .map(|prediction| prediction.file_path().to_str().unwrap())
+        .collect();
+    assert_eq!(finished_paths, vec!["a.jpg", "d.jpg"]);
+}
+
+#[test]
+fn test_temporary_path_of() {
+    assert_eq!(
+        temporary_path_of(&PathBuf::from("output/predictions.json")),
+        PathBuf::from("output/predictions.json.tmp")
+    );
+}
+
+#[test]
+fn test_save_predictions() -> anyhow::Result<()> {
+    let folder = temp_dir().join(format!("speciesnet-checkpoint-{}", std::process::id()));
+    fs::create_dir_all(&folder)?;
+
+    let path = folder.join("predictions.json");
+    fs::write(&path, "an interrupted checkpoint")?;
+
+    let predictions = vec![
+        Prediction::from_detections(PathBuf::from("a.jpg"), Vec::new()),
+        failed_prediction("b.jpg"),
+    ];
+    save_predictions(&path, &predictions, true)?;
+
+    // The temporary file is renamed over the previous checkpoint.
+    assert!(!temporary_path_of(&path).exists());
+
+    let loaded = load_predictions(&path)?;
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].file_path(), PathBuf::from("a.jpg"));
+    assert_eq!(loaded[1].failures(), &Some(vec![Failure::Detector]));
+
+    fs::remove_dir_all(&folder)?;
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use speciesnet::{Geofence, Taxonomy};
+use speciesnet_core::{
+    geography::validate_location,
+    taxonomy::{Label, Rank},
+};
+use tracing::info;
+
+use crate::{
+    models::load_model_info,
+    taxonomy::{format_label, resolve},
+};
+
+#[derive(Debug, Args)]
+pub struct GeofenceArguments {
+    /// Folder of an extracted model to read the geofence from instead of the selected model of
+    /// the cache.
+    #[arg(long, global = true)]
+    model_folder: Option<PathBuf>,
+    /// Csv file of geofence fixes to apply on top of the geofence of the model, can be given
+    /// several times to apply the fix files in order.
+    #[arg(long, global = true)]
+    geofence_fix_file: Vec<PathBuf>,
+    /// Prints json instead of tables.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: GeofenceCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GeofenceCommand {
+    /// Lists the species the classifier can output which are allowed or blocked in a country
+    /// and admin1 region.
+    Species {
+        /// Country code in ISO 3166-1 alpha-3 format, e.g. `USA`.
+        #[arg(long)]
+        country: String,
+        /// Admin1 region code in ISO 3166-2 format, e.g. `CA`.
+        #[arg(long)]
+        admin1_region: Option<String>,
+        /// Lists only the allowed or the blocked species.
+        #[arg(long, value_enum)]
+        status: Option<GeofenceStatusArg>,
+    },
+    /// Lists the countries which allow a label, looked up by UUID, common name or scientific name.
+    Countries { query: String },
+    /// Lists the species the classifier can output which have no geofence data, and so are
+    /// allowed everywhere.
+    Missing,
+    /// Writes the geofence of the model with the fixes applied, in the format of
+    /// `geofence_release.json`.
+    Export {
+        /// Path of the json file to write, the geofence is printed when none is given.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GeofenceStatusArg {
+    Allowed,
+    Blocked,
+}
+
+/// Runs the `geofence` subcommand.
+pub fn run_geofence(arguments: &GeofenceArguments) -> anyhow::Result<()> {
+    let model_info = load_model_info(arguments.model_folder.as_deref())?;
+    let taxonomy = Taxonomy::from_files(model_info.taxonomy(), model_info.classifier_labels())?;
+    let mut geofence = Geofence::from_file(model_info.geofence(), None)?;
+
+    for geofence_fix_file in &arguments.geofence_fix_file {
+        geofence.apply_fixes(geofence_fix_file)?;
+    }
+
+    let species: Vec<&Label> = taxonomy
+        .outputs()
+        .iter()
+        .filter(|label| label.rank() == Some(Rank::Species))
+        .collect();
+
+    match &arguments.command {
+        GeofenceCommand::Species {
+            country,
+            admin1_region,
+            status,
+        } => {
+            validate_location(Some(country), admin1_region.as_deref())?;
+
+            let lookups: Vec<_> = geofence
+                .lookup_all(species, country, admin1_region.as_deref())?
+                .into_iter()
+                .filter(|lookup| match status {
+                    Some(GeofenceStatusArg::Allowed) => !lookup.geofenced,
+                    Some(GeofenceStatusArg::Blocked) => lookup.geofenced,
+                    None => true,
+                })
+                .collect();
+
+            if arguments.json {
+                println!("{}", serde_json::to_string_pretty(&lookups)?);
+            } else {
+                for lookup in &lookups {
+                    println!(
+                        "{:<8} {:<20} {}",
+                        if lookup.geofenced {
+                            "blocked"
+                        } else {
+                            "allowed"
+                        },
+                        serde_json::to_value(lookup.reason)?
+                            .as_str()
+                            .unwrap_or_default(),
+                        format_label(&lookup.label)
+                    );
+                }
+            }
+        }
+        GeofenceCommand::Countries { query } => {
+            let label = resolve(&taxonomy, query)?;
+            let countries = geofence.allowed_countries(label);
+
+            if !geofence.has_rules(label) {
+                info!(
+                    "{} has no geofence data, it is allowed everywhere.",
+                    label.common_name()
+                );
+            }
+
+            if arguments.json {
+                println!("{}", serde_json::to_string_pretty(&countries)?);
+            } else {
+                for country in &countries {
+                    let mut line = country.country.clone();
+
+                    if !country.allowed_regions.is_empty() {
+                        line += &format!(" only in {}", country.allowed_regions.join(", "));
+                    }
+                    if !country.blocked_regions.is_empty() {
+                        line += &format!(" except in {}", country.blocked_regions.join(", "));
+                    }
+
+                    println!("{}", line);
+                }
+            }
+        }
+        GeofenceCommand::Missing => {
+            let missing = geofence.without_rules(species);
+
+            if arguments.json {
+                println!("{}", serde_json::to_string_pretty(&missing)?);
+            } else {
+                for label in missing {
+                    println!("{}", format_label(label));
+                }
+            }
+        }
+        GeofenceCommand::Export { output } => match output {
+            Some(output) => {
+                geofence.save(output)?;
+                info!("Geofence written to {}.", output.display());
+            }
+            None => println!("{}", serde_json::to_string_pretty(&geofence)?),
+        },
+    }
+
+    Ok(())
+}
@@ -3,7 +3,10 @@ use std::{
     io::{BufRead, BufReader},
 };
 
-use speciesnet_core::io::{Instance, Instances};
+use speciesnet_core::{
+    geography::admin1_region_code,
+    io::{Instance, Instances},
+};
 use tracing::{debug, info};
 use walkdir::WalkDir;
 
@@ -145,3 +148,50 @@ pub fn prepare_image_inputs(input_type: &InputType) -> anyhow::Result<Vec<Instan
 
     Ok(filtered_paths)
 }
+
+/// Fills in the given country and admin1 region on every instance which does not have them. The
+/// admin1 region is only filled in on the instances of the same country, since a region of one
+/// country is meaningless on another.
+///
+/// The admin1 regions are then stored without the prefix of their country, e.g. `US-CA` becomes
+/// `CA`, which is how the geofence has them.
+pub fn apply_location_defaults(
+    instances: &mut [Instance],
+    country: Option<&str>,
+    admin1_region: Option<&str>,
+) {
+    for instance in instances {
+        if instance.country().is_none() && country.is_some() {
+            instance.set_country(country.map(str::to_string));
+        }
+
+        if instance.admin1_region().is_none()
+            && admin1_region.is_some()
+            && instance.country() == country
+        {
+            instance.set_admin1_region(admin1_region.map(str::to_string));
+        }
+
+        if let (Some(country), Some(admin1_region)) = (instance.country(), instance.admin1_region())
+        {
+            let admin1_region = admin1_region_code(country, admin1_region).to_string();
+            instance.set_admin1_region(Some(admin1_region));
+        }
+    }
+}
+
+/// Validates the country and admin1 region of every instance, returning the error of the first
+/// invalid one along with its file path.
+pub fn validate_locations(instances: &[Instance]) -> anyhow::Result<()> {
+    for instance in instances {
+        instance.validate_location().map_err(|e| {
+            anyhow::anyhow!(
+                "Invalid location of {}: {}",
+                instance.file_path().display(),
+                e
+            )
+        })?;
+    }
+
+    Ok(())
+}
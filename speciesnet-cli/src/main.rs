@@ -21,8 +21,9 @@
 //! - The input instance file only support `filepath`, `country`, and `admin1_region` keys.
 //! - The classifier output only support the `classes`, and `scores` key.
 //! - Other keys of `predictions` are still not supported on both reading from and writing to files.
-//! - The Rust version does not override or edit an existing `predictions.json` file, if one is found when supplied using `--predictions-json`, CLI will error saying the file already existed.
-//! - The CLI flag `--country` and `--admin1-region` currently does nothing to the input.
+//! - The Rust version does not override an existing `predictions.json` file, if one is found when supplied using `--predictions-json`, CLI will error saying the file already existed unless `--resume` is passed.
+//! - The CLI flags `--country` and `--admin1-region` are filled in on every input which does not have them, the codes are validated against ISO 3166-1 alpha-3 and ISO 3166-2 before running, including with `--ensemble-only`. The admin1 regions can be given with or without the prefix of their country, e.g. `US-CA` or `CA`, and are stored without it since the geofence has them that way.
+//! - Images which fail on any stage are kept in the output with the failed stages in `failures`, and the error messages in `errors`, a summary of the failures is logged at the end of the run.
 //!
 //! below is the examples of running the ensemble using speciesnet compared to cameratrapai.
 //!
@@ -65,18 +66,263 @@
 //! # speciesnet-rust.
 //! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json
 //! ```
+//!
+//! #### Resuming an interrupted run
+//!
+//! While running the detector or the whole pipeline, the finished predictions are written to the
+//! `--predictions-json` file every `--checkpoint-every` images (1000 by default). Interrupting the
+//! run with Ctrl-C saves the predictions which have finished so far and stops the run. The
+//! inference streams each prediction back as soon as its image finishes, `--queue-size` (64 by
+//! default) limits how many finished predictions can wait in the queue. Passing `--resume` loads
+//! the existing predictions file and skips every image which already has a prediction inside, the
+//! images whose prediction has failures recorded are run again.
+//!
+//! Each checkpoint rewrites the whole predictions file with every prediction finished so far, so
+//! on runs of hundreds of thousands of images a larger `--checkpoint-every` keeps the time spent
+//! saving down.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --resume
+//! ```
+//!
+//! #### Batched inference
+//!
+//! `--batch-size` runs that many images through the detector and the classifier in one go, which
+//! is faster on large runs. The models have to be exported with a dynamic batch axis, when a batch
+//! fails the images of the batch are run again one by one.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --batch-size 8
+//! ```
+//!
+//! #### Pipeline workers
+//!
+//! The whole pipeline decodes, detects, and classifies the images on separate workers connected
+//! by bounded queues of `--queue-size` images. `--decode-workers`, `--detector-workers`, and
+//! `--classifier-workers` sets the number of workers of each stage.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --decode-workers 4 --detector-workers 2 --classifier-workers 2
+//! ```
+//!
+//! #### Tuning the ensemble thresholds
+//!
+//! `--ensemble-config` takes a `.toml` or `.json` file of the ensemble's decision thresholds, the
+//! thresholds which are not in the file keep the same values as cameratrapai.
+//!
+//! ```toml
+//! # ensemble.toml
+//! animal_high_classification_threshold = 0.9
+//! rollup_threshold = 0.7
+//! rollup_levels = ["genus", "family"]
+//! ```
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --ensemble-config ./ensemble.toml
+//! ```
+//!
+//! #### Classifying every detection
+//!
+//! `--classify-detections` classifies each detection whose confidence is at least
+//! `--detection-min-confidence` (0.2 by default) on its own, up to `--max-detections` (10 by
+//! default) detections per image. Each detection gets its own `classifications`, and the
+//! `species_present` of each prediction lists the distinct labels found among them next to the
+//! image-level `prediction`.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --classify-detections --max-detections 5
+//! ```
+//!
+//! #### Top-k and score retention
+//!
+//! `--top-k` (5 by default) sets how many of the highest scoring classifications are kept on each
+//! image. The ensemble rolls the labels up to genus, family, and so on by summing their scores,
+//! which only covers the top-k labels unless `--score-retention` keeps more of them. `full` keeps
+//! every score and `sparse` keeps the scores of at least `--sparse-min-score` (0.001 by default),
+//! only the top-k classifications are written to the predictions file either way.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --top-k 10 --score-retention sparse
+//! ```
+//!
+//! #### Detector post-processing
+//!
+//! `--detector-confidence` (0.01 by default) and `--detector-iou` (0.45 by default) set the minimum
+//! confidence of a detection and the IoU above which overlapping detections are suppressed.
+//! `--detector-max-detections` (300 by default) caps the detections of each image,
+//! `--detector-class-agnostic` suppresses overlapping detections of different categories too, and
+//! `--detector-min-box-area` (a fraction of the image) and `--detector-min-box-size` (in pixels)
+//! drop the small detections. Values out of their range are rejected before the models are loaded.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --detector-confidence 0.1 --detector-class-agnostic --detector-min-box-size 16
+//! ```
+//!
+//! #### Tiled detection
+//!
+//! The detector letterboxes every image to 1280 pixels, where the small and distant animals of the
+//! high resolution and panoramic images can disappear. `--tile-size` runs the detector on
+//! overlapping tiles of the given number of pixels instead, along with the whole image so the large
+//! animals are still found in one piece. `--tile-overlap` (0.2 by default) sets the fraction of a
+//! tile which overlaps its neighbours, and the detections of the tiles are merged with
+//! `--tile-merge nms` (the default) or `wbf` for the weighted box fusion, above an IoU of
+//! `--tile-merge-iou` (0.5 by default). Each tile takes about as long as a whole image to run.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --tile-size 1280 --tile-merge wbf
+//! ```
+//!
+//! #### Suppressing overlapping detections
+//!
+//! `--detector-suppression` picks how the overlapping detections of an image are suppressed. `nms`
+//! (the default) is the greedy non-max suppression of the Python implementation, `soft-nms-linear`
+//! and `soft-nms-gaussian` decay the confidences of the overlapping detections instead of removing
+//! them, which keeps more of the animals standing close together, and `wbf` averages the
+//! overlapping detections with the weighted box fusion. `--soft-nms-sigma` (0.5 by default) sets
+//! how fast the Gaussian decay goes, the detections decayed below `--detector-confidence` are
+//! dropped.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --detector-suppression soft-nms-gaussian
+//! ```
+//!
+//! #### Other detector models
+//!
+//! `--detector-model` runs the images through the given ONNX detector instead of the MegaDetector
+//! v5 of the model, e.g. to try a newer MegaDetector release. `--detector-kind` tells the layout of
+//! its output, `yolov5` (the default) for the `[1, boxes, 5 + classes]` output of YOLOv5 and
+//! `anchor-free` for the `[1, 4 + classes, boxes]` output of YOLOv8, YOLOv9 and MegaDetector v6.
+//! The classes of the model must be animal, person and vehicle in this order.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --detector-model ./md_v6.onnx --detector-kind anchor-free
+//! ```
+//!
+//! #### Managing the models
+//!
+//! The model is downloaded into the cache directory of the user the first time the CLI runs, into a
+//! temporary file which is only moved into place once it is complete. `speciesnet-cli models`
+//! manages the cached models: `list` shows the model versions of the manifest and the ones in the
+//! cache, `download` fetches a version ahead of time, `verify` checks the files of a cached version
+//! along with the SHA-256 of its archive, `select` picks the version the runs use, and `remove`
+//! deletes a version from the cache. `--manifest` takes a json file of the model versions with
+//! their urls and checksums instead of the built-in one.
+//!
+//! ```bash
+//! speciesnet-cli models list
+//! speciesnet-cli models download 4.0.0a
+//! speciesnet-cli models verify
+//! speciesnet-cli models select 4.0.0a
+//! ```
+//!
+//! The `SPECIESNET_CACHE_DIR` environment variable moves the cache into another directory, and
+//! `SPECIESNET_MODEL_URL` downloads the model from another url, e.g. a mirror.
+//!
+//! The machines which cannot download the model can import it from a `.zip`, `.tar.gz` or `.tgz`
+//! archive of the model folder with `models import`. The `info.json` and every file it points at
+//! are checked before the model is moved into the cache under the version of its `info.json`,
+//! `--select` selects the imported version right away.
+//!
+//! ```bash
+//! speciesnet-cli models import ./speciesnet-onnx-v4.0.0a.tar.gz --select
+//! ```
+//!
+//! #### Explaining the ensemble
+//!
+//! `--explain` adds an `ensemble_trace` to each prediction which explains how the ensemble came to
+//! it. The `rules` are the numbered rules of the ensemble in the order they were evaluated, each
+//! with the scores and labels it compared against its thresholds and whether it fired. The
+//! `geofence_lookups` give the label, the location, why the label was or was not geofenced and the
+//! `allow` or `block` entry which decided it. The `rollups` list the rolled up scores of each
+//! taxonomy level which was tried. The predictions are the same with or without `--explain`.
+//!
+//! ```bash
+//! speciesnet-cli --instance-json ./instance.json --predictions-json ./predictions.json --explain
+//! ```
+//!
+//! #### Looking up the taxonomy
+//!
+//! `speciesnet-cli taxonomy` looks up the taxonomy of the selected model, or of the extracted model
+//! given with `--model-folder`. The labels are looked up by UUID, common name or scientific name,
+//! ignoring the case and small typos, and the labels the classifier can output are marked with `*`.
+//! `find` lists the labels matching a query, `show` tells whether the classifier can output a label
+//! and the ancestors it rolls up to, `descendants` lists the labels under a taxon at a `--rank`,
+//! species by default, and `outputs` lists every label the classifier can output under a taxon.
+//!
+//! ```bash
+//! speciesnet-cli taxonomy find "mountain lion"
+//! speciesnet-cli taxonomy show "puma concolor"
+//! speciesnet-cli taxonomy descendants felidae --rank genus
+//! speciesnet-cli taxonomy outputs "cat family"
+//! ```
+//!
+//! #### Querying the geofence
+//!
+//! `speciesnet-cli geofence` looks up the geofence of the selected model, or of the extracted model
+//! given with `--model-folder`, with the fixes of `--geofence-fix-file` applied on top of it.
+//! `species` lists the species the classifier can output along with whether they are allowed or
+//! blocked in a country and admin1 region and why, `--status` keeps only the allowed or the blocked
+//! ones. `countries` lists the countries which allow a species, along with the admin1 regions it is
+//! limited to or blocked in, and `missing` lists the species without any geofence data, which are
+//! allowed everywhere. `--json` prints json instead of tables.
+//!
+//! ```bash
+//! speciesnet-cli geofence species --country USA --admin1-region CA --status allowed
+//! speciesnet-cli geofence countries "puma concolor"
+//! speciesnet-cli geofence missing --json
+//! ```
+//!
+//! #### Layering and exporting the geofence fixes
+//!
+//! `--geofence-fix-file` can be given several times, the fix files are applied in order on top of
+//! the geofence of the model, so a project can keep its own overrides on top of the fixes of a
+//! release. `geofence export` writes the merged geofence in the format of `geofence_release.json`,
+//! with its keys sorted, to `--output` or to the standard output.
+//!
+//! ```bash
+//! speciesnet-cli geofence --geofence-fix-file ./release_fixes.csv --geofence-fix-file ./project_fixes.csv export --output ./geofence_merged.json
+//! ```
 
-use std::{fs::File, io::BufWriter, path::PathBuf};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::Receiver,
+    },
+};
 
-use clap::{Args, CommandFactory, Parser, error::ErrorKind};
-use inputs::prepare_image_inputs;
-use speciesnet::SpeciesNet;
-use speciesnet_core::io::Predictions;
-use tracing::info;
+use checkpoint::{load_predictions, remove_finished_instances, save_predictions};
+use clap::{Args, CommandFactory, Parser, Subcommand, error::ErrorKind};
+use geofence::{GeofenceArguments, run_geofence};
+use inputs::{apply_location_defaults, prepare_image_inputs, validate_locations};
+use models::{ModelsArguments, run_models};
+use speciesnet::{
+    DetectionClassificationOptions, DetectorConfig, DetectorKind, EnsembleConfig, PipelineOptions,
+    RunSummary, SoftNmsDecay, SpeciesNet, Suppression, TileMerge, TilingOptions,
+};
+use speciesnet_core::{
+    classifier::{ClassifierOutputOptions, ScoreRetention},
+    geography::validate_location,
+    io::{Instance, Instances, Prediction, Predictions},
+};
+use speciesnet_detector::{
+    config::{DEFAULT_CONFIDENCE_THRESHOLD, DEFAULT_IOU_THRESHOLD, DEFAULT_MAX_DETECTIONS},
+    suppression::DEFAULT_SOFT_NMS_SIGMA,
+    tiling::{DEFAULT_MERGE_IOU_THRESHOLD, DEFAULT_TILE_OVERLAP},
+};
+use taxonomy::{TaxonomyArguments, run_taxonomy};
+use tracing::{debug, info, warn};
 use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt};
 
+mod checkpoint;
 mod file_extension;
+mod geofence;
 mod inputs;
+mod models;
+mod taxonomy;
 
 /// The name of the environment variable that can be set to specify the log level of speciesnet.
 const SPECIESNET_LOG_ENV_NAME: &str = "SPECIESNET_LOG";
@@ -124,13 +370,27 @@ pub struct RunType {
     /// Running only the ensembler (requires detections-json and classifications-json).
     #[arg(long)]
     ensemble_only: bool,
-    /// Enables geofence while running the ensembler.
-    #[arg(long)]
+    /// Enables or disables the geofence while running the ensembler, `--geofence false` turns it
+    /// off.
+    #[arg(
+        long,
+        default_value_t = true,
+        action = clap::ArgAction::Set,
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
     geofence: bool,
+    /// Adds an `ensemble_trace` to each prediction, explaining which rule of the ensembler fired,
+    /// the scores it compared with their thresholds, the geofence lookups and the rollups.
+    #[arg(long)]
+    explain: bool,
 }
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CliArguments {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[command(flatten)]
     input_type: InputType,
     #[command(flatten)]
@@ -138,8 +398,279 @@ pub struct CliArguments {
     #[command(flatten)]
     additional_config: AdditionalConfiguration,
     /// Output predictions.json file path of the predictions result.
+    #[arg(long, required = true)]
+    predictions_json: Option<PathBuf>,
+    /// Continues from an existing predictions.json file, skipping the images which already have
+    /// predictions inside of it.
+    #[arg(long)]
+    resume: bool,
+    /// Number of images to run between each save of the predictions.json file. Each save rewrites
+    /// every prediction finished so far, so raise it on runs of hundreds of thousands of images.
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    checkpoint_every: u64,
+    /// Maximum number of items waiting in each queue of the pipeline and of finished predictions
+    /// waiting to be written out, the inference waits when the queue is full.
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u64).range(1..))]
+    queue_size: u64,
+    /// Number of images run through the detector and the classifier together, batch sizes larger
+    /// than 1 requires models exported with a dynamic batch axis.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    batch_size: u64,
+    /// Number of workers decoding the images while running the whole pipeline, defaults to half
+    /// of the cores.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    decode_workers: Option<u64>,
+    /// Number of workers running the detector while running the whole pipeline, defaults to a
+    /// quarter of the cores.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    detector_workers: Option<u64>,
+    /// Number of workers running the classifier while running the whole pipeline, defaults to a
+    /// quarter of the cores.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    classifier_workers: Option<u64>,
+    /// Number of threads each detector and classifier session uses to run the model, defaults to
+    /// 2.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    intra_threads: Option<u64>,
+    /// Number of threads running the detector-only, classifier-only and ensemble-only runs,
+    /// defaults to the number of cores.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    thread_pool_size: Option<u64>,
+    /// Path of a `.toml` or `.json` file of the ensemble thresholds, the thresholds which are not
+    /// in the file keep their default values.
+    #[arg(long)]
+    ensemble_config: Option<PathBuf>,
+    /// ISO 3166-1 alpha-3 country code, e.g. `USA`, filled in on every input which does not have
+    /// a country.
+    #[arg(long)]
+    country: Option<String>,
+    /// ISO 3166-2 subdivision code of the given country, e.g. `CA`, filled in on every input of
+    /// that country which does not have an admin1 region.
+    #[arg(long, requires = "country")]
+    admin1_region: Option<String>,
+    /// Classifies each detection on its own on top of the top one, listing every distinct label
+    /// found in `species_present` of the predictions.
+    #[arg(long)]
+    classify_detections: bool,
+    /// Minimum confidence of a detection for it to be classified on its own, between 0 and 1.
+    #[arg(long, default_value_t = 0.2, requires = "classify_detections")]
+    detection_min_confidence: f64,
+    /// Maximum number of detections classified on their own per image.
+    #[arg(
+        long,
+        default_value_t = 10,
+        requires = "classify_detections",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    max_detections: u64,
+    /// Number of the highest scoring classifications kept on each image.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    top_k: u64,
+    /// How much of the classifier's scores is kept for the rollups of the ensemble, only the top-k
+    /// classifications are written out either way.
+    #[arg(long, value_enum, default_value_t = ScoreRetentionArg::TopK)]
+    score_retention: ScoreRetentionArg,
+    /// Minimum score of the classifications kept by `--score-retention sparse`.
+    #[arg(long, default_value_t = 1e-3)]
+    sparse_min_score: f64,
+    /// Path of an ONNX detector model to use instead of the detector of the downloaded model.
+    #[arg(long)]
+    detector_model: Option<PathBuf>,
+    /// Output layout of the detector model.
+    #[arg(long, value_enum, default_value_t = DetectorKindArg::Yolov5)]
+    detector_kind: DetectorKindArg,
+    /// Minimum confidence of a detection to be kept, between 0 and 1.
+    #[arg(long, default_value_t = DEFAULT_CONFIDENCE_THRESHOLD)]
+    detector_confidence: f32,
+    /// IoU above which the less confident of two overlapping detections is suppressed, between 0
+    /// and 1.
+    #[arg(long, default_value_t = DEFAULT_IOU_THRESHOLD)]
+    detector_iou: f32,
+    /// Maximum number of detections kept on each image.
+    #[arg(long, default_value_t = DEFAULT_MAX_DETECTIONS as u64)]
+    detector_max_detections: u64,
+    /// Suppresses overlapping detections regardless of their categories.
     #[arg(long)]
-    predictions_json: PathBuf,
+    detector_class_agnostic: bool,
+    /// Minimum area of a detection as a fraction of the image's area, between 0 and 1.
+    #[arg(long, default_value_t = 0.0)]
+    detector_min_box_area: f64,
+    /// Minimum width and height of a detection in pixels.
+    #[arg(long, default_value_t = 0.0)]
+    detector_min_box_size: f64,
+    /// How the overlapping detections of an image are suppressed.
+    #[arg(long, value_enum, default_value_t = SuppressionArg::Nms)]
+    detector_suppression: SuppressionArg,
+    /// Sigma of `--detector-suppression soft-nms-gaussian`, greater than 0.
+    #[arg(long, default_value_t = DEFAULT_SOFT_NMS_SIGMA)]
+    soft_nms_sigma: f32,
+    /// Runs the detector on overlapping tiles of this many pixels instead of the whole image, for
+    /// the high resolution and panoramic images.
+    #[arg(long)]
+    tile_size: Option<u32>,
+    /// Fraction of a tile which overlaps its neighbours, between 0 and 0.9.
+    #[arg(long, default_value_t = DEFAULT_TILE_OVERLAP, requires = "tile_size")]
+    tile_overlap: f32,
+    /// How the detections of the overlapping tiles are merged.
+    #[arg(long, value_enum, default_value_t = TileMergeArg::Nms, requires = "tile_size")]
+    tile_merge: TileMergeArg,
+    /// IoU above which the detections of different tiles are merged, between 0 and 1.
+    #[arg(long, default_value_t = DEFAULT_MERGE_IOU_THRESHOLD, requires = "tile_size")]
+    tile_merge_iou: f32,
+}
+
+impl CliArguments {
+    /// Returns the path of the predictions file, which is required when running the models.
+    fn predictions_json(&self) -> &Path {
+        self.predictions_json
+            .as_deref()
+            .expect("--predictions-json is required unless a subcommand is given")
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Lists, downloads, verifies, selects and removes the cached models.
+    Models(ModelsArguments),
+    /// Looks up the labels of the taxonomy, their ancestors, their descendants and the labels the
+    /// classifier can output under them.
+    Taxonomy(TaxonomyArguments),
+    /// Lists the species allowed or blocked in a country, the countries which allow a species and
+    /// the species without geofence data.
+    Geofence(GeofenceArguments),
+}
+
+/// How much of the classifier's scores is kept for the rollups of the ensemble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ScoreRetentionArg {
+    /// Only the top-k classifications.
+    TopK,
+    /// Every classification.
+    Full,
+    /// The classifications with a score of at least `--sparse-min-score`.
+    Sparse,
+}
+
+/// Output layout of the detector model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum DetectorKindArg {
+    /// The `[1, boxes, 5 + classes]` output of YOLOv5, which MegaDetector v5 uses.
+    Yolov5,
+    /// The `[1, 4 + classes, boxes]` output of YOLOv8, YOLOv9 and MegaDetector v6.
+    AnchorFree,
+}
+
+/// How the overlapping detections of an image are suppressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum SuppressionArg {
+    /// Greedy non-max suppression, the same as the Python implementation.
+    Nms,
+    /// Soft-NMS decaying the confidences of the overlapping detections linearly.
+    SoftNmsLinear,
+    /// Soft-NMS decaying the confidences of the overlapping detections with a Gaussian.
+    SoftNmsGaussian,
+    /// Weighted boxes fusion, averaging the overlapping detections.
+    Wbf,
+}
+
+/// How the detections of the overlapping tiles are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum TileMergeArg {
+    /// Keeps the most confident of the overlapping detections.
+    Nms,
+    /// Averages the overlapping detections weighted by their confidences.
+    Wbf,
+}
+
+/// Runs the given prediction stream on the instances, saving every finished prediction to the
+/// predictions file each `checkpoint_every` images.
+///
+/// When resuming, the predictions from the existing predictions file are kept and the instances
+/// which already have a prediction are skipped, except the ones whose prediction has failures.
+/// When `interrupted` gets set, the predictions which have finished so far are saved right away
+/// and the run stops.
+///
+/// Each save rewrites every finished prediction, so the amount written over a run grows with the
+/// square of its number of images.
+fn run_with_checkpoints<F>(
+    args: &CliArguments,
+    instances: &[Instance],
+    interrupted: &AtomicBool,
+    pretty: bool,
+    predict_stream: F,
+) -> anyhow::Result<()>
+where
+    F: FnOnce(Vec<Instance>) -> anyhow::Result<Receiver<Prediction>>,
+{
+    // Loads the finished predictions from the last run when resuming.
+    let mut finished = if args.resume && args.predictions_json().exists() {
+        load_predictions(args.predictions_json())?
+    } else {
+        Vec::new()
+    };
+    let instances = remove_finished_instances(instances.to_vec(), &mut finished);
+
+    let total_instances = instances.len();
+    let mut done_instances = 0;
+    let mut summary = RunSummary::default();
+
+    let predictions = predict_stream(instances)?;
+
+    for prediction in predictions.iter() {
+        debug!(
+            "Finished {}, {}/{} images done.",
+            prediction.file_path().display(),
+            done_instances + 1,
+            total_instances
+        );
+
+        summary.add(&prediction);
+        finished.push(prediction);
+        done_instances += 1;
+
+        if interrupted.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if done_instances % args.checkpoint_every as usize == 0 {
+            save_predictions(args.predictions_json(), &finished, pretty)?;
+
+            info!(
+                "Checkpoint saved to {}, {}/{} images done.",
+                args.predictions_json().display(),
+                done_instances,
+                total_instances
+            );
+        }
+    }
+
+    save_predictions(args.predictions_json(), &finished, pretty)?;
+
+    if interrupted.load(Ordering::SeqCst) && done_instances < total_instances {
+        warn!(
+            "Run interrupted, {} predictions were saved to {}, run again with --resume to continue.",
+            finished.len(),
+            args.predictions_json().display()
+        );
+    } else {
+        info!(
+            "Predictions file has been successfully saved to {}.",
+            args.predictions_json().display()
+        );
+    }
+
+    log_summary(&summary);
+
+    Ok(())
+}
+
+/// Logs the summary of the run, as a warning when some of the images have failed.
+fn log_summary(summary: &RunSummary) {
+    if summary.failed() > 0 {
+        warn!("{}", summary);
+    } else {
+        info!("{}", summary);
+    }
 }
 
 fn main() -> anyhow::Result<()> {
@@ -154,18 +685,35 @@ fn main() -> anyhow::Result<()> {
     let args = CliArguments::parse();
     let mut cmd = CliArguments::command();
 
-    // Stops the run if predictions-json exists.
-    if args.predictions_json.exists() {
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Models(arguments) => run_models(arguments),
+            Command::Taxonomy(arguments) => run_taxonomy(arguments),
+            Command::Geofence(arguments) => run_geofence(arguments),
+        };
+    }
+
+    // Stops the run if predictions-json exists, unless we are resuming from it.
+    if args.predictions_json().exists() && !args.resume {
         cmd.error(
             ErrorKind::ValueValidation,
             format!(
-                "Predictions file at {:?} already exists.",
-                args.predictions_json.display()
+                "Predictions file at {:?} already exists, pass --resume to continue from it.",
+                args.predictions_json().display()
             ),
         )
         .exit();
     }
 
+    // Resuming only works on the runs which takes the images as their input.
+    if args.resume && (args.run_type.classifier_only || args.run_type.ensemble_only) {
+        cmd.error(
+            ErrorKind::ArgumentConflict,
+            "--resume can only be used when running the detector or the whole pipeline.",
+        )
+        .exit();
+    }
+
     // Classifier can only be run when detections-json is provided.
     if args.run_type.classifier_only && args.additional_config.detections_json.is_none() {
         cmd.error(
@@ -186,70 +734,213 @@ fn main() -> anyhow::Result<()> {
         ).exit();
     }
 
+    // Validates the location codes before any model gets loaded.
+    if let Err(e) = validate_location(args.country.as_deref(), args.admin1_region.as_deref()) {
+        cmd.error(ErrorKind::ValueValidation, e).exit();
+    }
+
+    let detector_config = DetectorConfig {
+        confidence_threshold: args.detector_confidence,
+        iou_threshold: args.detector_iou,
+        max_detections: args.detector_max_detections as usize,
+        class_agnostic: args.detector_class_agnostic,
+        min_box_area: args.detector_min_box_area,
+        min_box_size: args.detector_min_box_size,
+        suppression: match args.detector_suppression {
+            SuppressionArg::Nms => Suppression::Nms,
+            SuppressionArg::SoftNmsLinear => Suppression::SoftNms(SoftNmsDecay::Linear),
+            SuppressionArg::SoftNmsGaussian => Suppression::SoftNms(SoftNmsDecay::Gaussian {
+                sigma: args.soft_nms_sigma,
+            }),
+            SuppressionArg::Wbf => Suppression::WeightedBoxFusion,
+        },
+    };
+
+    if let Err(e) = detector_config.validate() {
+        cmd.error(ErrorKind::ValueValidation, e).exit();
+    }
+
+    let tiling = args.tile_size.map(|tile_size| TilingOptions {
+        tile_size,
+        overlap: args.tile_overlap,
+        merge: match args.tile_merge {
+            TileMergeArg::Nms => TileMerge::Nms,
+            TileMergeArg::Wbf => TileMerge::WeightedBoxFusion,
+        },
+        merge_iou_threshold: args.tile_merge_iou,
+        ..TilingOptions::default()
+    });
+
+    if let Some(Err(e)) = tiling.map(|tiling| tiling.validate()) {
+        cmd.error(ErrorKind::ValueValidation, e).exit();
+    }
+
+    if !(0.0..=1.0).contains(&args.detection_min_confidence) {
+        cmd.error(
+            ErrorKind::ValueValidation,
+            format!(
+                "`--detection-min-confidence` must be between 0 and 1, but found {}",
+                args.detection_min_confidence
+            ),
+        )
+        .exit();
+    }
+
     // Parse the input files into list of files.
-    let images = prepare_image_inputs(&args.input_type)?;
-    let speciesnet = SpeciesNet::new()?;
+    let mut images = prepare_image_inputs(&args.input_type)?;
+    apply_location_defaults(
+        &mut images,
+        args.country.as_deref(),
+        args.admin1_region.as_deref(),
+    );
+    validate_locations(&images)?;
+    let mut pipeline_options = PipelineOptions::default();
+    pipeline_options.set_queue_size(args.queue_size as usize);
 
-    if args.run_type.detector_only {
-        let detector_results = speciesnet.detect(&images)?;
-        let predictions = Predictions::from(detector_results);
+    if let Some(decode_workers) = args.decode_workers {
+        pipeline_options.set_decode_workers(decode_workers as usize);
+    }
 
-        info!(
-            "Saving the detected results to {}.",
-            args.predictions_json.display()
-        );
+    if let Some(detector_workers) = args.detector_workers {
+        pipeline_options.set_detector_workers(detector_workers as usize);
+    }
 
-        let writer = BufWriter::new(File::create(&args.predictions_json)?);
-        serde_json::to_writer(writer, &predictions)?;
+    if let Some(classifier_workers) = args.classifier_workers {
+        pipeline_options.set_classifier_workers(classifier_workers as usize);
+    }
 
-        info!(
-            "Predictions file has been successfully saved to {}.",
-            args.predictions_json.display()
-        );
+    let mut builder = SpeciesNet::builder();
+    builder
+        .batch_size(args.batch_size as usize)
+        .pipeline_options(pipeline_options)
+        .enable_geofence(args.run_type.geofence)
+        .explain(args.run_type.explain)
+        .detector_config(detector_config)
+        .detector_kind(match args.detector_kind {
+            DetectorKindArg::Yolov5 => DetectorKind::YoloV5,
+            DetectorKindArg::AnchorFree => DetectorKind::AnchorFree,
+        });
+
+    if let Some(detector_model) = &args.detector_model {
+        builder.detector_model(detector_model);
+    }
+
+    if let Some(tiling) = tiling {
+        builder.tiling(tiling);
+    }
+
+    if let Some(intra_threads) = args.intra_threads {
+        builder.intra_threads(intra_threads as usize);
+    }
+
+    if let Some(thread_pool_size) = args.thread_pool_size {
+        builder.thread_pool_size(thread_pool_size as usize);
+    }
+
+    if args.classify_detections {
+        let mut detection_classification = DetectionClassificationOptions::default();
+        detection_classification
+            .set_min_confidence(args.detection_min_confidence)
+            .set_max_detections(args.max_detections as usize);
+        builder.classify_detections(detection_classification);
+    }
+
+    let mut classifier_output = ClassifierOutputOptions::default();
+    classifier_output
+        .set_top_k(args.top_k as usize)
+        .set_retention(match args.score_retention {
+            ScoreRetentionArg::TopK => ScoreRetention::TopK,
+            ScoreRetentionArg::Full => ScoreRetention::Full,
+            ScoreRetentionArg::Sparse => ScoreRetention::Sparse {
+                min_score: args.sparse_min_score,
+            },
+        });
+    builder.classifier_output(classifier_output);
+
+    if let Some(ensemble_config) = &args.ensemble_config {
+        builder.ensemble_config(EnsembleConfig::from_file(ensemble_config)?);
+    }
+
+    let speciesnet = builder.build()?;
+
+    // The first Ctrl-C saves the finished predictions and stops the run, the second one exits right
+    // away.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = Arc::clone(&interrupted);
+        ctrlc::set_handler(move || {
+            if interrupted.swap(true, Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+
+            warn!("Interrupted, saving the predictions after the next image finishes.");
+        })?;
+    }
+
+    if args.run_type.detector_only {
+        run_with_checkpoints(&args, &images, &interrupted, false, |instances| {
+            Ok(speciesnet.detect_stream(instances, args.queue_size as usize)?)
+        })?;
     }
 
     if args.run_type.classifier_only {
         let output_detection_path = args.additional_config.detections_json.clone();
         let classifier_results = speciesnet.classify(&output_detection_path.unwrap())?; // assumed labels is in the same folder as model
+        let summary = RunSummary::from_predictions(&classifier_results);
         let predictions = Predictions::from(classifier_results);
 
         info!(
             "Saving the classified results to {}.",
-            args.predictions_json.display()
+            args.predictions_json().display()
         );
 
-        let writer = BufWriter::new(File::create(&args.predictions_json)?);
+        let writer = BufWriter::new(File::create(args.predictions_json())?);
         serde_json::to_writer(writer, &predictions)?;
 
         info!(
             "Predictions file has been successfully saved to {}.",
-            args.predictions_json.display()
+            args.predictions_json().display()
         );
+        log_summary(&summary);
     }
 
     if args.run_type.ensemble_only {
         let instances_json_path = args.input_type.instances_json.clone();
         let output_detection_path = args.additional_config.detections_json.clone();
         let output_classification_path = args.additional_config.classifications_json.clone();
-        let ensemble_results = speciesnet.ensemble(
-            &instances_json_path.unwrap(),
+
+        // The file paths are kept as they are written, they are matched against the ones of the
+        // detections and the classifications.
+        let instances_file = BufReader::new(File::open(instances_json_path.unwrap())?);
+        let mut instances: Instances = serde_json::from_reader(instances_file)?;
+        apply_location_defaults(
+            instances.instances_mut(),
+            args.country.as_deref(),
+            args.admin1_region.as_deref(),
+        );
+        validate_locations(instances.instances())?;
+
+        let ensemble_results = speciesnet.ensemble_instances(
+            instances.instances(),
             &output_detection_path.unwrap(),
             &output_classification_path.unwrap(),
         )?;
+        let summary = RunSummary::from_predictions(&ensemble_results);
         let predictions = Predictions::from(ensemble_results);
 
         info!(
             "Saving the classified results to {}.",
-            args.predictions_json.display()
+            args.predictions_json().display()
         );
 
-        let writer = BufWriter::new(File::create(&args.predictions_json)?);
+        let writer = BufWriter::new(File::create(args.predictions_json())?);
         serde_json::to_writer(writer, &predictions)?;
 
         info!(
             "Predictions file has been successfully saved to {}.",
-            args.predictions_json.display()
+            args.predictions_json().display()
         );
+        log_summary(&summary);
     }
 
     // Performs full inference when none of the options are set.
@@ -257,21 +948,9 @@ fn main() -> anyhow::Result<()> {
         && !args.run_type.classifier_only
         && !args.run_type.ensemble_only
     {
-        let full_results = speciesnet.predict(&images)?;
-        let predictions = Predictions::from(full_results);
-
-        info!(
-            "Saving the detected results to {}.",
-            args.predictions_json.display()
-        );
-
-        let writer = BufWriter::new(File::create(&args.predictions_json)?);
-        serde_json::to_writer_pretty(writer, &predictions)?;
-
-        info!(
-            "Predictions file has been successfully saved to {}.",
-            args.predictions_json.display()
-        );
+        run_with_checkpoints(&args, &images, &interrupted, true, |instances| {
+            Ok(speciesnet.predict_stream(instances, args.queue_size as usize)?)
+        })?;
     }
 
     info!("Program finished.");
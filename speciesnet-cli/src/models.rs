@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use clap::{Args, Subcommand};
+use speciesnet::model_info::{
+    ModelInfo,
+    registry::{Manifest, ModelRegistry},
+};
+use tracing::{error, info, warn};
+
+#[derive(Debug, Args)]
+pub struct ModelsArguments {
+    /// Path of a json manifest of the model versions to use instead of the built-in one.
+    #[arg(long, global = true)]
+    manifest: Option<PathBuf>,
+    #[command(subcommand)]
+    command: ModelsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ModelsCommand {
+    /// Lists the model versions of the manifest and the ones in the cache, the selected version is
+    /// marked with `*`.
+    List,
+    /// Downloads a model version, the selected version when none is given.
+    Download {
+        version: Option<String>,
+        /// Downloads the version again even if it is already cached.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Imports a model from a local `.zip`, `.tar.gz` or `.tgz` archive into the cache, without
+    /// downloading anything.
+    Import {
+        archive: PathBuf,
+        /// Selects the imported version once it has been imported.
+        #[arg(long)]
+        select: bool,
+    },
+    /// Checks the files and the checksum of a cached model version, every cached version when
+    /// none is given.
+    Verify { version: Option<String> },
+    /// Selects the model version used when running the models.
+    Select { version: String },
+    /// Removes a model version from the cache.
+    Remove { version: String },
+}
+
+/// Runs the `models` subcommand.
+pub fn run_models(arguments: &ModelsArguments) -> anyhow::Result<()> {
+    let mut registry = ModelRegistry::new()?;
+
+    if let Some(manifest) = &arguments.manifest {
+        registry.set_manifest(Manifest::from_file(manifest)?);
+    }
+
+    info!(
+        "Model cache directory is {}.",
+        registry.cache_dir().display()
+    );
+
+    match &arguments.command {
+        ModelsCommand::List => {
+            for model in registry.list()? {
+                let line = format!(
+                    "{} {:<12} {:<10} {}",
+                    if model.selected { "*" } else { " " },
+                    model.version,
+                    if model.cached { "cached" } else { "-" },
+                    if model.in_manifest {
+                        ""
+                    } else {
+                        "(not in manifest)"
+                    },
+                );
+                println!("{}", line.trim_end());
+            }
+        }
+        ModelsCommand::Download { version, force } => {
+            let version = match version {
+                Some(version) => version.clone(),
+                None => registry.selected()?,
+            };
+            let model_info = if *force {
+                registry.download(&version)?
+            } else {
+                registry.fetch(&version)?
+            };
+
+            info!(
+                "Model version {} is ready at {}.",
+                model_info.version(),
+                registry.model_folder(&version)?.display()
+            );
+        }
+        ModelsCommand::Import { archive, select } => {
+            let model_info = registry.import(archive)?;
+
+            if *select {
+                registry.select(model_info.version())?;
+            }
+        }
+        ModelsCommand::Verify { version } => {
+            let versions: Vec<String> = match version {
+                Some(version) => vec![version.clone()],
+                None => registry
+                    .list()?
+                    .into_iter()
+                    .filter(|model| model.cached)
+                    .map(|model| model.version)
+                    .collect(),
+            };
+
+            if versions.is_empty() {
+                warn!("No model has been downloaded yet.");
+            }
+
+            let mut failed = 0;
+
+            for version in &versions {
+                match registry.verify(version) {
+                    Ok(_) => info!("Model version {} is OK.", version),
+                    Err(e) => {
+                        error!("Model version {} failed the verification: {}", version, e);
+                        failed += 1;
+                    }
+                }
+            }
+
+            if failed > 0 {
+                bail!(
+                    "{} of {} model versions failed the verification.",
+                    failed,
+                    versions.len()
+                );
+            }
+        }
+        ModelsCommand::Select { version } => registry.select(version)?,
+        ModelsCommand::Remove { version } => {
+            if !registry.remove(version)? {
+                warn!("Model version {} is not in the cache.", version);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the model of the given extracted model folder, the selected model of the cache when none
+/// is given.
+pub fn load_model_info(model_folder: Option<&Path>) -> anyhow::Result<ModelInfo> {
+    Ok(match model_folder {
+        Some(model_folder) => ModelInfo::from_path(model_folder)?,
+        None => ModelRegistry::new()?.load()?,
+    })
+}
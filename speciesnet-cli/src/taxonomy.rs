@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use anyhow::bail;
+use clap::{Args, Subcommand};
+use speciesnet::{MatchKind, Taxonomy};
+use speciesnet_core::taxonomy::{Label, Rank};
+use tracing::info;
+
+use crate::models::load_model_info;
+
+#[derive(Debug, Args)]
+pub struct TaxonomyArguments {
+    /// Folder of an extracted model to read the taxonomy from instead of the selected model of
+    /// the cache.
+    #[arg(long, global = true)]
+    model_folder: Option<PathBuf>,
+    #[command(subcommand)]
+    command: TaxonomyCommand,
+}
+
+/// The labels are looked up by UUID, common name or scientific name, ignoring the case and small
+/// typos. Labels the classifier can output are marked with `*`.
+#[derive(Debug, Subcommand)]
+pub enum TaxonomyCommand {
+    /// Lists the labels matching the query, from the best match to the worst.
+    Find {
+        query: String,
+        /// Maximum number of labels to list.
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Shows a label, whether the classifier can output it and the ancestors it rolls up to.
+    Show { query: String },
+    /// Lists the labels of the taxonomy at a rank under a taxon, e.g. the species of a family.
+    Descendants {
+        query: String,
+        /// One of `class`, `order`, `family`, `genus` or `species`.
+        #[arg(long, default_value = "species")]
+        rank: Rank,
+    },
+    /// Lists the labels the classifier can output under a taxon.
+    Outputs { query: String },
+}
+
+/// Runs the `taxonomy` subcommand.
+pub fn run_taxonomy(arguments: &TaxonomyArguments) -> anyhow::Result<()> {
+    let model_info = load_model_info(arguments.model_folder.as_deref())?;
+    let taxonomy = Taxonomy::from_files(model_info.taxonomy(), model_info.classifier_labels())?;
+
+    match &arguments.command {
+        TaxonomyCommand::Find { query, limit } => {
+            let matches = taxonomy.search(query);
+
+            if matches.is_empty() {
+                bail!("No label matches {:?}.", query);
+            }
+
+            for taxon_match in matches.iter().take(*limit) {
+                println!("{}", describe(&taxonomy, taxon_match.label));
+            }
+        }
+        TaxonomyCommand::Show { query } => {
+            let label = resolve(&taxonomy, query)?;
+
+            println!("{}", describe(&taxonomy, label));
+            println!(
+                "  {}",
+                if taxonomy.is_output(label) {
+                    "output by the classifier"
+                } else {
+                    "not output by the classifier"
+                }
+            );
+
+            let ancestors = taxonomy.ancestors(label);
+
+            if !ancestors.is_empty() {
+                println!("  rolls up to:");
+            }
+
+            for ancestor in ancestors {
+                println!("    {}", describe(&taxonomy, ancestor));
+            }
+        }
+        TaxonomyCommand::Descendants { query, rank } => {
+            let label = resolve(&taxonomy, query)?;
+
+            for descendant in taxonomy.descendants(label, *rank) {
+                println!("{}", describe(&taxonomy, descendant));
+            }
+        }
+        TaxonomyCommand::Outputs { query } => {
+            let label = resolve(&taxonomy, query)?;
+            let outputs = taxonomy.outputs_under(label);
+
+            info!(
+                "The classifier can output {} labels under {}.",
+                outputs.len(),
+                label.common_name()
+            );
+
+            for output in outputs {
+                println!("{}", describe(&taxonomy, output));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the label the query is about, failing when several labels match it equally well.
+pub fn resolve<'a>(taxonomy: &'a Taxonomy, query: &str) -> anyhow::Result<&'a Label> {
+    let matches = taxonomy.search(query);
+
+    let Some(best) = matches.first() else {
+        bail!("No label matches {:?}.", query);
+    };
+
+    let tied: Vec<&Label> = matches
+        .iter()
+        .take_while(|taxon_match| taxon_match.kind == best.kind)
+        .map(|taxon_match| taxon_match.label)
+        .collect();
+
+    if tied.len() > 1 {
+        let candidates: Vec<String> = tied
+            .iter()
+            .take(10)
+            .map(|label| format!("  {}", describe(taxonomy, label)))
+            .collect();
+
+        bail!(
+            "{} labels match {:?}, use one of their UUIDs instead:\n{}",
+            tied.len(),
+            query,
+            candidates.join("\n")
+        );
+    }
+
+    if best.kind != MatchKind::Exact {
+        info!("Using {} for {:?}.", best.label.common_name(), query);
+    }
+
+    Ok(best.label)
+}
+
+/// Formats a label as `* common name (scientific name, rank) uuid`, the `*` marking the labels
+/// the classifier can output.
+pub fn describe(taxonomy: &Taxonomy, label: &Label) -> String {
+    format!(
+        "{} {}",
+        if taxonomy.is_output(label) { "*" } else { " " },
+        format_label(label)
+    )
+}
+
+/// Formats a label as `common name (scientific name, rank) uuid`.
+pub fn format_label(label: &Label) -> String {
+    format!(
+        "{} ({}, {}) {}",
+        label.common_name(),
+        label.scientific_name().unwrap_or_else(|| "-".to_string()),
+        label
+            .rank()
+            .map_or_else(|| "-".to_string(), |rank| rank.to_string()),
+        label.id()
+    )
+}
@@ -0,0 +1,351 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rayon::ThreadPoolBuilder;
+use speciesnet_classifier::{
+    SpeciesNetClassifier, classifier::read_labels_from_file, model::Classifier,
+};
+use speciesnet_core::{
+    classifier::ClassifierOutputOptions,
+    constants::detector::IMAGE_HEIGHT,
+    session::{OptimizationLevel, SessionOptions},
+    shape::Shape,
+};
+use speciesnet_detector::{
+    SpeciesNetDetector,
+    config::DetectorConfig,
+    model::{Detector, DetectorKind},
+    preprocess::LetterboxOptions,
+    tiling::TilingOptions,
+};
+use speciesnet_ensemble::{SpeciesNetEnsemble, config::EnsembleConfig};
+use tracing::info;
+
+use crate::{
+    detection_classification::DetectionClassificationOptions,
+    error::Error,
+    model_info::ModelInfo,
+    pipeline::PipelineOptions,
+    speciesnet::{DEFAULT_BATCH_SIZE, SpeciesNet},
+};
+
+/// Builder for configuring the runtime options of [`SpeciesNet`].
+///
+/// Every instance built owns its own sessions, options and thread pool, so multiple differently
+/// configured instances can be used in the same process.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use speciesnet::SpeciesNet;
+/// use speciesnet_core::session::OptimizationLevel;
+///
+/// let speciesnet = SpeciesNet::builder()
+///     .model_folder("./speciesnet-v4a/")
+///     .intra_threads(4)
+///     .optimization_level(OptimizationLevel::Level2)
+///     .thread_pool_size(8)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpeciesNetBuilder {
+    model_folder: Option<PathBuf>,
+    detector_session_options: SessionOptions,
+    classifier_session_options: SessionOptions,
+    letterbox_shape: Shape,
+    classifier: Option<Arc<dyn Classifier>>,
+    detector_model: Option<PathBuf>,
+    detector_kind: DetectorKind,
+    detector: Option<Arc<dyn Detector>>,
+    detector_config: DetectorConfig,
+    tiling: Option<TilingOptions>,
+    geofence_fix_files: Vec<PathBuf>,
+    ensemble_config: EnsembleConfig,
+    enable_geofence: bool,
+    explain: bool,
+    thread_pool_size: Option<usize>,
+    batch_size: usize,
+    pipeline_options: PipelineOptions,
+    detection_classification: Option<DetectionClassificationOptions>,
+    classifier_output: ClassifierOutputOptions,
+}
+
+impl Default for SpeciesNetBuilder {
+    fn default() -> Self {
+        Self {
+            model_folder: None,
+            detector_session_options: SessionOptions::default(),
+            classifier_session_options: SessionOptions::default(),
+            letterbox_shape: Shape::Square(IMAGE_HEIGHT),
+            classifier: None,
+            detector_model: None,
+            detector_kind: DetectorKind::default(),
+            detector: None,
+            detector_config: DetectorConfig::default(),
+            tiling: None,
+            geofence_fix_files: Vec::new(),
+            ensemble_config: EnsembleConfig::default(),
+            enable_geofence: true,
+            explain: false,
+            thread_pool_size: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            pipeline_options: PipelineOptions::default(),
+            detection_classification: None,
+            classifier_output: ClassifierOutputOptions::default(),
+        }
+    }
+}
+
+impl SpeciesNetBuilder {
+    /// Sets the folder of the extracted model to load. When this is not set, the default model is
+    /// downloaded with the `download-model` feature.
+    pub fn model_folder<P>(&mut self, model_folder: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.model_folder = Some(model_folder.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the number of intra threads of both the detector and the classifier sessions.
+    pub fn intra_threads(&mut self, intra_threads: usize) -> &mut Self {
+        self.detector_session_options
+            .set_intra_threads(intra_threads);
+        self.classifier_session_options
+            .set_intra_threads(intra_threads);
+        self
+    }
+
+    /// Sets the graph optimization level of both the detector and the classifier sessions.
+    pub fn optimization_level(&mut self, optimization_level: OptimizationLevel) -> &mut Self {
+        self.detector_session_options
+            .set_optimization_level(optimization_level);
+        self.classifier_session_options
+            .set_optimization_level(optimization_level);
+        self
+    }
+
+    pub fn detector_session_options(&mut self, options: SessionOptions) -> &mut Self {
+        self.detector_session_options = options;
+        self
+    }
+
+    pub fn classifier_session_options(&mut self, options: SessionOptions) -> &mut Self {
+        self.classifier_session_options = options;
+        self
+    }
+
+    /// Sets the shape the images are letterboxed into before going into the detector.
+    pub fn letterbox_shape(&mut self, shape: Shape) -> &mut Self {
+        self.letterbox_shape = shape;
+        self
+    }
+
+    /// Runs the images through the given classifier instead of the classifier of the model folder,
+    /// the labels of the model folder must still match its scores.
+    pub fn classifier(&mut self, classifier: Arc<dyn Classifier>) -> &mut Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// Loads the detector from the given ONNX file instead of the detector of the model folder,
+    /// e.g. to try a newer MegaDetector release. See [`SpeciesNetBuilder::detector_kind`] for the
+    /// output layout of the model.
+    pub fn detector_model<P>(&mut self, detector_model: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.detector_model = Some(detector_model.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the output layout of the detector model, the YOLOv5 layout of MegaDetector v5 by
+    /// default.
+    pub fn detector_kind(&mut self, detector_kind: DetectorKind) -> &mut Self {
+        self.detector_kind = detector_kind;
+        self
+    }
+
+    /// Runs the images through the given detector instead of loading one, the detector model and
+    /// kind are then ignored.
+    pub fn detector(&mut self, detector: Arc<dyn Detector>) -> &mut Self {
+        self.detector = Some(detector);
+        self
+    }
+
+    /// Sets the post-processing options of the detector, they are validated when building.
+    pub fn detector_config(&mut self, detector_config: DetectorConfig) -> &mut Self {
+        self.detector_config = detector_config;
+        self
+    }
+
+    /// Runs the detector on overlapping tiles of each image, see [`SpeciesNet::set_tiling`]. The
+    /// options are validated when building.
+    pub fn tiling(&mut self, tiling: TilingOptions) -> &mut Self {
+        self.tiling = Some(tiling);
+        self
+    }
+
+    /// Adds a geofence fix file to apply on top of the geofence base of the model. The fix files
+    /// are applied in the order they are added, so the later ones override the earlier ones.
+    pub fn geofence_fix_file<P>(&mut self, geofence_fix_file: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.geofence_fix_files
+            .push(geofence_fix_file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the decision thresholds of the ensemble.
+    pub fn ensemble_config(&mut self, ensemble_config: EnsembleConfig) -> &mut Self {
+        self.ensemble_config = ensemble_config;
+        self
+    }
+
+    /// See [`SpeciesNet::set_enable_geofence`], geofencing is enabled by default.
+    pub fn enable_geofence(&mut self, enable_geofence: bool) -> &mut Self {
+        self.enable_geofence = enable_geofence;
+        self
+    }
+
+    /// See [`SpeciesNet::set_explain`], disabled by default.
+    pub fn explain(&mut self, explain: bool) -> &mut Self {
+        self.explain = explain;
+        self
+    }
+
+    /// Runs the parallel parts of [`SpeciesNet::detect`], [`SpeciesNet::classify`] and
+    /// [`SpeciesNet::ensemble`] on a dedicated rayon thread pool with the given number of threads
+    /// instead of the global one.
+    pub fn thread_pool_size(&mut self, thread_pool_size: usize) -> &mut Self {
+        self.thread_pool_size = Some(thread_pool_size);
+        self
+    }
+
+    /// See [`SpeciesNet::set_batch_size`].
+    pub fn batch_size(&mut self, batch_size: usize) -> &mut Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// See [`SpeciesNet::set_pipeline_options`].
+    pub fn pipeline_options(&mut self, pipeline_options: PipelineOptions) -> &mut Self {
+        self.pipeline_options = pipeline_options;
+        self
+    }
+
+    /// Classifies each detection on its own with the given options, see
+    /// [`SpeciesNet::set_detection_classification`].
+    pub fn classify_detections(
+        &mut self,
+        detection_classification: DetectionClassificationOptions,
+    ) -> &mut Self {
+        self.detection_classification = Some(detection_classification);
+        self
+    }
+
+    /// See [`SpeciesNet::set_classifier_output`].
+    pub fn classifier_output(&mut self, classifier_output: ClassifierOutputOptions) -> &mut Self {
+        self.classifier_output = classifier_output;
+        self
+    }
+
+    /// Loads the models and builds the [`SpeciesNet`] instance.
+    pub fn build(&self) -> Result<SpeciesNet, Error> {
+        let model_info = match &self.model_folder {
+            Some(model_folder) => ModelInfo::from_path(model_folder)?,
+            #[cfg(feature = "download-model")]
+            None => ModelInfo::from_default_url()?,
+            #[cfg(not(feature = "download-model"))]
+            None => return Err(Error::MissingModelFolder),
+        };
+
+        self.build_from_model_info(model_info)
+    }
+
+    pub(crate) fn build_from_model_info(&self, model_info: ModelInfo) -> Result<SpeciesNet, Error> {
+        // Rejects a bad config before spending the time on loading the models.
+        self.detector_config.validate()?;
+        if let Some(tiling) = &self.tiling {
+            tiling.validate()?;
+        }
+
+        let mut classifier = match &self.classifier {
+            Some(classifier) => SpeciesNetClassifier::from_classifier(Arc::clone(classifier)),
+            None => SpeciesNetClassifier::with_spec(
+                model_info.classifier(),
+                &self.classifier_session_options,
+                model_info.classifier_input().cloned(),
+            )?,
+        };
+        classifier.set_model_type(model_info.model_type());
+        classifier
+            .check_label_count(read_labels_from_file(model_info.classifier_labels())?.len())?;
+        info!("Classifier initialized.");
+
+        let mut detector = match &self.detector {
+            Some(detector) => SpeciesNetDetector::from_detector(Arc::clone(detector)),
+            None => SpeciesNetDetector::from_detector(
+                self.detector_kind.load(
+                    self.detector_model
+                        .as_deref()
+                        .unwrap_or(model_info.detector()),
+                    &self.detector_session_options,
+                )?,
+            ),
+        };
+        detector
+            .set_config(self.detector_config)?
+            .set_tiling(self.tiling)?;
+        info!("Detector initialized.");
+
+        let mut ensemble = SpeciesNetEnsemble::new(
+            model_info.geofence(),
+            model_info.taxonomy(),
+            None,
+            self.ensemble_config.clone(),
+        )?;
+        for geofence_fix_file in &self.geofence_fix_files {
+            ensemble.geofence_mut().apply_fixes(geofence_fix_file)?;
+        }
+        ensemble
+            .set_enable_geofence(self.enable_geofence)
+            .set_explain(self.explain);
+        info!("Ensemble initialized.");
+
+        let letterbox_options = LetterboxOptions::builder()
+            .shape(self.letterbox_shape)
+            .build();
+
+        let thread_pool = match self.thread_pool_size {
+            Some(thread_pool_size) => Some(Arc::new(
+                ThreadPoolBuilder::new()
+                    .num_threads(thread_pool_size)
+                    .thread_name(|i| format!("speciesnet-{i}"))
+                    .build()?,
+            )),
+            None => None,
+        };
+
+        let mut speciesnet = SpeciesNet::from_parts(
+            model_info,
+            detector,
+            classifier,
+            ensemble,
+            letterbox_options,
+            thread_pool,
+        );
+
+        speciesnet
+            .set_batch_size(self.batch_size)
+            .set_pipeline_options(self.pipeline_options)
+            .set_detection_classification(self.detection_classification)
+            .set_classifier_output(self.classifier_output);
+
+        Ok(speciesnet)
+    }
+}
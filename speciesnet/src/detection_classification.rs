@@ -0,0 +1,64 @@
+use speciesnet_core::detector::Detection;
+
+#[cfg(test)]
+mod tests;
+
+/// Default minimum confidence of a detection for it to be classified on its own.
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.2;
+
+/// Default maximum number of detections of an image which are classified on their own.
+pub const DEFAULT_MAX_DETECTIONS: usize = 10;
+
+/// Options of classifying each detection of an image on its own, instead of only the top one.
+///
+/// The detections whose confidence is at least `min_confidence` are cropped and classified, up to
+/// `max_detections` detections per image in the order the detector returns them. Each of them gets
+/// its own classifications, and the ensemble lists the distinct labels found among them in the
+/// `species_present` of the prediction, next to the usual image-level label.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectionClassificationOptions {
+    min_confidence: f64,
+    max_detections: usize,
+}
+
+impl Default for DetectionClassificationOptions {
+    fn default() -> Self {
+        Self {
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+            max_detections: DEFAULT_MAX_DETECTIONS,
+        }
+    }
+}
+
+impl DetectionClassificationOptions {
+    /// Sets the minimum confidence of a detection for it to be classified on its own.
+    pub fn set_min_confidence(&mut self, min_confidence: f64) -> &mut Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    /// Sets the maximum number of detections classified on their own per image, 0 is treated as 1.
+    pub fn set_max_detections(&mut self, max_detections: usize) -> &mut Self {
+        self.max_detections = max_detections.max(1);
+        self
+    }
+
+    pub fn min_confidence(&self) -> f64 {
+        self.min_confidence
+    }
+
+    pub fn max_detections(&self) -> usize {
+        self.max_detections
+    }
+
+    /// Returns the indexes of the detections which are classified on their own.
+    pub(crate) fn select(&self, detections: &[Detection]) -> Vec<usize> {
+        detections
+            .iter()
+            .enumerate()
+            .filter(|(_, detection)| detection.confidence() >= self.min_confidence)
+            .map(|(index, _)| index)
+            .take(self.max_detections)
+            .collect()
+    }
+}
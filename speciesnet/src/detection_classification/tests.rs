@@ -0,0 +1,49 @@
+use speciesnet_core::detector::{BoundingBox, Category, Detection};
+
+use crate::detection_classification::DetectionClassificationOptions;
+
+fn detections(confidences: &[f64]) -> Vec<Detection> {
+    confidences
+        .iter()
+        .map(|&confidence| {
+            Detection::new(
+                Category::Animal,
+                confidence,
+                BoundingBox::new(0.1, 0.1, 0.5, 0.5),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_select_filters_by_confidence() {
+    let detections = detections(&[0.9, 0.1, 0.2, 0.19, 0.5]);
+
+    // The minimum confidence is included.
+    assert_eq!(
+        DetectionClassificationOptions::default().select(&detections),
+        vec![0, 2, 4]
+    );
+    assert_eq!(
+        DetectionClassificationOptions::default()
+            .set_min_confidence(0.95)
+            .select(&detections),
+        Vec::<usize>::new()
+    );
+}
+
+#[test]
+fn test_select_caps_the_number_of_detections() {
+    let detections = detections(&[0.1, 0.9, 0.8, 0.7, 0.6]);
+    let mut options = DetectionClassificationOptions::default();
+
+    // The first detections above the minimum confidence are kept, in the order of the detector.
+    assert_eq!(
+        options.set_max_detections(2).select(&detections),
+        vec![1, 2]
+    );
+
+    // 0 is treated as 1.
+    assert_eq!(options.set_max_detections(0).max_detections(), 1);
+    assert_eq!(options.select(&detections), vec![1]);
+}
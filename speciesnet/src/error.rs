@@ -12,6 +12,13 @@ pub enum Error {
     CoreError(#[from] speciesnet_core::error::Error),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+    #[error("thread pool error: {0}")]
+    ThreadPoolError(#[from] rayon::ThreadPoolBuildError),
+    #[error("a worker of the pipeline panicked before the image was finished.")]
+    WorkerPanicked,
+    #[cfg(not(feature = "download-model"))]
+    #[error("model folder is required when the download-model feature is disabled.")]
+    MissingModelFolder,
     #[cfg(feature = "download-model")]
     #[error("ureq error: {0}")]
     UreqError(#[from] ureq::Error),
@@ -27,4 +34,107 @@ pub enum Error {
     #[cfg(feature = "download-model")]
     #[error("serde_json error: {0}")]
     SerdeJsonError(#[from] serde_json::Error),
+    #[cfg(feature = "download-model")]
+    #[error("model version {0} is not in the model manifest.")]
+    UnknownModelVersion(String),
+    #[cfg(feature = "download-model")]
+    #[error("model version {0} has not been downloaded.")]
+    ModelNotCached(String),
+    #[cfg(feature = "download-model")]
+    #[error("model version {version} is missing {missing:?}.")]
+    IncompleteModel {
+        version: String,
+        missing: Vec<std::path::PathBuf>,
+    },
+    #[cfg(feature = "download-model")]
+    #[error("unsupported model archive {0:?}, expected a .zip, .tar.gz or .tgz file.")]
+    UnsupportedArchive(std::path::PathBuf),
+    #[cfg(feature = "download-model")]
+    #[error("no info.json found at the root of the model archive {0:?} or in its only folder.")]
+    MissingInfoJson(std::path::PathBuf),
+    #[cfg(feature = "download-model")]
+    #[error("model version {0:?} cannot be used as the name of a folder.")]
+    InvalidModelVersion(String),
+    #[cfg(feature = "download-model")]
+    #[error("checksum mismatch of {path:?}, expected a SHA-256 of {expected} but found {found}.")]
+    ChecksumMismatch {
+        path: std::path::PathBuf,
+        expected: String,
+        found: String,
+    },
+}
+
+impl Error {
+    /// Returns a short name of the kind of the error, this is used for grouping the per-image
+    /// failures together when summarizing a run.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ImageError(_) => "image",
+            Self::DetectorError(e) => match e {
+                speciesnet_detector::error::Error::OrtError(_) => "ort",
+                speciesnet_detector::error::Error::IoError(_) => "io",
+                speciesnet_detector::error::Error::ImageDecodeError(_) => "image",
+                speciesnet_detector::error::Error::ShapeError(_) => "shape",
+                speciesnet_detector::error::Error::InvalidConfig(_) => "config",
+                speciesnet_detector::error::Error::InvalidModel(_)
+                | speciesnet_detector::error::Error::MissingTensor { .. }
+                | speciesnet_detector::error::Error::UnexpectedTensor { .. } => "model",
+                speciesnet_detector::error::Error::SpeciesNetCoreError(e) => core_error_kind(e),
+            },
+            Self::ClassifierError(e) => match e {
+                speciesnet_classifier::error::Error::ORTError(_) => "ort",
+                speciesnet_classifier::error::Error::MissingTensor { .. }
+                | speciesnet_classifier::error::Error::UnexpectedTensor { .. }
+                | speciesnet_classifier::error::Error::AmbiguousOutput { .. }
+                | speciesnet_classifier::error::Error::LabelCountMismatch { .. } => "model",
+                speciesnet_classifier::error::Error::IoError(_) => "io",
+                speciesnet_classifier::error::Error::CsvError(_) => "csv",
+                speciesnet_classifier::error::Error::ImageError(_)
+                | speciesnet_classifier::error::Error::ResizeError(_)
+                | speciesnet_classifier::error::Error::ImageDecodeError(_) => "image",
+                speciesnet_classifier::error::Error::DeserializeError(_) => "json",
+                speciesnet_classifier::error::Error::NDArray(_) => "shape",
+                speciesnet_classifier::error::Error::SpeciesNetCoreError(e) => core_error_kind(e),
+            },
+            Self::EnsembleError(e) => match e {
+                speciesnet_ensemble::error::Error::IoError(_) => "io",
+                speciesnet_ensemble::error::Error::CsvError(_) => "csv",
+                speciesnet_ensemble::error::Error::DeserializeError(_) => "json",
+                _ => "ensemble",
+            },
+            Self::CoreError(e) => core_error_kind(e),
+            Self::IoError(_) => "io",
+            Self::ThreadPoolError(_) => "thread_pool",
+            Self::WorkerPanicked => "panic",
+            #[cfg(not(feature = "download-model"))]
+            Self::MissingModelFolder => "model",
+            #[cfg(feature = "download-model")]
+            Self::UreqError(_) | Self::RequestFailed(_) => "download",
+            #[cfg(feature = "download-model")]
+            Self::BaseDirInitFailed => "io",
+            #[cfg(feature = "download-model")]
+            Self::ZipError(_) => "zip",
+            #[cfg(feature = "download-model")]
+            Self::SerdeJsonError(_) => "json",
+            #[cfg(feature = "download-model")]
+            Self::UnknownModelVersion(_)
+            | Self::ModelNotCached(_)
+            | Self::IncompleteModel { .. }
+            | Self::UnsupportedArchive(_)
+            | Self::MissingInfoJson(_)
+            | Self::InvalidModelVersion(_)
+            | Self::ChecksumMismatch { .. } => "model",
+        }
+    }
+}
+
+/// Returns a short name of the kind of the error of the speciesnet core crate.
+fn core_error_kind(error: &speciesnet_core::error::Error) -> &'static str {
+    match error {
+        speciesnet_core::error::Error::IoError(_) => "io",
+        speciesnet_core::error::Error::ImageError(_)
+        | speciesnet_core::error::Error::MozjpegPanicError(_) => "image",
+        speciesnet_core::error::Error::SerdeJsonError(_) => "json",
+        _ => "core",
+    }
 }
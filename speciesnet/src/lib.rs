@@ -18,6 +18,8 @@
 //!
 //! - `download-model`, enabled by default, this allows you to run [`SpeciesNet::new`] to
 //!   initialize the ensemble, which will download the default model from the internet.
+//!   The downloaded models are cached and can be listed, verified, selected and removed with
+//!   [`model_info::registry::ModelRegistry`].
 //!
 //! ## Model setup
 //!
@@ -31,15 +33,35 @@
 //!
 //! Initializing speciesnet with a custom extracted model folder.
 //!
-//! ```rust
+//! ```rust,no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! use speciesnet::SpeciesNet;
 //!
 //! let speciesnet = SpeciesNet::from_model_folder("./speciesnet-v4a/")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Initializing speciesnet with custom runtime options, see [`SpeciesNetBuilder`] for all of the
+//! options.
+//!
+//! ```rust,no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use speciesnet::SpeciesNet;
+//!
+//! let speciesnet = SpeciesNet::builder()
+//!     .model_folder("./speciesnet-v4a/")
+//!     .intra_threads(4)
+//!     .thread_pool_size(8)
+//!     .build()?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! Running the entire pipeline (detector + classifier + ensemble).
 //!
-//! ```rust
+//! ```rust,no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! use std::path::PathBuf;
 //!
 //! use speciesnet_core::io::Instance;
@@ -52,11 +74,14 @@
 //!
 //! let speciesnet = SpeciesNet::new()?;
 //! let detections = speciesnet.predict(&instances)?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! Running the detector pipeline.
 //!
-//! ```rust
+//! ```rust,no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! use std::path::PathBuf;
 //!
 //! use speciesnet_core::io::Instance;
@@ -69,14 +94,18 @@
 //!
 //! let speciesnet = SpeciesNet::new()?;
 //! let detections = speciesnet.detect(&instances)?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! The returned detections is in the format of [Prediction] vector, which is the same for all
 //! apis.
 //!
-//! Running the classifier pipeline.
-//!  
-//! ```rust
+//! Running the entire pipeline as a stream, each prediction is received as soon as it finishes,
+//! with at most 64 finished predictions waiting in the queue.
+//!
+//! ```rust,no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! use std::path::PathBuf;
 //!
 //! use speciesnet_core::io::Instance;
@@ -88,7 +117,28 @@
 //! ];
 //!
 //! let speciesnet = SpeciesNet::new()?;
-//! let classifications = speciesnet.classify(&instances)?;
+//!
+//! for prediction in speciesnet.predict_stream(instances, 64)? {
+//!     println!("{}", prediction.file_path().display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Running the classifier pipeline on the images and boxes of a detector output file.
+//!
+//! ```rust,no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::path::PathBuf;
+//!
+//! use speciesnet::SpeciesNet;
+//!
+//! let detector_file_path = PathBuf::from("./output_detector.json");
+//!
+//! let speciesnet = SpeciesNet::new()?;
+//! let classifications = speciesnet.classify(&detector_file_path)?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! Running the ensemble and geofence of the pipeline.
@@ -96,7 +146,8 @@
 //! NOTE: This function differs from other functions where it operates on each instance of
 //! prediction, instead of taking the vector of predictions or instance like other API.
 //!
-//! ```rust
+//! ```rust,no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! use std::path::PathBuf;
 //!
 //! use speciesnet_core::{
@@ -105,16 +156,18 @@
 //! };
 //! use speciesnet::SpeciesNet;
 //!
-//! let instances_json_path = "./instances.json";
-//! let detector_file_path = "./output_detector.json";
-//! let classifier_file_path = "./output_classifier.json";
+//! let instances_json_path = PathBuf::from("./instances.json");
+//! let detector_file_path = PathBuf::from("./output_detector.json");
+//! let classifier_file_path = PathBuf::from("./output_classifier.json");
 //!
 //! let speciesnet = SpeciesNet::new()?;
 //! let ensembles = speciesnet.ensemble(
-//!     instances_json_path,
-//!     detector_file_path,
-//!     classifier_file_path,
+//!     &instances_json_path,
+//!     &detector_file_path,
+//!     &classifier_file_path,
 //! )?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! [SpeciesNet]: https://www.kaggle.com/models/google/speciesnet
@@ -123,8 +176,33 @@
 //! [zubalis/speciesnet-onnx]: https://github.com/zubalis/speciesnet-onnx
 //! [ort]: https://docs.rs/ort
 
+pub mod builder;
+pub mod detection_classification;
 pub mod error;
 pub mod model_info;
+pub mod pipeline;
 pub mod speciesnet;
+pub mod summary;
+#[cfg(test)]
+mod testing;
 
+pub use builder::SpeciesNetBuilder;
+pub use detection_classification::DetectionClassificationOptions;
+pub use pipeline::PipelineOptions;
 pub use speciesnet::SpeciesNet;
+pub use speciesnet_classifier::model::{Classifier, ClassifierInputSpec, TensorLayout};
+pub use speciesnet_detector::{
+    config::DetectorConfig,
+    model::{Detector, DetectorKind},
+    suppression::{SoftNmsDecay, Suppression},
+    tiling::{TileMerge, TilingOptions},
+};
+pub use speciesnet_ensemble::{
+    config::EnsembleConfig,
+    geofence::{
+        Geofence,
+        map::{AllowedCountry, GeofenceMap},
+    },
+    taxonomy::{MatchKind, TaxonMatch, Taxonomy},
+};
+pub use summary::RunSummary;
@@ -3,23 +3,23 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use speciesnet_classifier::model::ClassifierInputSpec;
 
 use crate::error::Error;
 
+pub use speciesnet_core::classifier::ModelType;
+
 #[cfg(feature = "download-model")]
 pub mod download_model;
+#[cfg(feature = "download-model")]
+pub mod registry;
 
-/// Possible types of the speciesnet model.
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
-pub enum ModelType {
-    AlwaysCrop,
-    FullImage,
-}
+#[cfg(test)]
+mod tests;
 
 /// Struct containing the model's information and where the files are.
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, PartialEq)]
 pub struct ModelInfo {
     /// Version of the loaded model.
     version: String,
@@ -36,6 +36,9 @@ pub struct ModelInfo {
     taxonomy: PathBuf,
     /// Path of the geofence file.
     geofence: PathBuf,
+    /// What the classifier expects its images in, read from the classifier model when missing.
+    #[serde(default)]
+    classifier_input: Option<ClassifierInputSpec>,
 }
 
 impl ModelInfo {
@@ -62,6 +65,7 @@ impl ModelInfo {
             detector: detector_path,
             taxonomy: taxonomy_path,
             geofence: geofence_path,
+            classifier_input: info_json.classifier_input,
         })
     }
 
@@ -92,4 +96,19 @@ impl ModelInfo {
     pub fn geofence(&self) -> &Path {
         &self.geofence
     }
+
+    /// Returns the paths of the files of the model.
+    pub fn files(&self) -> [&Path; 5] {
+        [
+            &self.classifier,
+            &self.classifier_labels,
+            &self.detector,
+            &self.taxonomy,
+            &self.geofence,
+        ]
+    }
+
+    pub fn classifier_input(&self) -> Option<&ClassifierInputSpec> {
+        self.classifier_input.as_ref()
+    }
 }
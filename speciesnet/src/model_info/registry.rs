@@ -0,0 +1,663 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use directories::BaseDirs;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use zip::ZipArchive;
+
+use crate::error::Error;
+
+use super::ModelInfo;
+
+#[cfg(test)]
+mod tests;
+
+/// The environment variable overriding the directory the models are cached in.
+pub const CACHE_DIR_ENV: &str = "SPECIESNET_CACHE_DIR";
+/// The environment variable overriding the url the models are downloaded from, e.g. to download
+/// them from a mirror.
+pub const MODEL_URL_ENV: &str = "SPECIESNET_MODEL_URL";
+
+/// The directory for storing the downloaded models, inside the cache directory of the user.
+const MODEL_DIRECTORY: &str = "speciesnet-rust/models/";
+/// The prefix of the folder of each model inside the cache directory.
+const MODEL_FOLDER_PREFIX: &str = "speciesnet-onnx-v";
+/// The file recording the selected model version inside the cache directory.
+const SELECTED_FILE: &str = "selected";
+/// The version of the default model.
+const DEFAULT_MODEL_VERSION: &str = "4.0.0a";
+/// The url of the default model.
+const DEFAULT_MODEL_URL: &str =
+    "https://drive.usercontent.google.com/download?id=1dAGnnJvOiNku6i2Zv82p0Rzidtsk02fy&confirm";
+
+/// A model version which can be downloaded.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModelEntry {
+    /// Version of the model, this also names the folder the model is extracted into.
+    pub version: String,
+    /// Url of the zip archive of the model.
+    pub url: String,
+    /// Hex encoded SHA-256 of the zip archive, the archive is not verified when this is missing.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// The list of the model versions which can be downloaded.
+///
+/// ```json
+/// {
+///     "default": "4.0.0a",
+///     "models": [
+///         { "version": "4.0.0a", "url": "https://example.com/model.zip", "sha256": "..." }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Manifest {
+    /// Version used when no version has been selected.
+    pub default: String,
+    pub models: Vec<ModelEntry>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            default: DEFAULT_MODEL_VERSION.to_string(),
+            models: vec![ModelEntry {
+                version: DEFAULT_MODEL_VERSION.to_string(),
+                url: DEFAULT_MODEL_URL.to_string(),
+                sha256: None,
+            }],
+        }
+    }
+}
+
+impl Manifest {
+    /// Reads the manifest from a json file.
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Returns the entry of the given version.
+    pub fn get(&self, version: &str) -> Option<&ModelEntry> {
+        self.models.iter().find(|entry| entry.version == version)
+    }
+}
+
+/// A model version known to the registry, either from the manifest or from the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedModel {
+    pub version: String,
+    /// Folder the model is, or would be, extracted into.
+    pub folder: PathBuf,
+    /// Whether the model has been downloaded.
+    pub cached: bool,
+    /// Whether this is the version used by [`ModelRegistry::load`].
+    pub selected: bool,
+    /// Whether the version is in the manifest, models imported by hand are not.
+    pub in_manifest: bool,
+}
+
+/// The models cached on this machine, along with the manifest of the models which can be
+/// downloaded.
+///
+/// Each model is extracted into its own `speciesnet-onnx-v<version>` folder of the cache
+/// directory, next to the zip archive it came from. The archive is downloaded into a temporary
+/// file and the model is extracted into a temporary folder, they are only moved into place once
+/// they are complete, so an interrupted download is never mistaken for a cached model.
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+    cache_dir: PathBuf,
+    manifest: Manifest,
+    url_override: Option<String>,
+}
+
+impl ModelRegistry {
+    /// Creates the registry with the default manifest in the `speciesnet-rust/models/` folder of
+    /// the cache directory of the user. The directory can be overridden with the
+    /// `SPECIESNET_CACHE_DIR` environment variable, and the download url with the
+    /// `SPECIESNET_MODEL_URL` environment variable.
+    pub fn new() -> Result<Self, Error> {
+        let cache_dir = match env::var_os(CACHE_DIR_ENV) {
+            Some(cache_dir) => PathBuf::from(cache_dir),
+            None => BaseDirs::new()
+                .ok_or(Error::BaseDirInitFailed)?
+                .cache_dir()
+                .join(MODEL_DIRECTORY),
+        };
+
+        let mut registry = Self::with_cache_dir(cache_dir, Manifest::default());
+        registry.set_url_override(env::var(MODEL_URL_ENV).ok().filter(|url| !url.is_empty()));
+
+        Ok(registry)
+    }
+
+    /// Creates the registry in the given cache directory with the given manifest.
+    pub fn with_cache_dir<P>(cache_dir: P, manifest: Manifest) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            cache_dir: cache_dir.as_ref().to_path_buf(),
+            manifest,
+            url_override: None,
+        }
+    }
+
+    pub fn set_manifest(&mut self, manifest: Manifest) -> &mut Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Downloads the models from the given url instead of the url of the manifest, the checksum
+    /// of the manifest is still checked.
+    pub fn set_url_override(&mut self, url_override: Option<String>) -> &mut Self {
+        self.url_override = url_override;
+        self
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Returns the folder the given version is extracted into, the version must be usable as the
+    /// name of a folder.
+    pub fn model_folder(&self, version: &str) -> Result<PathBuf, Error> {
+        validate_version(version)?;
+
+        Ok(self
+            .cache_dir
+            .join(format!("{MODEL_FOLDER_PREFIX}{version}")))
+    }
+
+    /// Returns the zip archive the given version is downloaded into.
+    fn archive(&self, version: &str) -> Result<PathBuf, Error> {
+        validate_version(version)?;
+
+        Ok(self
+            .cache_dir
+            .join(format!("{MODEL_FOLDER_PREFIX}{version}.zip")))
+    }
+
+    /// Lists the versions of the manifest, followed by the other versions found in the cache.
+    pub fn list(&self) -> Result<Vec<CachedModel>, Error> {
+        let selected = self.selected()?;
+        let mut versions: Vec<String> = self
+            .manifest
+            .models
+            .iter()
+            .map(|entry| entry.version.clone())
+            .collect();
+
+        for version in self.cached_versions()? {
+            if !versions.contains(&version) {
+                versions.push(version);
+            }
+        }
+
+        versions
+            .into_iter()
+            .map(|version| {
+                let folder = self.model_folder(&version)?;
+
+                Ok(CachedModel {
+                    cached: folder.is_dir(),
+                    selected: version == selected,
+                    in_manifest: self.manifest.get(&version).is_some(),
+                    version,
+                    folder,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the versions which have a model folder in the cache directory.
+    fn cached_versions(&self) -> Result<Vec<String>, Error> {
+        if !self.cache_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut versions = Vec::new();
+
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            if let Some(version) = name.strip_prefix(MODEL_FOLDER_PREFIX)
+                && validate_version(version).is_ok()
+                && entry.file_type()?.is_dir()
+            {
+                versions.push(version.to_string());
+            }
+        }
+
+        versions.sort();
+        Ok(versions)
+    }
+
+    /// Returns the version used by [`ModelRegistry::load`], the default version of the manifest
+    /// unless another one has been selected.
+    pub fn selected(&self) -> Result<String, Error> {
+        match fs::read_to_string(self.cache_dir.join(SELECTED_FILE)) {
+            Ok(version) if !version.trim().is_empty() => Ok(version.trim().to_string()),
+            Ok(_) => Ok(self.manifest.default.clone()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(self.manifest.default.clone()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Selects the version used by [`ModelRegistry::load`], the version must either be in the
+    /// manifest or be cached.
+    pub fn select(&self, version: &str) -> Result<(), Error> {
+        if self.manifest.get(version).is_none() && !self.model_folder(version)?.is_dir() {
+            return Err(Error::UnknownModelVersion(version.to_string()));
+        }
+
+        fs::create_dir_all(&self.cache_dir)?;
+        write_atomically(&self.cache_dir.join(SELECTED_FILE), |writer| {
+            writer.write_all(version.as_bytes())
+        })?;
+
+        info!("Selected the model version {}.", version);
+        Ok(())
+    }
+
+    /// Removes the folder and the archive of the given version from the cache, returns whether
+    /// anything was removed.
+    pub fn remove(&self, version: &str) -> Result<bool, Error> {
+        let folder = self.model_folder(version)?;
+        let archive = self.archive(version)?;
+        let mut removed = false;
+
+        if folder.is_dir() {
+            fs::remove_dir_all(&folder)?;
+            removed = true;
+        }
+
+        if archive.is_file() {
+            fs::remove_file(&archive)?;
+            removed = true;
+        }
+
+        if removed {
+            info!("Removed the model version {} from the cache.", version);
+        }
+
+        Ok(removed)
+    }
+
+    /// Checks that the given version is cached with all of its files, and that its archive still
+    /// matches the checksum of the manifest.
+    pub fn verify(&self, version: &str) -> Result<ModelInfo, Error> {
+        let folder = self.model_folder(version)?;
+
+        if !folder.is_dir() {
+            return Err(Error::ModelNotCached(version.to_string()));
+        }
+
+        let info_json = folder.join("info.json");
+
+        if !info_json.is_file() {
+            return Err(Error::IncompleteModel {
+                version: version.to_string(),
+                missing: vec![info_json],
+            });
+        }
+
+        let model_info = ModelInfo::from_path(&folder)?;
+        check_files(version, &model_info)?;
+
+        let archive = self.archive(version)?;
+        let expected = self
+            .manifest
+            .get(version)
+            .and_then(|entry| entry.sha256.as_deref());
+
+        match expected {
+            Some(expected) if archive.is_file() => {
+                check_sha256(&archive, expected, &sha256_file(&archive)?)?
+            }
+            Some(_) => warn!(
+                "The archive of the model version {} is gone, only its files were checked.",
+                version
+            ),
+            None => warn!(
+                "The model version {} has no checksum in the manifest, only its files were checked.",
+                version
+            ),
+        }
+
+        Ok(model_info)
+    }
+
+    /// Loads the selected version, downloading it first when it is not cached.
+    pub fn load(&self) -> Result<ModelInfo, Error> {
+        self.fetch(&self.selected()?)
+    }
+
+    /// Loads the given version, downloading it first when it is not cached.
+    pub fn fetch(&self, version: &str) -> Result<ModelInfo, Error> {
+        let folder = self.model_folder(version)?;
+
+        info!(
+            "Checking if the model has been downloaded at {}.",
+            folder.display()
+        );
+
+        if folder.is_dir() {
+            return ModelInfo::from_path(folder);
+        }
+
+        self.download(version)
+    }
+
+    /// Downloads the given version into the cache, replacing the cached one if any.
+    pub fn download(&self, version: &str) -> Result<ModelInfo, Error> {
+        let entry = self
+            .manifest
+            .get(version)
+            .ok_or_else(|| Error::UnknownModelVersion(version.to_string()))?;
+        let url = self.url_override.as_deref().unwrap_or(&entry.url);
+        let archive = self.archive(version)?;
+
+        fs::create_dir_all(&self.cache_dir)?;
+
+        info!("Downloading the model version {} from {}", version, url);
+
+        let response = ureq::get(url).call()?;
+
+        if response.status() != 200 {
+            return Err(Error::RequestFailed(response.status().as_u16()));
+        }
+
+        let (_, body) = response.into_parts();
+        let mut body_reader = body.into_reader();
+        let mut hasher = Sha256::new();
+
+        write_atomically(&archive, |writer| {
+            let mut buffer = vec![0; 64 * 1024];
+
+            loop {
+                let read = body_reader.read(&mut buffer)?;
+
+                if read == 0 {
+                    return Ok(());
+                }
+
+                hasher.update(&buffer[..read]);
+                writer.write_all(&buffer[..read])?;
+            }
+        })?;
+
+        let found = to_hex(&hasher.finalize());
+
+        match &entry.sha256 {
+            Some(expected) => {
+                if let Err(e) = check_sha256(&archive, expected, &found) {
+                    fs::remove_file(&archive)?;
+                    return Err(e);
+                }
+            }
+            None => warn!(
+                "The model version {} has no checksum in the manifest, its archive has a SHA-256 of {}.",
+                version, found
+            ),
+        }
+
+        // An incomplete archive is removed along with its folder, so it is downloaded again.
+        let result = self.extract(version, &archive);
+
+        if result.is_err() {
+            fs::remove_file(&archive)?;
+        }
+
+        result
+    }
+
+    /// Imports the model of a local `.zip`, `.tar.gz` or `.tgz` archive into the cache, for the
+    /// machines which cannot download it. The `info.json` of the model is either at the root of
+    /// the archive or inside its only folder, and the model goes into the folder of the version of
+    /// its `info.json`, replacing the cached one if any. The model is only moved into the cache
+    /// once all of its files are found.
+    pub fn import<P>(&self, archive: P) -> Result<ModelInfo, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let archive = archive.as_ref();
+        let extract_dir = temporary_path(&self.cache_dir.join("import"));
+
+        fs::create_dir_all(&self.cache_dir)?;
+
+        let result = self.import_from(archive, &extract_dir);
+
+        if extract_dir.exists() {
+            fs::remove_dir_all(&extract_dir)?;
+        }
+
+        result
+    }
+
+    fn import_from(&self, archive: &Path, extract_dir: &Path) -> Result<ModelInfo, Error> {
+        let name = archive
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        info!(
+            "Unpacking the contents inside {} into {}",
+            archive.display(),
+            extract_dir.display(),
+        );
+
+        if name.ends_with(".zip") {
+            ZipArchive::new(File::open(archive)?)?.extract(extract_dir)?;
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            let decoder = GzDecoder::new(BufReader::new(File::open(archive)?));
+            tar::Archive::new(decoder).unpack(extract_dir)?;
+        } else {
+            return Err(Error::UnsupportedArchive(archive.to_path_buf()));
+        }
+
+        let root =
+            model_root(extract_dir).ok_or_else(|| Error::MissingInfoJson(archive.to_path_buf()))?;
+        let model_info = ModelInfo::from_path(&root)?;
+        let version = model_info.version().to_string();
+
+        validate_version(&version)?;
+        check_files(&version, &model_info)?;
+
+        // The archive of an earlier download is not the one of the imported model anymore.
+        let downloaded_archive = self.archive(&version)?;
+        if downloaded_archive.is_file() {
+            fs::remove_file(&downloaded_archive)?;
+        }
+
+        let folder = self.model_folder(&version)?;
+        replace_dir(&root, &folder)?;
+
+        info!(
+            "Imported the model version {} into {}.",
+            version,
+            folder.display()
+        );
+
+        ModelInfo::from_path(folder)
+    }
+
+    /// Extracts the archive into a temporary folder, then moves it into the folder of the version
+    /// once all of the files of the model are found. Like [`ModelRegistry::import`], the
+    /// `info.json` is either at the root of the archive or inside its only folder.
+    pub(crate) fn extract(&self, version: &str, archive: &Path) -> Result<ModelInfo, Error> {
+        let folder = self.model_folder(version)?;
+        let extract_dir = temporary_path(&folder);
+
+        info!(
+            "Unzipping the contents inside {} into {}",
+            archive.display(),
+            folder.display(),
+        );
+
+        let result = extract_into(version, archive, &extract_dir, &folder);
+
+        if extract_dir.exists() {
+            fs::remove_dir_all(&extract_dir)?;
+        }
+
+        result
+    }
+}
+
+/// Unzips the archive into the temporary folder and moves the root of the model into the folder.
+fn extract_into(
+    version: &str,
+    archive: &Path,
+    extract_dir: &Path,
+    folder: &Path,
+) -> Result<ModelInfo, Error> {
+    ZipArchive::new(File::open(archive)?)?.extract(extract_dir)?;
+
+    let root =
+        model_root(extract_dir).ok_or_else(|| Error::MissingInfoJson(archive.to_path_buf()))?;
+    check_files(version, &ModelInfo::from_path(&root)?)?;
+
+    replace_dir(&root, folder)?;
+
+    ModelInfo::from_path(folder)
+}
+
+/// Returns an error when the version cannot be used as the name of a folder of the cache, so a
+/// version like `../..` never points outside of it.
+fn validate_version(version: &str) -> Result<(), Error> {
+    if version.is_empty() || version.contains(['/', '\\']) || version.starts_with('.') {
+        return Err(Error::InvalidModelVersion(version.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Returns an error listing the files of the model which cannot be found.
+fn check_files(version: &str, model_info: &ModelInfo) -> Result<(), Error> {
+    let missing: Vec<PathBuf> = model_info
+        .files()
+        .into_iter()
+        .filter(|file| !file.is_file())
+        .map(Path::to_path_buf)
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::IncompleteModel {
+            version: version.to_string(),
+            missing,
+        })
+    }
+}
+
+/// Returns the folder of the unpacked archive with the `info.json` file, either the folder itself
+/// or its only sub folder.
+fn model_root(folder: &Path) -> Option<PathBuf> {
+    if folder.join("info.json").is_file() {
+        return Some(folder.to_path_buf());
+    }
+
+    let folders: Vec<PathBuf> = fs::read_dir(folder)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+
+    match folders.as_slice() {
+        [root] if root.join("info.json").is_file() => Some(root.clone()),
+        _ => None,
+    }
+}
+
+/// Returns an error when the SHA-256 found does not match the expected one.
+fn check_sha256(path: &Path, expected: &str, found: &str) -> Result<(), Error> {
+    if expected.eq_ignore_ascii_case(found) {
+        Ok(())
+    } else {
+        Err(Error::ChecksumMismatch {
+            path: path.to_path_buf(),
+            expected: expected.to_lowercase(),
+            found: found.to_string(),
+        })
+    }
+}
+
+/// Computes the hex encoded SHA-256 of the file.
+pub(crate) fn sha256_file(path: &Path) -> Result<String, Error> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Returns a hidden path next to the given one which is unique to this process.
+fn temporary_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    path.with_file_name(format!(".{name}.{}.tmp", process::id()))
+}
+
+/// Writes the file into a temporary file which is renamed over the file once it has been written,
+/// the temporary file is removed on failure.
+fn write_atomically<F>(path: &Path, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let temporary = temporary_path(path);
+
+    let result = File::create(&temporary).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.into_inner()?.sync_all()
+    });
+
+    match result.and_then(|_| fs::rename(&temporary, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            fs::remove_file(&temporary).ok();
+            Err(e.into())
+        }
+    }
+}
+
+/// Moves the folder over the destination, removing the destination first if it exists.
+pub(crate) fn replace_dir(folder: &Path, destination: &Path) -> Result<(), Error> {
+    if destination.exists() {
+        fs::remove_dir_all(destination)?;
+    }
+
+    if let Err(e) = fs::rename(folder, destination) {
+        fs::remove_dir_all(folder).ok();
+        return Err(e.into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,330 @@
+use std::{
+    env::temp_dir,
+    fs,
+    io::{Cursor, Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    thread,
+};
+
+use flate2::{Compression, write::GzEncoder};
+use sha2::{Digest, Sha256};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    error::Error,
+    model_info::registry::{Manifest, ModelEntry, ModelRegistry, to_hex},
+};
+
+/// The files of a model bundle, with an `info.json` pointing at the other ones.
+const MODEL_FILES: [(&str, &str); 6] = [
+    (
+        "info.json",
+        r#"{
+            "version": "4.0.0a",
+            "type": "always_crop",
+            "classifier": "model.onnx",
+            "classifier_labels": "labels.txt",
+            "detector": "md_v5a.0.0.onnx",
+            "taxonomy": "taxonomy_release.txt",
+            "geofence": "geofence_release.json"
+        }"#,
+    ),
+    ("model.onnx", "classifier"),
+    ("labels.txt", "label"),
+    ("md_v5a.0.0.onnx", "detector"),
+    ("taxonomy_release.txt", "taxonomy"),
+    ("geofence_release.json", "{}"),
+];
+
+/// Zips the files of a model bundle.
+fn model_zip() -> Vec<u8> {
+    model_zip_in("", "")
+}
+
+/// Zips the files of a model bundle with the given prefix in front of their names, leaving out the
+/// given file.
+fn model_zip_in(prefix: &str, left_out: &str) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    for (name, contents) in MODEL_FILES
+        .into_iter()
+        .filter(|(name, _)| *name != left_out)
+    {
+        writer
+            .start_file(format!("{prefix}{name}"), SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(contents.as_bytes()).unwrap();
+    }
+
+    writer.finish().unwrap().into_inner()
+}
+
+/// Packs the files of a model bundle into a tarball, leaving out the given file.
+fn model_tar_gz(left_out: &str) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    for (name, contents) in MODEL_FILES
+        .into_iter()
+        .filter(|(name, _)| *name != left_out)
+    {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, contents.as_bytes())
+            .unwrap();
+    }
+
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Serves the body on a local HTTP server for the given number of requests, returns its url.
+fn serve(body: Vec<u8>, requests: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/model.zip", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+        }
+    });
+
+    url
+}
+
+/// Creates an empty cache directory for the test.
+fn cache_dir(name: &str) -> PathBuf {
+    let cache_dir = temp_dir().join(format!("speciesnet-registry-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&cache_dir);
+    cache_dir
+}
+
+fn manifest(url: String, sha256: Option<String>) -> Manifest {
+    Manifest {
+        default: "4.0.0a".to_string(),
+        models: vec![ModelEntry {
+            version: "4.0.0a".to_string(),
+            url,
+            sha256,
+        }],
+    }
+}
+
+#[test]
+fn test_download_verify_select_and_remove() -> Result<(), Error> {
+    let zip = model_zip();
+    let sha256 = to_hex(&Sha256::digest(&zip));
+    let cache_dir = cache_dir("download");
+    let mut registry =
+        ModelRegistry::with_cache_dir(&cache_dir, manifest(serve(zip, 1), Some(sha256)));
+
+    assert!(matches!(
+        registry.verify("4.0.0a"),
+        Err(Error::ModelNotCached(_))
+    ));
+
+    let model_info = registry.load()?;
+    assert_eq!(model_info.version(), "4.0.0a");
+    assert!(model_info.files().iter().all(|file| file.is_file()));
+
+    // The second load is served from the cache, the server only answers once.
+    registry.load()?;
+    registry.verify("4.0.0a")?;
+
+    // A newer version which is not in the manifest cannot be selected until it is cached.
+    assert!(matches!(
+        registry.select("5.0.0"),
+        Err(Error::UnknownModelVersion(_))
+    ));
+    fs::create_dir_all(registry.model_folder("5.0.0")?)?;
+    registry.select("5.0.0")?;
+    assert!(matches!(
+        registry.verify("5.0.0"),
+        Err(Error::IncompleteModel { version, .. }) if version == "5.0.0"
+    ));
+    assert_eq!(registry.selected()?, "5.0.0");
+
+    let models = registry.list()?;
+    assert_eq!(models.len(), 2);
+    assert!(models[0].cached && models[0].in_manifest && !models[0].selected);
+    assert!(models[1].selected && !models[1].in_manifest);
+
+    // Removing a file of the model is found by the verification.
+    fs::remove_file(model_info.classifier())?;
+    assert!(matches!(
+        registry.verify("4.0.0a"),
+        Err(Error::IncompleteModel { missing, .. }) if missing == [model_info.classifier()]
+    ));
+
+    assert!(registry.remove("4.0.0a")?);
+    assert!(!registry.remove("4.0.0a")?);
+    assert!(!registry.list()?[0].cached);
+
+    registry.set_manifest(Manifest::default());
+    assert_eq!(registry.manifest().default, "4.0.0a");
+
+    fs::remove_dir_all(&cache_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_checksum_mismatch_leaves_nothing_behind() -> Result<(), Error> {
+    let cache_dir = cache_dir("checksum");
+    let registry = ModelRegistry::with_cache_dir(
+        &cache_dir,
+        manifest(serve(model_zip(), 1), Some("00".repeat(32))),
+    );
+
+    assert!(matches!(
+        registry.load(),
+        Err(Error::ChecksumMismatch { expected, .. }) if expected == "00".repeat(32)
+    ));
+    assert_eq!(fs::read_dir(&cache_dir)?.count(), 0);
+
+    fs::remove_dir_all(&cache_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_download_rejects_incomplete_archives() -> Result<(), Error> {
+    let cache_dir = cache_dir("download-incomplete");
+    let zip = model_zip_in("speciesnet-v4a/", "labels.txt");
+    let registry = ModelRegistry::with_cache_dir(&cache_dir, manifest(serve(zip, 1), None));
+
+    assert!(matches!(
+        registry.load(),
+        Err(Error::IncompleteModel { missing, .. })
+            if missing.len() == 1 && missing[0].ends_with("labels.txt")
+    ));
+
+    // Neither the archive nor the folder are kept, so the next load downloads it again.
+    assert_eq!(fs::read_dir(&cache_dir)?.count(), 0);
+
+    // The files of the zip are inside a folder, which is the root of the model.
+    let registry = ModelRegistry::with_cache_dir(
+        &cache_dir,
+        manifest(serve(model_zip_in("speciesnet-v4a/", ""), 1), None),
+    );
+    assert_eq!(
+        registry.load()?.classifier(),
+        registry.model_folder("4.0.0a")?.join("model.onnx")
+    );
+
+    fs::remove_dir_all(&cache_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_import_archives() -> Result<(), Error> {
+    let cache_dir = cache_dir("import");
+    let sources = cache_dir.with_extension("sources");
+    fs::create_dir_all(&sources)?;
+    let registry = ModelRegistry::with_cache_dir(&cache_dir, Manifest::default());
+
+    // The files of the zip are inside a folder, like an archive made by zipping the model folder.
+    let zip = sources.join("model.zip");
+    fs::write(&zip, model_zip_in("speciesnet-v4a/", ""))?;
+    let model_info = registry.import(&zip)?;
+
+    assert_eq!(model_info.version(), "4.0.0a");
+    assert_eq!(
+        model_info.classifier(),
+        registry.model_folder("4.0.0a")?.join("model.onnx")
+    );
+    registry.verify("4.0.0a")?;
+    assert!(registry.list()?[0].cached);
+
+    let tar_gz = sources.join("model.tar.gz");
+    fs::write(&tar_gz, model_tar_gz(""))?;
+    registry.import(&tar_gz)?;
+    registry.verify("4.0.0a")?;
+
+    fs::remove_dir_all(&cache_dir)?;
+    fs::remove_dir_all(&sources)?;
+    Ok(())
+}
+
+#[test]
+fn test_import_rejects_incomplete_archives() -> Result<(), Error> {
+    let cache_dir = cache_dir("import-incomplete");
+    let sources = cache_dir.with_extension("sources");
+    fs::create_dir_all(&sources)?;
+    let registry = ModelRegistry::with_cache_dir(&cache_dir, Manifest::default());
+
+    let tgz = sources.join("model.tgz");
+    fs::write(&tgz, model_tar_gz("labels.txt"))?;
+    assert!(matches!(
+        registry.import(&tgz),
+        Err(Error::IncompleteModel { missing, .. })
+            if missing.len() == 1 && missing[0].ends_with("labels.txt")
+    ));
+
+    let no_info_json = sources.join("no-info-json.tar.gz");
+    fs::write(&no_info_json, model_tar_gz("info.json"))?;
+    assert!(matches!(
+        registry.import(&no_info_json),
+        Err(Error::MissingInfoJson(_))
+    ));
+
+    assert!(matches!(
+        registry.import(sources.join("model.rar")),
+        Err(Error::UnsupportedArchive(_))
+    ));
+
+    // Nothing of the failed imports is left in the cache.
+    assert_eq!(fs::read_dir(&cache_dir)?.count(), 0);
+
+    fs::remove_dir_all(&cache_dir)?;
+    fs::remove_dir_all(&sources)?;
+    Ok(())
+}
+
+#[test]
+fn test_versions_cannot_leave_the_cache() -> Result<(), Error> {
+    // The cache is inside of a folder of the test, which `x/../..` would point at.
+    let outside = cache_dir("versions");
+    let cache = outside.join("cache");
+    fs::create_dir_all(cache.join("speciesnet-onnx-vx"))?;
+    fs::write(outside.join("marker"), "")?;
+    let registry = ModelRegistry::with_cache_dir(&cache, Manifest::default());
+
+    for version in ["x/../..", "x\\..\\..", "..", ".hidden", ""] {
+        assert!(
+            matches!(
+                registry.remove(version),
+                Err(Error::InvalidModelVersion(found)) if found == version
+            ),
+            "{version}"
+        );
+        assert!(matches!(
+            registry.select(version),
+            Err(Error::InvalidModelVersion(_))
+        ));
+        assert!(matches!(
+            registry.verify(version),
+            Err(Error::InvalidModelVersion(_))
+        ));
+        assert!(matches!(
+            registry.fetch(version),
+            Err(Error::InvalidModelVersion(_))
+        ));
+    }
+
+    assert!(outside.join("marker").is_file());
+    assert_eq!(registry.list()?[1].version, "x");
+
+    fs::remove_dir_all(&outside)?;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use std::{env::temp_dir, fs, path::PathBuf, sync::Arc};
+
+use speciesnet_classifier::{
+    image::{preprocess_impl, to_tensor},
+    model::{Classifier, ClassifierInputSpec, TensorLayout},
+};
+use speciesnet_core::{detector::BoundingBox, load_image};
+
+use crate::{
+    error::Error,
+    model_info::{ModelInfo, ModelType},
+    testing::{Fixture, StubClassifier, StubDetector},
+};
+
+/// Writes a model bundle with only the `info.json` file of the given type into a temporary folder.
+fn model_bundle(name: &str, model_type: &str) -> Result<PathBuf, Error> {
+    model_bundle_with(name, model_type, "")
+}
+
+/// Same as [`model_bundle`], with the given fields added at the end of the `info.json` file.
+fn model_bundle_with(name: &str, model_type: &str, extra_fields: &str) -> Result<PathBuf, Error> {
+    let folder = temp_dir().join(format!(
+        "speciesnet-model-info-{}-{name}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&folder)?;
+    fs::write(
+        folder.join("info.json"),
+        format!(
+            r#"{{
+                "version": "4.0.0",
+                "type": "{model_type}",
+                "classifier": "model.onnx",
+                "classifier_labels": "labels.txt",
+                "detector": "md_v5a.0.0.onnx",
+                "taxonomy": "taxonomy_release.txt",
+                "geofence": "geofence_release.2025.02.27.0702.json"{extra_fields}
+            }}"#
+        ),
+    )?;
+
+    Ok(folder)
+}
+
+#[test]
+fn test_full_image_model_skips_cropping() -> Result<(), Error> {
+    let folder = model_bundle("full-image", "full_image")?;
+    let model_info = ModelInfo::from_path(&folder)?;
+    fs::remove_dir_all(&folder)?;
+
+    let bboxes = [BoundingBox::new(0.1, 0.2, 0.3, 0.4)];
+
+    assert_eq!(model_info.model_type(), ModelType::FullImage);
+    assert!(!model_info.model_type().crops());
+    assert_eq!(model_info.model_type().crop_box(&bboxes), None);
+    assert_eq!(model_info.model_type().crop_box(&[]), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_full_image_model_classifies_the_whole_image() -> Result<(), Error> {
+    let fixture = Fixture::with_model_type("full-image-run", 1, "full_image")?;
+    let classifier = Arc::new(StubClassifier::default());
+    let speciesnet = fixture
+        .builder_with(Arc::new(StubDetector::default()), Arc::clone(&classifier))
+        .build()?;
+
+    let predictions = speciesnet.predict(fixture.instances())?;
+
+    // The 48 by 32 image has a detection, which a full image model does not crop to.
+    let detections = predictions[0].detections().clone().unwrap_or_default();
+    assert_eq!(detections.len(), 1);
+
+    let image = load_image(fixture.instances()[0].file_path())?;
+    let spec = classifier.input_spec();
+    let full_image = to_tensor(&preprocess_impl(image.clone().into(), None, spec)?, spec);
+    let crop = to_tensor(
+        &preprocess_impl(image.into(), Some(*detections[0].bounding_box()), spec)?,
+        spec,
+    );
+
+    assert_eq!(classifier.images(), vec![full_image]);
+    assert_ne!(classifier.images()[0], crop);
+
+    Ok(())
+}
+
+#[test]
+fn test_always_crop_model_crops_to_top_detection() -> Result<(), Error> {
+    let folder = model_bundle("always-crop", "always_crop")?;
+    let model_info = ModelInfo::from_path(&folder)?;
+    fs::remove_dir_all(&folder)?;
+
+    let bboxes = [
+        BoundingBox::new(0.1, 0.2, 0.3, 0.4),
+        BoundingBox::new(0.5, 0.5, 0.2, 0.2),
+    ];
+
+    assert_eq!(model_info.model_type(), ModelType::AlwaysCrop);
+    assert_eq!(model_info.model_type().crop_box(&bboxes), Some(bboxes[0]));
+    // Without any detection the full image is classified, like the Python implementation.
+    assert_eq!(model_info.model_type().crop_box(&[]), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_classifier_input_defaults_to_the_model() -> Result<(), Error> {
+    let folder = model_bundle("no-classifier-input", "always_crop")?;
+    let model_info = ModelInfo::from_path(&folder)?;
+    fs::remove_dir_all(&folder)?;
+
+    assert_eq!(model_info.classifier_input(), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_classifier_input_is_read_from_info_json() -> Result<(), Error> {
+    let folder = model_bundle_with(
+        "classifier-input",
+        "full_image",
+        r#",
+                "classifier_input": {
+                    "input_name": "pixel_values",
+                    "output_name": "logits",
+                    "width": 224,
+                    "height": 224,
+                    "layout": "nchw",
+                    "mean": [0.485, 0.456, 0.406]
+                }"#,
+    )?;
+    let model_info = ModelInfo::from_path(&folder)?;
+    fs::remove_dir_all(&folder)?;
+
+    let expected = ClassifierInputSpec {
+        input_name: "pixel_values".to_string(),
+        output_name: "logits".to_string(),
+        width: 224,
+        height: 224,
+        layout: TensorLayout::Nchw,
+        mean: [0.485, 0.456, 0.406],
+        ..Default::default()
+    };
+
+    assert_eq!(model_info.classifier_input(), Some(&expected));
+    assert_eq!(expected.shape(), [1, 3, 224, 224]);
+
+    Ok(())
+}
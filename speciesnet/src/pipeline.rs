@@ -0,0 +1,496 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, SyncSender, sync_channel},
+    },
+    thread::{self, JoinHandle},
+};
+
+use image::RgbImage;
+use speciesnet_core::{
+    io::{Failure, Instance, Prediction},
+    load_image,
+};
+use speciesnet_detector::preprocess::{LetterboxOptions, PreprocessedImage};
+use tracing::{debug, error, warn};
+
+use crate::{
+    error::Error,
+    speciesnet::{SpeciesNet, merge_classifier_outputs, record_failure},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Default number of finished items each queue between the stages of the pipeline can hold.
+pub const DEFAULT_QUEUE_SIZE: usize = 16;
+
+/// Options of the staged pipeline used by [`SpeciesNet::predict`] and
+/// [`SpeciesNet::predict_stream`].
+///
+/// The images go through three stages, each one running on its own workers and connected to the
+/// next stage by a bounded queue. The decode workers read and letterbox the images, the detector
+/// workers run the detector, and the classifier workers crop, classify, and ensemble the images.
+/// When a queue is full, the stage in front of it waits, so at most around `queue_size` decoded
+/// images are kept in memory for each stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineOptions {
+    decode_workers: usize,
+    detector_workers: usize,
+    classifier_workers: usize,
+    queue_size: usize,
+}
+
+impl Default for PipelineOptions {
+    /// Half of the cores decode the images, the sessions runs with 2 intra threads each so a
+    /// quarter of the cores worth of detector and classifier workers keeps every core busy.
+    fn default() -> Self {
+        let cpus = num_cpus::get();
+
+        Self {
+            decode_workers: (cpus / 2).max(1),
+            detector_workers: (cpus / 4).max(1),
+            classifier_workers: (cpus / 4).max(1),
+            queue_size: DEFAULT_QUEUE_SIZE,
+        }
+    }
+}
+
+impl PipelineOptions {
+    /// Sets the number of workers decoding and letterboxing the images, 0 is treated as 1.
+    pub fn set_decode_workers(&mut self, decode_workers: usize) -> &mut Self {
+        self.decode_workers = decode_workers.max(1);
+        self
+    }
+
+    /// Sets the number of workers running the detector, 0 is treated as 1.
+    pub fn set_detector_workers(&mut self, detector_workers: usize) -> &mut Self {
+        self.detector_workers = detector_workers.max(1);
+        self
+    }
+
+    /// Sets the number of workers running the classifier and the ensemble, 0 is treated as 1.
+    pub fn set_classifier_workers(&mut self, classifier_workers: usize) -> &mut Self {
+        self.classifier_workers = classifier_workers.max(1);
+        self
+    }
+
+    /// Sets the number of items each queue between the stages can hold, 0 is treated as 1.
+    pub fn set_queue_size(&mut self, queue_size: usize) -> &mut Self {
+        self.queue_size = queue_size.max(1);
+        self
+    }
+
+    pub fn decode_workers(&self) -> usize {
+        self.decode_workers
+    }
+
+    pub fn detector_workers(&self) -> usize {
+        self.detector_workers
+    }
+
+    pub fn classifier_workers(&self) -> usize {
+        self.classifier_workers
+    }
+
+    pub fn queue_size(&self) -> usize {
+        self.queue_size
+    }
+}
+
+/// An image which has been decoded and letterboxed, waiting for the detector. The image is not
+/// letterboxed when the detector runs on its tiles.
+struct DecodedImage {
+    index: usize,
+    instance: Instance,
+    prediction: Prediction,
+    image: RgbImage,
+    detector_image: Option<PreprocessedImage>,
+}
+
+/// An image which has been through the detector, waiting for the classifier.
+struct DetectedImage {
+    index: usize,
+    instance: Instance,
+    prediction: Prediction,
+    image: RgbImage,
+}
+
+/// Keeps track of the stage each instance is in until its prediction has been sent, so the
+/// instances lost to a panicking worker can still be sent with a failure recorded.
+#[derive(Clone)]
+struct Pending {
+    stages: Arc<Mutex<Vec<Option<Failure>>>>,
+}
+
+impl Pending {
+    fn new(len: usize) -> Self {
+        Self {
+            stages: Arc::new(Mutex::new(vec![Some(Failure::Detector); len])),
+        }
+    }
+
+    /// Sets the stage the instance is in, [`None`] once its prediction has been sent.
+    fn set(&self, index: usize, stage: Option<Failure>) {
+        if let Ok(mut stages) = self.stages.lock() {
+            stages[index] = stage;
+        }
+    }
+
+    /// Returns the indexes of the instances which have not been sent along with their stages.
+    fn remaining(&self) -> Vec<(usize, Failure)> {
+        let stages = match self.stages.lock() {
+            Ok(stages) => stages,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        stages
+            .iter()
+            .enumerate()
+            .filter_map(|(index, stage)| stage.map(|stage| (index, stage)))
+            .collect()
+    }
+}
+
+/// Runs the instances through the staged pipeline in the background, sending each finished
+/// prediction along with the index of its instance to the given sender, mapped by `output`.
+///
+/// The pipeline stops early when the receiver of the given sender gets dropped. Every worker is
+/// joined once the pipeline is done, when one of them panicked the images it was working on are
+/// sent with the failure of their stage recorded, so every instance still gets a prediction.
+pub(crate) fn run<T, F>(
+    speciesnet: SpeciesNet,
+    instances: Vec<Instance>,
+    labels: Arc<Vec<String>>,
+    letterbox_options: LetterboxOptions,
+    sender: SyncSender<T>,
+    output: F,
+) where
+    T: Send + 'static,
+    F: Fn(usize, Prediction) -> T + Clone + Send + 'static,
+{
+    let options = *speciesnet.pipeline_options();
+    let batch_size = speciesnet.batch_size();
+    let speciesnet = Arc::new(speciesnet);
+
+    let file_paths: Vec<PathBuf> = instances
+        .iter()
+        .map(|instance| instance.file_path().to_path_buf())
+        .collect();
+    let pending = Pending::new(instances.len());
+    let mut workers: Vec<JoinHandle<()>> = Vec::new();
+
+    let (instance_sender, instance_receiver) =
+        sync_channel::<(usize, Instance)>(options.queue_size);
+    let (decoded_sender, decoded_receiver) = sync_channel::<DecodedImage>(options.queue_size);
+    let (detected_sender, detected_receiver) = sync_channel::<DetectedImage>(options.queue_size);
+
+    let instance_receiver = Arc::new(Mutex::new(instance_receiver));
+    let decoded_receiver = Arc::new(Mutex::new(decoded_receiver));
+    let detected_receiver = Arc::new(Mutex::new(detected_receiver));
+
+    // Feeds the instances into the pipeline.
+    workers.push(thread::spawn(move || {
+        for (index, instance) in instances.into_iter().enumerate() {
+            if instance_sender.send((index, instance)).is_err() {
+                debug!("Pipeline got stopped, stopping feeding the instances.");
+                break;
+            }
+        }
+    }));
+
+    // Decoding and letterboxing the images.
+    for _ in 0..options.decode_workers {
+        let speciesnet = Arc::clone(&speciesnet);
+        let receiver = Arc::clone(&instance_receiver);
+        let decoded_sender = decoded_sender.clone();
+        let sender = sender.clone();
+        let output = output.clone();
+        let pending = pending.clone();
+
+        workers.push(thread::spawn(move || {
+            while let Some((index, instance)) = receive(&receiver) {
+                let mut prediction = Prediction::new(instance.file_path().to_path_buf());
+
+                let image = match load_image(instance.file_path()) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        let e = Error::from(e);
+                        error!("image failed to load {}", e);
+
+                        record_failure(&mut prediction, Failure::Detector, &e);
+                        record_failure(&mut prediction, Failure::Classifier, &e);
+
+                        if sender.send(output(index, prediction)).is_err() {
+                            break;
+                        }
+
+                        pending.set(index, None);
+                        continue;
+                    }
+                };
+
+                // The tiles of a tiled detection are letterboxed by the detector workers.
+                let detector_image = if speciesnet.detector().tiling().is_some() {
+                    None
+                } else {
+                    match speciesnet
+                        .detector()
+                        .preprocess_ref(&image, letterbox_options)
+                    {
+                        Ok(detector_image) => {
+                            Some(PreprocessedImage::new(detector_image, instance.file_path()))
+                        }
+                        Err(e) => {
+                            let e = Error::from(e);
+                            error!(
+                                "Detector failed on {}: {}",
+                                instance.file_path().display(),
+                                e
+                            );
+
+                            record_failure(&mut prediction, Failure::Detector, &e);
+                            None
+                        }
+                    }
+                };
+
+                let decoded_image = DecodedImage {
+                    index,
+                    instance,
+                    prediction,
+                    image,
+                    detector_image,
+                };
+
+                if decoded_sender.send(decoded_image).is_err() {
+                    break;
+                }
+            }
+        }));
+    }
+
+    drop(decoded_sender);
+
+    // Running the detector.
+    for _ in 0..options.detector_workers {
+        let speciesnet = Arc::clone(&speciesnet);
+        let receiver = Arc::clone(&decoded_receiver);
+        let detected_sender = detected_sender.clone();
+
+        workers.push(thread::spawn(move || {
+            while let Some(mut batch) = receive_batch(&receiver, batch_size) {
+                let results: Vec<(usize, Result<Option<Prediction>, Error>)> =
+                    if speciesnet.detector().tiling().is_some() {
+                        batch
+                            .iter()
+                            .enumerate()
+                            .map(|(i, decoded_image)| {
+                                let result = speciesnet.run_detector_tiled(
+                                    &decoded_image.image,
+                                    decoded_image.instance.file_path(),
+                                    letterbox_options,
+                                );
+                                (i, result)
+                            })
+                            .collect()
+                    } else {
+                        let mut detector_images = Vec::with_capacity(batch.len());
+                        let mut detector_indexes = Vec::with_capacity(batch.len());
+
+                        for (i, decoded_image) in batch.iter_mut().enumerate() {
+                            if let Some(detector_image) = decoded_image.detector_image.take() {
+                                detector_images.push(detector_image);
+                                detector_indexes.push(i);
+                            }
+                        }
+
+                        detector_indexes
+                            .into_iter()
+                            .zip(speciesnet.run_detector(&detector_images))
+                            .collect()
+                    };
+
+                for (i, result) in results {
+                    let decoded_image = &mut batch[i];
+
+                    match result {
+                        Ok(Some(detections)) => {
+                            decoded_image.prediction.merge(detections);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            error!(
+                                "Detector failed on {}: {}",
+                                decoded_image.instance.file_path().display(),
+                                e
+                            );
+
+                            record_failure(&mut decoded_image.prediction, Failure::Detector, &e);
+                        }
+                    }
+                }
+
+                for decoded_image in batch {
+                    let detected_image = DetectedImage {
+                        index: decoded_image.index,
+                        instance: decoded_image.instance,
+                        prediction: decoded_image.prediction,
+                        image: decoded_image.image,
+                    };
+
+                    if detected_sender.send(detected_image).is_err() {
+                        return;
+                    }
+                }
+            }
+        }));
+    }
+
+    drop(detected_sender);
+
+    // Running the classifier and the ensemble.
+    for _ in 0..options.classifier_workers {
+        let speciesnet = Arc::clone(&speciesnet);
+        let receiver = Arc::clone(&detected_receiver);
+        let labels = Arc::clone(&labels);
+        let sender = sender.clone();
+        let output = output.clone();
+        let pending = pending.clone();
+
+        workers.push(thread::spawn(move || {
+            while let Some(batch) = receive_batch(&receiver, batch_size) {
+                let mut predictions = Vec::with_capacity(batch.len());
+                let mut classifier_tensors = Vec::with_capacity(batch.len());
+                let mut classifier_targets = Vec::with_capacity(batch.len());
+
+                for detected_image in batch {
+                    let DetectedImage {
+                        index,
+                        instance,
+                        mut prediction,
+                        image,
+                    } = detected_image;
+                    pending.set(index, Some(Failure::Classifier));
+
+                    let bounding_box = prediction
+                        .bounding_boxes()
+                        .and_then(|bounding_boxes| bounding_boxes.first().copied());
+                    let detections = prediction.detections().as_deref().unwrap_or_default();
+
+                    match speciesnet.classifier_tensors(image, bounding_box, detections) {
+                        Ok(image_tensors) => {
+                            for (target, tensor) in image_tensors {
+                                classifier_tensors.push(tensor);
+                                classifier_targets.push((predictions.len(), target));
+                            }
+                        }
+                        Err(e) => {
+                            error!(
+                                "Classifier failed on {}: {}",
+                                instance.file_path().display(),
+                                e
+                            );
+
+                            record_failure(&mut prediction, Failure::Classifier, &e);
+                        }
+                    }
+
+                    predictions.push((index, instance, prediction));
+                }
+
+                for ((i, target), result) in classifier_targets
+                    .into_iter()
+                    .zip(speciesnet.run_classifier(classifier_tensors))
+                {
+                    let (_, instance, prediction) = &mut predictions[i];
+
+                    match result {
+                        Ok(classifier_results) => {
+                            merge_classifier_outputs(
+                                prediction,
+                                instance.file_path(),
+                                target,
+                                classifier_results.view(),
+                                &labels,
+                                speciesnet.classifier_output(),
+                            );
+                        }
+                        Err(e) => {
+                            error!(
+                                "Classifier failed on {}: {}",
+                                instance.file_path().display(),
+                                e
+                            );
+
+                            record_failure(prediction, Failure::Classifier, &e);
+                        }
+                    }
+                }
+
+                for (index, instance, mut prediction) in predictions {
+                    speciesnet.run_ensemble(&instance, &mut prediction);
+
+                    if sender.send(output(index, prediction)).is_err() {
+                        return;
+                    }
+
+                    pending.set(index, None);
+                }
+            }
+        }));
+    }
+
+    // Joins the workers, then sends the images which did not come out of the pipeline.
+    thread::spawn(move || {
+        let panicked = workers
+            .into_iter()
+            .map(JoinHandle::join)
+            .filter(Result::is_err)
+            .count();
+
+        if panicked == 0 {
+            return;
+        }
+
+        let remaining = pending.remaining();
+
+        warn!(
+            "{} workers of the pipeline panicked, recording the failure of {} images.",
+            panicked,
+            remaining.len()
+        );
+
+        for (index, stage) in remaining {
+            let mut prediction = Prediction::new(file_paths[index].clone());
+            record_failure(&mut prediction, stage, &Error::WorkerPanicked);
+
+            if sender.send(output(index, prediction)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Receives an item from the shared receiver. Returns [`None`] when every sender of the queue has
+/// been dropped and the queue is empty.
+fn receive<T>(receiver: &Mutex<Receiver<T>>) -> Option<T> {
+    receiver.lock().ok()?.recv().ok()
+}
+
+/// Receives up to `batch_size` items from the shared receiver, waiting only for the first item.
+/// Returns [`None`] when every sender of the queue has been dropped and the queue is empty.
+fn receive_batch<T>(receiver: &Mutex<Receiver<T>>, batch_size: usize) -> Option<Vec<T>> {
+    let receiver = receiver.lock().ok()?;
+    let mut batch = vec![receiver.recv().ok()?];
+
+    while batch.len() < batch_size {
+        match receiver.try_recv() {
+            Ok(item) => batch.push(item),
+            Err(_) => break,
+        }
+    }
+
+    Some(batch)
+}
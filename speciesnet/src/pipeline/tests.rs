@@ -0,0 +1,137 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use speciesnet_core::io::{Failure, Prediction};
+
+use crate::{
+    PipelineOptions,
+    error::Error,
+    testing::{Fixture, LION, StubDetector, wait_for_workers},
+};
+
+/// Pipeline with one worker for each stage, each queue holding a single item.
+fn single_worker_options() -> PipelineOptions {
+    let mut options = PipelineOptions::default();
+    options
+        .set_decode_workers(1)
+        .set_detector_workers(1)
+        .set_classifier_workers(1)
+        .set_queue_size(1);
+    options
+}
+
+#[test]
+fn test_predict_keeps_the_order_of_the_instances() -> Result<(), Error> {
+    let fixture = Fixture::new("pipeline-order", 24)?;
+    let mut options = PipelineOptions::default();
+    options
+        .set_decode_workers(4)
+        .set_detector_workers(3)
+        .set_classifier_workers(3)
+        .set_queue_size(2);
+
+    let speciesnet = fixture
+        .builder(Arc::new(StubDetector::default()))
+        .batch_size(4)
+        .pipeline_options(options)
+        .build()?;
+
+    let predictions = speciesnet.predict(fixture.instances())?;
+
+    let paths: Vec<_> = predictions.iter().map(Prediction::file_path).collect();
+    let expected: Vec<_> = fixture
+        .instances()
+        .iter()
+        .map(|instance| instance.file_path())
+        .collect();
+    assert_eq!(paths, expected);
+
+    for prediction in &predictions {
+        assert!(!prediction.has_failed());
+        assert_eq!(
+            prediction.prediction_reference().map(ToString::to_string),
+            Some(LION.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_predict_stream_waits_for_the_receiver() -> Result<(), Error> {
+    let fixture = Fixture::new("pipeline-backpressure", 32)?;
+    let detector = Arc::new(StubDetector::default());
+    let speciesnet = fixture
+        .builder(Arc::clone(&detector))
+        .batch_size(1)
+        .pipeline_options(single_worker_options())
+        .build()?;
+
+    let receiver = speciesnet.predict_stream(fixture.instances().to_vec(), 1)?;
+    thread::sleep(Duration::from_millis(300));
+
+    // Only the images filling the queues between the stages get detected while nothing is read.
+    let detected = detector.images();
+    assert!(detected <= 6, "{detected} images detected without reading");
+
+    assert_eq!(receiver.iter().count(), 32);
+    assert_eq!(detector.images(), 32);
+
+    Ok(())
+}
+
+#[test]
+fn test_predict_stream_stops_when_the_receiver_is_dropped() -> Result<(), Error> {
+    let fixture = Fixture::new("pipeline-shutdown", 32)?;
+    let detector = Arc::new(StubDetector::default());
+    let speciesnet = fixture
+        .builder(Arc::clone(&detector))
+        .batch_size(1)
+        .pipeline_options(single_worker_options())
+        .build()?;
+    let references = Arc::strong_count(&detector);
+
+    let receiver = speciesnet.predict_stream(fixture.instances().to_vec(), 1)?;
+    assert!(receiver.recv().is_ok());
+    drop(receiver);
+
+    assert!(wait_for_workers(&detector, references));
+    assert!(detector.images() < 32);
+
+    Ok(())
+}
+
+#[test]
+fn test_predict_records_the_images_of_a_panicking_worker() -> Result<(), Error> {
+    let fixture = Fixture::new("pipeline-panic", 8)?;
+    let detector = Arc::new(StubDetector::panicking_on(3));
+    let speciesnet = fixture
+        .builder(Arc::clone(&detector))
+        .batch_size(1)
+        .pipeline_options(single_worker_options())
+        .build()?;
+    let references = Arc::strong_count(&detector);
+
+    let predictions = speciesnet.predict(fixture.instances())?;
+
+    // Every image still gets a prediction, the ones the detector worker took down with it have the
+    // failure of the detector recorded.
+    assert_eq!(predictions.len(), 8);
+
+    for (index, prediction) in predictions.iter().enumerate() {
+        assert_eq!(
+            prediction.file_path(),
+            fixture.instances()[index].file_path()
+        );
+
+        if index < 3 {
+            assert!(!prediction.has_failed(), "{index}");
+        } else {
+            assert_eq!(prediction.failures(), &Some(vec![Failure::Detector]));
+            assert_eq!(prediction.errors().as_ref().unwrap()[0].kind(), "panic");
+        }
+    }
+
+    assert!(wait_for_workers(&detector, references));
+
+    Ok(())
+}
@@ -1,40 +1,69 @@
 use std::{
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        Arc,
+        mpsc::{Receiver, SyncSender, sync_channel},
+    },
+    thread,
 };
 
-use rayon::prelude::*;
+use image::RgbImage;
+use ndarray::{Array1, Array4, ArrayView1};
+use rayon::{ThreadPool, prelude::*};
 use speciesnet_classifier::{
     SpeciesNetClassifier,
-    classifier::{read_labels_from_file, transform},
-    image::preprocess as classifier_preprocess,
+    classifier::{classifications_with, read_labels_from_file, transform_with},
     input::ClassifierInput,
 };
 use speciesnet_core::{
-    detector::BoundingBox,
-    io::{Instance, Prediction},
+    classifier::ClassifierOutputOptions,
+    detector::{BoundingBox, Detection},
+    io::{Failure, Instance, Prediction},
     load_image,
-    shape::Shape,
 };
 use speciesnet_detector::{
     SpeciesNetDetector,
+    config::DetectorConfig,
     preprocess::{LetterboxOptions, PreprocessedImage},
+    tiling::TilingOptions,
 };
 use speciesnet_ensemble::{
     SpeciesNetEnsemble, error::Error::NoneDetectionOrClassification, input::EnsembleInput,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    builder::SpeciesNetBuilder,
+    detection_classification::DetectionClassificationOptions,
+    error::Error,
+    model_info::ModelInfo,
+    pipeline::{self, PipelineOptions},
+};
 
-use crate::{error::Error, model_info::ModelInfo};
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug, Clone)]
 pub struct SpeciesNet {
     model_info: ModelInfo,
     detector: SpeciesNetDetector,
     classifier: SpeciesNetClassifier,
-    ensemble: SpeciesNetEnsemble,
+    ensemble: Arc<SpeciesNetEnsemble>,
+    letterbox_options: LetterboxOptions,
+    thread_pool: Option<Arc<ThreadPool>>,
+    batch_size: usize,
+    pipeline_options: PipelineOptions,
+    detection_classification: Option<DetectionClassificationOptions>,
+    classifier_output: ClassifierOutputOptions,
 }
 
+/// Tensors of an image for the classifier, along with the index of the detection each of them is
+/// cropped to, [`None`] being the image-level tensor.
+pub(crate) type ClassifierTensors = Vec<(Option<usize>, Array4<f32>)>;
+
+/// Default number of images run through the detector and the classifier together.
+pub const DEFAULT_BATCH_SIZE: usize = 1;
+
 impl SpeciesNet {
     /// Initialize the detector, classifier, and ensemble by loading them into memory.
     #[cfg(feature = "download-model")]
@@ -53,54 +82,285 @@ impl SpeciesNet {
         Self::from_model_info(model_info)
     }
 
-    fn from_model_info(model_info: ModelInfo) -> Result<Self, Error> {
-        let classifier = SpeciesNetClassifier::new(model_info.classifier())?;
-        info!("Classifier initialized.");
-
-        let detector = SpeciesNetDetector::new(model_info.detector())?;
-        info!("Detector initialized.");
+    /// Creates a [`SpeciesNetBuilder`] for configuring the runtime options before loading the
+    /// models.
+    pub fn builder() -> SpeciesNetBuilder {
+        SpeciesNetBuilder::default()
+    }
 
-        let ensemble = SpeciesNetEnsemble::new(model_info.geofence(), model_info.taxonomy(), None)?;
-        info!("Ensemble initialized.");
+    fn from_model_info(model_info: ModelInfo) -> Result<Self, Error> {
+        Self::builder().build_from_model_info(model_info)
+    }
 
-        Ok(Self {
+    pub(crate) fn from_parts(
+        model_info: ModelInfo,
+        detector: SpeciesNetDetector,
+        classifier: SpeciesNetClassifier,
+        ensemble: SpeciesNetEnsemble,
+        letterbox_options: LetterboxOptions,
+        thread_pool: Option<Arc<ThreadPool>>,
+    ) -> Self {
+        Self {
             model_info,
-            classifier,
             detector,
-            ensemble,
-        })
+            classifier,
+            ensemble: Arc::new(ensemble),
+            letterbox_options,
+            thread_pool,
+            batch_size: DEFAULT_BATCH_SIZE,
+            pipeline_options: PipelineOptions::default(),
+            detection_classification: None,
+            classifier_output: ClassifierOutputOptions::default(),
+        }
+    }
+
+    /// Sets the number of images which are stacked into one tensor and run through the detector
+    /// and the classifier together, a batch size of 0 is treated as 1.
+    ///
+    /// Batch sizes larger than 1 requires the models to be exported with a dynamic batch axis,
+    /// when running a batch fails the images of the batch are run again one by one.
+    pub fn set_batch_size(&mut self, batch_size: usize) -> &mut Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Sets the number of workers and the size of the queues of each stage of the pipeline used by
+    /// [`SpeciesNet::predict`] and [`SpeciesNet::predict_stream`].
+    pub fn set_pipeline_options(&mut self, pipeline_options: PipelineOptions) -> &mut Self {
+        self.pipeline_options = pipeline_options;
+        self
+    }
+
+    pub fn pipeline_options(&self) -> &PipelineOptions {
+        &self.pipeline_options
+    }
+
+    /// Classifies each detection of the images on its own with the given options on top of the
+    /// image-level classification, [`None`] only classifies the top detection, which is the
+    /// default.
+    pub fn set_detection_classification(
+        &mut self,
+        detection_classification: Option<DetectionClassificationOptions>,
+    ) -> &mut Self {
+        self.detection_classification = detection_classification;
+        self
+    }
+
+    pub fn detection_classification(&self) -> Option<&DetectionClassificationOptions> {
+        self.detection_classification.as_ref()
+    }
+
+    /// Sets the number of classifications kept on each image and how much of the scores is
+    /// retained for the rollups of the ensemble. Only the top-k classifications are written out,
+    /// the retained scores are only used inside of [`SpeciesNet::predict`] and
+    /// [`SpeciesNet::predict_stream`].
+    pub fn set_classifier_output(
+        &mut self,
+        classifier_output: ClassifierOutputOptions,
+    ) -> &mut Self {
+        self.classifier_output = classifier_output;
+        self
+    }
+
+    pub fn classifier_output(&self) -> &ClassifierOutputOptions {
+        &self.classifier_output
+    }
+
+    /// Sets whether the ensemble geofences the animal classifications by the country and admin1
+    /// region of the images, see [`SpeciesNetEnsemble::set_enable_geofence`].
+    pub fn set_enable_geofence(&mut self, enable_geofence: bool) -> &mut Self {
+        Arc::make_mut(&mut self.ensemble).set_enable_geofence(enable_geofence);
+        self
+    }
+
+    pub fn enable_geofence(&self) -> bool {
+        self.ensemble.enable_geofence()
+    }
+
+    /// Sets whether the predictions carry the trace of how the ensemble came to them, see
+    /// [`SpeciesNetEnsemble::set_explain`].
+    pub fn set_explain(&mut self, explain: bool) -> &mut Self {
+        Arc::make_mut(&mut self.ensemble).set_explain(explain);
+        self
+    }
+
+    pub fn explain(&self) -> bool {
+        self.ensemble.explain()
+    }
+
+    pub fn model_info(&self) -> &ModelInfo {
+        &self.model_info
+    }
+
+    pub fn letterbox_options(&self) -> &LetterboxOptions {
+        &self.letterbox_options
+    }
+
+    /// Sets the post-processing options of the detector, see [`DetectorConfig`].
+    pub fn set_detector_config(&mut self, config: DetectorConfig) -> Result<&mut Self, Error> {
+        self.detector.set_config(config)?;
+        Ok(self)
+    }
+
+    pub fn detector_config(&self) -> &DetectorConfig {
+        self.detector.config()
+    }
+
+    /// Runs the detector on overlapping tiles of each image instead of the whole image, see
+    /// [`TilingOptions`]. [`None`] turns the tiled detection off, which is the default.
+    pub fn set_tiling(&mut self, tiling: Option<TilingOptions>) -> Result<&mut Self, Error> {
+        self.detector.set_tiling(tiling)?;
+        Ok(self)
+    }
+
+    pub fn tiling(&self) -> Option<&TilingOptions> {
+        self.detector.tiling()
+    }
+
+    pub(crate) fn detector(&self) -> &SpeciesNetDetector {
+        &self.detector
     }
 
     /// Performs the detection by MegaDetector Model from given file or folder. Returns a list of
     /// detections.
+    ///
+    /// An image which fails to load or run through the detector does not stop the run, its
+    /// prediction is returned with the failure recorded instead.
     pub fn detect(&self, instances: &[Instance]) -> Result<Vec<Prediction>, Error> {
         info!("Starting the detector ort step.");
 
-        let image_format_options: Arc<LetterboxOptions> = Arc::new(
-            LetterboxOptions::builder()
-                .shape(Shape::Square(1280))
-                .build(),
-        );
+        let letterbox_options = self.letterbox_options;
 
-        let detections = instances
-            .par_iter()
-            .map(|fp| {
+        let detections = self.install(|| {
+            instances
+                .par_chunks(self.batch_size)
+                .flat_map_iter(|batch| self.detect_batch(batch, letterbox_options))
+                .collect::<Vec<Prediction>>()
+        });
+
+        Ok(detections)
+    }
+
+    /// Performs the detection like [`SpeciesNet::detect`], but sends each prediction to the
+    /// returned receiver as soon as it finishes instead of waiting for the whole list.
+    ///
+    /// See [`SpeciesNet::predict_stream`] for how the queue works.
+    pub fn detect_stream(
+        &self,
+        instances: Vec<Instance>,
+        queue_size: usize,
+    ) -> Result<Receiver<Prediction>, Error> {
+        info!("Starting the detector ort step as a stream.");
+
+        let letterbox_options = self.letterbox_options;
+
+        Ok(
+            self.stream(instances, queue_size, move |speciesnet, batch| {
+                speciesnet.detect_batch(batch, letterbox_options)
+            }),
+        )
+    }
+
+    /// Runs the detector on a batch of instances, the instances which the detector finds nothing
+    /// in get a prediction with no detections.
+    fn detect_batch(
+        &self,
+        instances: &[Instance],
+        letterbox_options: LetterboxOptions,
+    ) -> Vec<Prediction> {
+        let mut results: Vec<Result<Option<Prediction>, Error>> =
+            Vec::with_capacity(instances.len());
+        let mut images: Vec<PreprocessedImage> = Vec::with_capacity(instances.len());
+        let mut image_indexes: Vec<usize> = Vec::with_capacity(instances.len());
+
+        if self.detector.tiling().is_some() {
+            for fp in instances {
+                let result = load_image(fp.file_path())
+                    .map_err(Error::from)
+                    .and_then(|image| {
+                        self.run_detector_tiled(&image, fp.file_path(), letterbox_options)
+                    });
+                results.push(result);
+            }
+
+            return collect_detections(instances, results);
+        }
+
+        for (index, fp) in instances.iter().enumerate() {
+            let preprocess = || -> Result<PreprocessedImage, Error> {
                 let loaded_image = load_image(fp.file_path())?;
                 let preprocessed_image = self
                     .detector
-                    .preprocess(loaded_image.into(), *image_format_options)?;
-                let preprocessed_image = PreprocessedImage::new(preprocessed_image, fp.file_path());
+                    .preprocess(loaded_image.into(), letterbox_options)?;
+
+                Ok(PreprocessedImage::new(preprocessed_image, fp.file_path()))
+            };
+
+            match preprocess() {
+                Ok(image) => {
+                    images.push(image);
+                    image_indexes.push(index);
+                    results.push(Ok(None));
+                }
+                Err(e) => results.push(Err(e)),
+            }
+        }
+
+        for (index, result) in image_indexes.into_iter().zip(self.run_detector(&images)) {
+            results[index] = result;
+        }
 
-                let predictions = self.detector.predict(preprocessed_image)?;
+        collect_detections(instances, results)
+    }
 
-                Ok(predictions)
-            })
-            .collect::<Result<Vec<Option<Prediction>>, Error>>()?;
+    /// Runs the detector on the tiles of the image, see [`SpeciesNetDetector::predict_tiled`].
+    pub(crate) fn run_detector_tiled(
+        &self,
+        image: &RgbImage,
+        path: &Path,
+        letterbox_options: LetterboxOptions,
+    ) -> Result<Option<Prediction>, Error> {
+        Ok(self
+            .detector
+            .predict_tiled(image, path, letterbox_options)?)
+    }
 
-        Ok(detections.into_iter().flatten().collect())
+    /// Runs the detector on the batch of images, when the batch fails the images are run again
+    /// one by one so only the images which actually fail are recorded as failed.
+    pub(crate) fn run_detector(
+        &self,
+        images: &[PreprocessedImage],
+    ) -> Vec<Result<Option<Prediction>, Error>> {
+        match self.detector.predict_batch(images) {
+            Ok(predictions) => predictions.into_iter().map(Ok).collect(),
+            Err(e) if images.len() > 1 => {
+                warn!(
+                    "Detector failed on a batch of {} images, running them one by one: {}",
+                    images.len(),
+                    e
+                );
+
+                images
+                    .iter()
+                    .map(|image| {
+                        let mut predictions =
+                            self.detector.predict_batch(std::slice::from_ref(image))?;
+                        Ok(predictions.pop().flatten())
+                    })
+                    .collect()
+            }
+            Err(e) => vec![Err(e.into())],
+        }
     }
 
     /// Performs the classification from detector output by the cameratrap model.
+    ///
+    /// An image which fails to load or run through the classifier does not stop the run, its
+    /// prediction is returned with the failure recorded instead.
     pub fn classify(&self, detector_output_path: &PathBuf) -> Result<Vec<Prediction>, Error> {
         info!("Starting classification");
 
@@ -108,163 +368,446 @@ impl SpeciesNet {
 
         // Load labels
         let labels: Vec<String> = read_labels_from_file(self.model_info.classifier_labels())?;
-        let predictions = classifier_inputs
-            .par_iter()
-            .map(|fp| {
-                let image = classifier_preprocess(fp)?;
-                let tensor = image.image_tensor;
-                let image_path = image.path;
-                let outputs = self.classifier.classify(tensor)?;
-
-                // Transform outputs into usable format (softmax, mapping labels, pick top 5)
-                let prediction = transform(image_path, outputs.view(), &labels);
-                Ok(prediction)
-            })
-            .collect::<Result<Vec<Prediction>, Error>>()?;
+        let predictions = self.install(|| {
+            classifier_inputs
+                .par_chunks(self.batch_size)
+                .flat_map_iter(|batch| {
+                    let mut results: Vec<Result<Prediction, Error>> =
+                        Vec::with_capacity(batch.len());
+                    let mut tensors: Vec<Array4<f32>> = Vec::with_capacity(batch.len());
+                    let mut tensor_targets: Vec<(usize, Option<usize>)> =
+                        Vec::with_capacity(batch.len());
+
+                    for (index, fp) in batch.iter().enumerate() {
+                        let preprocess = || -> Result<ClassifierTensors, Error> {
+                            let image = load_image(&fp.file_path)?;
+                            self.classifier_tensors(image, fp.bbox, &fp.detections)
+                        };
+
+                        match preprocess() {
+                            Ok(image_tensors) => {
+                                let mut prediction = Prediction::new(fp.file_path.clone());
+
+                                // Keeps the detections to put their own classifications on.
+                                if self.detection_classification.is_some() {
+                                    prediction.set_detections(Some(fp.detections.clone()));
+                                }
+
+                                for (target, tensor) in image_tensors {
+                                    tensors.push(tensor);
+                                    tensor_targets.push((index, target));
+                                }
+
+                                results.push(Ok(prediction));
+                            }
+                            Err(e) => results.push(Err(e)),
+                        }
+                    }
+
+                    for ((index, target), outputs) in
+                        tensor_targets.into_iter().zip(self.run_classifier(tensors))
+                    {
+                        match (outputs, &mut results[index]) {
+                            (Ok(outputs), Ok(prediction)) => merge_classifier_outputs(
+                                prediction,
+                                &batch[index].file_path,
+                                target,
+                                outputs.view(),
+                                &labels,
+                                &self.classifier_output,
+                            ),
+                            (Err(e), result) => *result = Err(e),
+                            (Ok(_), Err(_)) => {}
+                        }
+                    }
+
+                    batch
+                        .iter()
+                        .zip(results)
+                        .map(|(fp, result)| {
+                            result.unwrap_or_else(|e| {
+                                error!("Classifier failed on {}: {}", fp.file_path.display(), e);
+
+                                let mut prediction = Prediction::new(fp.file_path.clone());
+                                record_failure(&mut prediction, Failure::Classifier, &e);
+                                prediction
+                            })
+                        })
+                        .collect::<Vec<Prediction>>()
+                })
+                .collect::<Vec<Prediction>>()
+        });
 
         debug!("Finished classification");
         Ok(predictions)
     }
 
     /// Performs the ensemble
+    ///
+    /// An image which is missing its detections or classifications, or fails the ensemble does
+    /// not stop the run, its prediction is returned with the failure recorded instead.
     pub fn ensemble(
         &self,
         instances_path: &PathBuf,
         detector_output_path: &PathBuf,
         classifier_output_path: &PathBuf,
     ) -> Result<Vec<Prediction>, Error> {
-        info!("Starting ensemble");
-
         let ensemble_inputs =
             EnsembleInput::from(instances_path, detector_output_path, classifier_output_path)?;
 
-        let predictions = ensemble_inputs
-            .par_iter()
-            .map(|input| {
-                if let (Some(detections), Some(classification)) =
-                    (input.detections(), input.classifications())
-                {
-                    let geofence_result = &self.ensemble.ensemble(
-                        detections,
-                        classification,
-                        input.country().clone(),
-                        input.admin1_region().clone(),
-                    )?;
-
-                    Ok(Prediction::from_ensemble(
-                        input.file_path().clone(),
-                        input.country().clone(),
-                        input.admin1_region().clone(),
-                        geofence_result.clone(),
-                        detections.clone(),
-                        classification.clone(),
-                    ))
-                } else {
-                    Err(NoneDetectionOrClassification.into())
-                }
-            })
-            .collect::<Result<Vec<Prediction>, Error>>()?;
+        self.ensemble_inputs(ensemble_inputs)
+    }
+
+    /// Same as [`SpeciesNet::ensemble`] with instances which have already been read, e.g. after
+    /// filling in their default locations.
+    pub fn ensemble_instances(
+        &self,
+        instances: &[Instance],
+        detector_output_path: &PathBuf,
+        classifier_output_path: &PathBuf,
+    ) -> Result<Vec<Prediction>, Error> {
+        let ensemble_inputs =
+            EnsembleInput::from_instances(instances, detector_output_path, classifier_output_path)?;
+
+        self.ensemble_inputs(ensemble_inputs)
+    }
+
+    fn ensemble_inputs(
+        &self,
+        ensemble_inputs: Vec<EnsembleInput>,
+    ) -> Result<Vec<Prediction>, Error> {
+        info!("Starting ensemble");
+
+        let predictions = self.install(|| {
+            ensemble_inputs
+                .par_iter()
+                .map(|input| {
+                    let ensemble = || -> Result<Prediction, Error> {
+                        let (Some(detections), Some(classification)) =
+                            (input.detections(), input.classifications())
+                        else {
+                            return Err(NoneDetectionOrClassification.into());
+                        };
+
+                        let geofence_result = self.ensemble.ensemble(
+                            detections,
+                            classification,
+                            input.country().clone(),
+                            input.admin1_region().clone(),
+                        )?;
+
+                        let mut prediction = Prediction::from_ensemble(
+                            input.file_path().clone(),
+                            input.country().clone(),
+                            input.admin1_region().clone(),
+                            geofence_result,
+                            detections.clone(),
+                            classification.clone(),
+                        );
+
+                        if detections.iter().any(|d| d.classifications().is_some()) {
+                            prediction.set_species_present(Some(self.ensemble.species_present(
+                                detections,
+                                input.country().clone(),
+                                input.admin1_region().clone(),
+                            )?));
+                        }
+
+                        Ok(prediction)
+                    };
+
+                    ensemble().unwrap_or_else(|e| {
+                        error!("Ensemble failed on {}: {}", input.file_path().display(), e);
+
+                        let mut prediction = Prediction::new(input.file_path().clone());
+                        prediction
+                            .set_country(input.country().clone())
+                            .set_admin1_region(input.admin1_region().clone())
+                            .set_detections(input.detections().clone())
+                            .set_classifications(input.classifications().clone());
+                        record_failure(&mut prediction, Failure::Ensemble, &e);
+                        prediction
+                    })
+                })
+                .collect::<Vec<Prediction>>()
+        });
 
         Ok(predictions)
     }
 
     /// Performs the whole pipeline (Detection, Classification, Ensemble) from given list of
     /// instances.
+    ///
+    /// The images run through the staged pipeline configured by
+    /// [`SpeciesNet::set_pipeline_options`], the predictions are returned in the same order as the
+    /// given instances.
+    ///
+    /// Each stage records its failures on the prediction of the image and the image keeps going
+    /// through the stages which can still run, e.g. the classifier still runs on the full image
+    /// when the detector fails.
     pub fn predict(&self, instances: &[Instance]) -> Result<Vec<Prediction>, Error> {
         info!("Starting the predictions on the whole pipeline.");
 
-        // loads the image, this will gets converted to both detector input and classifier so they
-        // need to stay.
+        let labels = Arc::new(read_labels_from_file(self.model_info.classifier_labels())?);
+        let (sender, receiver) = sync_channel(self.pipeline_options.queue_size());
 
-        let letterbox_options: Arc<LetterboxOptions> = Arc::new(
-            LetterboxOptions::builder()
-                .shape(Shape::Square(1280))
-                .build(),
+        pipeline::run(
+            self.clone(),
+            instances.to_vec(),
+            labels,
+            self.letterbox_options,
+            sender,
+            |index, prediction| (index, prediction),
         );
 
-        let labels = read_labels_from_file(self.model_info.classifier_labels())?;
+        let mut predictions = receiver.iter().collect::<Vec<(usize, Prediction)>>();
+        predictions.sort_unstable_by_key(|(index, _)| *index);
 
-        let predictions = instances
-            .par_iter()
-            .map(|fp| {
-                let mut prediction = Prediction::new(fp.file_path().to_path_buf());
+        info!("Finished running the whole flow.");
+        Ok(predictions
+            .into_iter()
+            .map(|(_, prediction)| prediction)
+            .collect())
+    }
 
-                // Loading the image
-                let loaded_image = match load_image(fp.file_path()) {
-                    Ok(image) => image,
-                    Err(e) => {
-                        error!("image failed to load {}", e);
-                        return Ok(prediction);
-                    }
-                };
+    /// Performs the whole pipeline like [`SpeciesNet::predict`], but sends each prediction to the
+    /// returned receiver as soon as it finishes instead of holding every prediction in memory
+    /// until the end.
+    ///
+    /// The instances are run in the background, at most `queue_size` finished predictions are
+    /// waiting in the queue at any time, the workers wait for the receiver to catch up when the
+    /// queue is full. The predictions come in the order they finish, not in the order of the
+    /// instances. Dropping the receiver stops the run after the images currently being processed.
+    pub fn predict_stream(
+        &self,
+        instances: Vec<Instance>,
+        queue_size: usize,
+    ) -> Result<Receiver<Prediction>, Error> {
+        info!("Starting the predictions on the whole pipeline as a stream.");
+
+        let labels = Arc::new(read_labels_from_file(self.model_info.classifier_labels())?);
+        let (sender, receiver) = sync_channel(queue_size);
+
+        pipeline::run(
+            self.clone(),
+            instances,
+            labels,
+            self.letterbox_options,
+            sender,
+            |_, prediction| prediction,
+        );
 
-                // Running the detector
-                let detector_image = self
-                    .detector
-                    .preprocess(loaded_image.clone().into(), *letterbox_options)?;
-                let detector_image = PreprocessedImage::new(detector_image, fp.file_path());
+        Ok(receiver)
+    }
 
-                let detector_results = self.detector.predict(detector_image)?;
+    /// Runs the classifier on the batch of tensors, when the batch fails the tensors are run again
+    /// one by one so only the images which actually fail are recorded as failed.
+    pub(crate) fn run_classifier(
+        &self,
+        tensors: Vec<Array4<f32>>,
+    ) -> Vec<Result<Array1<f32>, Error>> {
+        if tensors.is_empty() {
+            return Vec::new();
+        }
+
+        match self.classifier.classify_batch(&tensors) {
+            Ok(outputs) => outputs.into_iter().map(Ok).collect(),
+            Err(e) if tensors.len() > 1 => {
+                warn!(
+                    "Classifier failed on a batch of {} images, running them one by one: {}",
+                    tensors.len(),
+                    e
+                );
+
+                tensors
+                    .into_iter()
+                    .map(|tensor| Ok(self.classifier.classify(tensor)?))
+                    .collect()
+            }
+            Err(e) => vec![Err(e.into())],
+        }
+    }
 
-                if let Some(ref res) = detector_results {
-                    prediction.merge(res.clone());
-                }
+    /// Runs the ensemble on the detections and classifications of the prediction, when both of
+    /// them are available.
+    pub(crate) fn run_ensemble(&self, fp: &Instance, prediction: &mut Prediction) {
+        let (Some(detections), Some(classifications)) =
+            (prediction.detections(), prediction.classifications())
+        else {
+            return;
+        };
+
+        let ensemble = || -> Result<Prediction, Error> {
+            let ensemble_results = self.ensemble.ensemble(
+                detections,
+                classifications,
+                fp.country().map(str::to_string),
+                fp.admin1_region().map(str::to_string),
+            )?;
+
+            let mut ensemble_prediction = Prediction::from_ensemble(
+                fp.file_path().to_path_buf(),
+                fp.country().map(str::to_string),
+                fp.admin1_region().map(str::to_string),
+                ensemble_results,
+                detections.clone(),
+                classifications.clone(),
+            );
+
+            if detections.iter().any(|d| d.classifications().is_some()) {
+                ensemble_prediction.set_species_present(Some(self.ensemble.species_present(
+                    detections,
+                    fp.country().map(str::to_string),
+                    fp.admin1_region().map(str::to_string),
+                )?));
+            }
+
+            Ok(ensemble_prediction)
+        };
+
+        match ensemble() {
+            Ok(ensemble_prediction) => {
+                prediction.set_model_version(Some(self.model_info.version().to_string()));
+                prediction.merge(ensemble_prediction);
+            }
+            Err(e) => {
+                error!("Ensemble failed on {}: {}", fp.file_path().display(), e);
+
+                record_failure(prediction, Failure::Ensemble, &e);
+            }
+        }
+    }
 
-                let bounding_boxes = match detector_results {
-                    Some(detections) => match detections.detections() {
-                        Some(det) => {
-                            let binding = det
-                                .iter()
-                                .map(|d| *d.bounding_box())
-                                .collect::<Vec<BoundingBox>>();
+    /// Preprocesses the image for the classifier following the type of the model, returning the
+    /// image-level tensor first, followed by the tensors of the detections which are classified on
+    /// their own along with their indexes when that is enabled.
+    pub(crate) fn classifier_tensors(
+        &self,
+        image: RgbImage,
+        bounding_box: Option<BoundingBox>,
+        detections: &[Detection],
+    ) -> Result<ClassifierTensors, Error> {
+        let mut tensors = Vec::new();
+
+        // Full image models never classify crops.
+        if let Some(options) = self
+            .detection_classification
+            .as_ref()
+            .filter(|_| self.classifier.model_type().crops())
+        {
+            let indexes = options.select(detections);
+            let bounding_boxes: Vec<BoundingBox> = indexes
+                .iter()
+                .map(|&i| *detections[i].bounding_box())
+                .collect();
+            let crops = self.classifier.preprocess_crops(&image, &bounding_boxes)?;
+
+            tensors.extend(indexes.into_iter().map(Some).zip(crops));
+        }
+
+        // The decoded image is moved into the classifier, it is not needed anymore.
+        let tensor = self
+            .classifier
+            .preprocess(image.into(), bounding_box.as_slice())?;
+        tensors.insert(0, (None, tensor));
+
+        Ok(tensors)
+    }
 
-                            binding
-                        }
-                        None => vec![],
-                    },
-                    None => vec![],
-                };
-
-                // Running the classifier
-                let classifier_tensor = self
-                    .classifier
-                    .preprocess(loaded_image.into(), &bounding_boxes)?;
-
-                let classifier_results = self.classifier.classify(classifier_tensor)?;
-                let classifier_results =
-                    transform(fp.file_path(), classifier_results.view(), &labels);
-
-                prediction.merge(classifier_results);
-
-                // Running the emsembler
-                if let (Some(detections), Some(classifications)) =
-                    (prediction.detections(), prediction.classifications())
-                {
-                    let ensemble_results = self.ensemble.ensemble(
-                        detections,
-                        classifications,
-                        fp.country().map(str::to_string),
-                        fp.admin1_region().map(str::to_string),
-                    )?;
-
-                    let ensemble_prediction = Prediction::from_ensemble(
-                        fp.file_path().to_path_buf(),
-                        fp.country().map(str::to_string),
-                        fp.admin1_region().map(str::to_string),
-                        ensemble_results.clone(),
-                        detections.clone(),
-                        classifications.clone(),
-                    );
-
-                    prediction.set_model_version(Some(self.model_info.version().to_string()));
-                    prediction.merge(ensemble_prediction);
-                }
+    /// Runs the given operation inside of the dedicated thread pool when there is one, otherwise
+    /// inside of the global rayon thread pool.
+    fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        match &self.thread_pool {
+            Some(thread_pool) => thread_pool.install(op),
+            None => op(),
+        }
+    }
 
-                Ok(prediction)
-            })
-            .collect::<Result<Vec<Prediction>, Error>>()?;
+    /// Runs the given function on every batch of instances in the background, sending its outputs
+    /// to the returned bounded receiver.
+    fn stream<F>(&self, instances: Vec<Instance>, queue_size: usize, f: F) -> Receiver<Prediction>
+    where
+        F: Fn(&SpeciesNet, &[Instance]) -> Vec<Prediction> + Send + Sync + 'static,
+    {
+        let (sender, receiver) = sync_channel(queue_size);
+        let speciesnet = self.clone();
+
+        thread::spawn(move || {
+            let result = speciesnet.install(|| {
+                instances
+                    .par_chunks(speciesnet.batch_size)
+                    .try_for_each_with(sender, |sender: &mut SyncSender<Prediction>, batch| {
+                        f(&speciesnet, batch)
+                            .into_iter()
+                            .try_for_each(|prediction| sender.send(prediction).map_err(|_| ()))
+                    })
+            });
+
+            match result {
+                Ok(()) => info!("Finished running the stream."),
+                Err(_) => debug!("Receiver of the stream got dropped, stopping the stream."),
+            }
+        });
+
+        receiver
+    }
+}
 
-        info!("Finished running the whole flow.");
-        Ok(predictions)
+/// Puts the outputs of the classifier on the prediction, the image-level classifications when the
+/// target is [`None`], otherwise the classifications of the detection at the target index.
+pub(crate) fn merge_classifier_outputs(
+    prediction: &mut Prediction,
+    file_path: &Path,
+    target: Option<usize>,
+    outputs: ArrayView1<f32>,
+    labels: &[String],
+    options: &ClassifierOutputOptions,
+) {
+    match target {
+        // Transform outputs into usable format (softmax, mapping labels, pick top k)
+        None => {
+            prediction.merge(transform_with(file_path, outputs, labels, options));
+        }
+        Some(index) => {
+            if let Some(detection) = prediction
+                .detections_mut()
+                .and_then(|detections| detections.get_mut(index))
+            {
+                detection.set_classifications(Some(classifications_with(outputs, labels, options)));
+            }
+        }
     }
 }
+
+/// Pairs the detector results with their instances, the instances which the detector finds nothing
+/// in get a prediction with no detections and the failed ones get a prediction with the failure
+/// recorded.
+fn collect_detections(
+    instances: &[Instance],
+    results: Vec<Result<Option<Prediction>, Error>>,
+) -> Vec<Prediction> {
+    instances
+        .iter()
+        .zip(results)
+        .map(|(fp, result)| match result {
+            Ok(Some(prediction)) => prediction,
+            Ok(None) => Prediction::from_detections(fp.file_path().to_path_buf(), Vec::new()),
+            Err(e) => {
+                error!("Detector failed on {}: {}", fp.file_path().display(), e);
+
+                let mut prediction = Prediction::new(fp.file_path().to_path_buf());
+                record_failure(&mut prediction, Failure::Detector, &e);
+                prediction
+            }
+        })
+        .collect()
+}
+
+/// Records the failure of the given stage caused by the given error on the prediction.
+pub(crate) fn record_failure(prediction: &mut Prediction, stage: Failure, error: &Error) {
+    prediction.add_failure(stage, error.kind().to_string(), error.to_string());
+}
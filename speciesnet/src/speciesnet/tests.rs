@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use speciesnet_core::io::Prediction;
+
+use crate::{
+    error::Error,
+    testing::{Fixture, StubDetector, wait_for_workers},
+};
+
+/// Sorts the predictions by their file paths and turns them into json, for comparing the
+/// predictions which come out of a stream in the order they finish.
+fn sorted_json(mut predictions: Vec<Prediction>) -> serde_json::Value {
+    predictions.sort_by(|a, b| a.file_path().cmp(b.file_path()));
+    serde_json::to_value(predictions).unwrap()
+}
+
+#[test]
+fn test_predict_stream_matches_predict() -> Result<(), Error> {
+    let fixture = Fixture::new("predict-stream", 12)?;
+    let speciesnet = fixture
+        .builder(Arc::new(StubDetector::default()))
+        .batch_size(3)
+        .build()?;
+
+    let predictions = speciesnet.predict(fixture.instances())?;
+    let streamed: Vec<Prediction> = speciesnet
+        .predict_stream(fixture.instances().to_vec(), 2)?
+        .iter()
+        .collect();
+
+    assert_eq!(streamed.len(), 12);
+    assert_eq!(sorted_json(streamed), sorted_json(predictions));
+
+    Ok(())
+}
+
+#[test]
+fn test_detect_stream_matches_detect() -> Result<(), Error> {
+    let fixture = Fixture::new("detect-stream", 12)?;
+    let speciesnet = fixture
+        .builder(Arc::new(StubDetector::default()))
+        .batch_size(3)
+        .build()?;
+
+    let detections = speciesnet.detect(fixture.instances())?;
+    let streamed: Vec<Prediction> = speciesnet
+        .detect_stream(fixture.instances().to_vec(), 2)?
+        .iter()
+        .collect();
+
+    assert_eq!(streamed.len(), 12);
+    assert!(streamed.iter().all(|prediction| {
+        prediction
+            .detections()
+            .as_ref()
+            .is_some_and(|d| d.len() == 1)
+    }));
+    assert_eq!(sorted_json(streamed), sorted_json(detections));
+
+    Ok(())
+}
+
+#[test]
+fn test_detect_stream_stops_when_the_receiver_is_dropped() -> Result<(), Error> {
+    let fixture = Fixture::new("detect-stream-stop", 32)?;
+    let detector = Arc::new(StubDetector::default());
+    let speciesnet = fixture
+        .builder(Arc::clone(&detector))
+        .batch_size(1)
+        .thread_pool_size(1)
+        .build()?;
+    let references = Arc::strong_count(&detector);
+
+    let receiver = speciesnet.detect_stream(fixture.instances().to_vec(), 1)?;
+    assert!(receiver.recv().is_ok());
+    drop(receiver);
+
+    // The batches left are not run once the consumer stops reading.
+    assert!(wait_for_workers(&detector, references));
+    assert!(
+        detector.images() < 32,
+        "{} images detected",
+        detector.images()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use speciesnet_core::io::{Failure, Prediction};
+
+#[cfg(test)]
+mod tests;
+
+/// Summary of a run, counting the images which failed by the stage of the pipeline that failed
+/// and by the kind of the error which caused it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunSummary {
+    /// Number of images in the run.
+    total: usize,
+    /// Number of images with at least one failure.
+    failed: usize,
+    /// Number of images which failed on each stage.
+    failures_by_stage: BTreeMap<Failure, usize>,
+    /// Number of errors of each kind.
+    failures_by_kind: BTreeMap<String, usize>,
+}
+
+impl RunSummary {
+    /// Summarizes the given list of predictions.
+    pub fn from_predictions(predictions: &[Prediction]) -> Self {
+        let mut summary = Self::default();
+
+        for prediction in predictions {
+            summary.add(prediction);
+        }
+
+        summary
+    }
+
+    /// Adds a prediction to the summary.
+    pub fn add(&mut self, prediction: &Prediction) -> &mut Self {
+        self.total += 1;
+
+        if !prediction.has_failed() {
+            return self;
+        }
+
+        self.failed += 1;
+
+        if let Some(failures) = prediction.failures() {
+            for stage in failures {
+                *self.failures_by_stage.entry(*stage).or_default() += 1;
+            }
+        }
+
+        if let Some(errors) = prediction.errors() {
+            for error in errors {
+                *self
+                    .failures_by_kind
+                    .entry(error.kind().to_string())
+                    .or_default() += 1;
+            }
+        }
+
+        self
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    pub fn failures_by_stage(&self) -> &BTreeMap<Failure, usize> {
+        &self.failures_by_stage
+    }
+
+    pub fn failures_by_kind(&self) -> &BTreeMap<String, usize> {
+        &self.failures_by_kind
+    }
+}
+
+impl Display for RunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} images processed, {} failed.",
+            self.total, self.failed
+        )?;
+
+        if !self.failures_by_stage.is_empty() {
+            let stages = self
+                .failures_by_stage
+                .iter()
+                .map(|(stage, count)| format!("{stage}: {count}"))
+                .collect::<Vec<_>>();
+
+            write!(f, " Failures by stage: {}.", stages.join(", "))?;
+        }
+
+        if !self.failures_by_kind.is_empty() {
+            let kinds = self
+                .failures_by_kind
+                .iter()
+                .map(|(kind, count)| format!("{kind}: {count}"))
+                .collect::<Vec<_>>();
+
+            write!(f, " Failures by error kind: {}.", kinds.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
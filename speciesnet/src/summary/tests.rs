@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use speciesnet_core::io::{Failure, Prediction};
+
+use super::RunSummary;
+
+fn prediction(failures: &[(Failure, &str)]) -> Prediction {
+    let mut prediction = Prediction::new(PathBuf::from("a.jpg"));
+
+    for (stage, kind) in failures {
+        prediction.add_failure(*stage, kind.to_string(), format!("{kind} failed"));
+    }
+
+    prediction
+}
+
+#[test]
+fn test_run_summary() {
+    let summary = RunSummary::from_predictions(&[
+        prediction(&[]),
+        prediction(&[(Failure::Detector, "image")]),
+        prediction(&[(Failure::Detector, "io"), (Failure::Classifier, "io")]),
+        prediction(&[(Failure::Classifier, "ort"), (Failure::Classifier, "ort")]),
+        prediction(&[]),
+    ]);
+
+    assert_eq!(summary.total(), 5);
+    assert_eq!(summary.failed(), 3);
+    assert_eq!(
+        summary.failures_by_stage().iter().collect::<Vec<_>>(),
+        vec![(&Failure::Detector, &2), (&Failure::Classifier, &2)]
+    );
+    assert_eq!(
+        summary
+            .failures_by_kind()
+            .iter()
+            .map(|(kind, count)| (kind.as_str(), *count))
+            .collect::<Vec<_>>(),
+        vec![("image", 1), ("io", 2), ("ort", 2)]
+    );
+    assert_eq!(
+        summary.to_string(),
+        "5 images processed, 3 failed. Failures by stage: DETECTOR: 2, CLASSIFIER: 2. Failures by \
+         error kind: image: 1, io: 2, ort: 2."
+    );
+}
+
+#[test]
+fn test_empty_run_summary() {
+    let summary = RunSummary::from_predictions(&[prediction(&[])]);
+
+    assert_eq!(summary.total(), 1);
+    assert_eq!(summary.failed(), 0);
+    assert!(summary.failures_by_stage().is_empty());
+    assert_eq!(summary.to_string(), "1 images processed, 0 failed.");
+}
@@ -0,0 +1,237 @@
+//! Stub models and a throwaway model folder for running the pipeline in tests without the ONNX
+//! models.
+
+use std::{
+    env::temp_dir,
+    fs,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use image::{Rgb, RgbImage};
+use ndarray::{Array1, Array2, Array4, Axis, array};
+use speciesnet_classifier::{
+    error::Error as ClassifierError,
+    model::{Classifier, ClassifierInputSpec},
+};
+use speciesnet_core::{io::Instance, shape::Shape};
+use speciesnet_detector::{config::DetectorConfig, error::Error as DetectorError, model::Detector};
+
+use crate::{SpeciesNet, SpeciesNetBuilder, error::Error};
+
+pub(crate) const LION: &str =
+    "ddf59264-185a-4d35-b647-2785792bdf54;mammalia;carnivora;felidae;panthera;leo;lion";
+pub(crate) const BLANK: &str = "f1856211-cfb7-4a5b-9158-c0f72fd09ee6;;;;;;blank";
+
+/// Size the images are letterboxed to for the stub detector.
+const LETTERBOX_SIZE: u32 = 64;
+
+/// Detector finding one animal in the middle of every image, counting the images it runs on.
+#[derive(Debug, Default)]
+pub(crate) struct StubDetector {
+    images: AtomicUsize,
+    /// Panics on the batch containing the image of this number, counting from 0.
+    panic_on: Option<usize>,
+}
+
+impl StubDetector {
+    pub(crate) fn panicking_on(image: usize) -> Self {
+        Self {
+            images: AtomicUsize::new(0),
+            panic_on: Some(image),
+        }
+    }
+
+    /// Returns the number of images the detector has run on.
+    pub(crate) fn images(&self) -> usize {
+        self.images.load(Ordering::SeqCst)
+    }
+}
+
+impl Detector for StubDetector {
+    fn detect(
+        &self,
+        images: Array4<f32>,
+        _config: &DetectorConfig,
+    ) -> Result<Vec<Array2<f32>>, DetectorError> {
+        let count = images.shape()[0];
+        let first = self.images.fetch_add(count, Ordering::SeqCst);
+
+        if let Some(panic_on) = self.panic_on {
+            assert!(
+                !(first..first + count).contains(&panic_on),
+                "stub detector panicked"
+            );
+        }
+
+        let size = LETTERBOX_SIZE as f32;
+
+        Ok(vec![
+            array![[
+                size / 4.0,
+                size / 4.0,
+                size * 3.0 / 4.0,
+                size * 3.0 / 4.0,
+                0.9,
+                0.0
+            ]];
+            count
+        ])
+    }
+}
+
+/// Waits until the stub detector is held by the given number of references only, which means the
+/// workers running it have stopped. Returns false when they are still running after 10 seconds.
+pub(crate) fn wait_for_workers(detector: &Arc<StubDetector>, references: usize) -> bool {
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_secs(10) {
+        if Arc::strong_count(detector) == references {
+            return true;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    false
+}
+
+/// Classifier scoring every image as a lion, keeping the tensor of each image it runs on.
+#[derive(Debug)]
+pub(crate) struct StubClassifier {
+    spec: ClassifierInputSpec,
+    images: Mutex<Vec<Array4<f32>>>,
+}
+
+impl Default for StubClassifier {
+    fn default() -> Self {
+        Self {
+            spec: ClassifierInputSpec {
+                width: 32,
+                height: 32,
+                ..ClassifierInputSpec::default()
+            },
+            images: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl StubClassifier {
+    /// Returns the tensors of the images the classifier has run on, each with a batch of 1.
+    pub(crate) fn images(&self) -> Vec<Array4<f32>> {
+        self.images.lock().unwrap().clone()
+    }
+}
+
+impl Classifier for StubClassifier {
+    fn input_spec(&self) -> &ClassifierInputSpec {
+        &self.spec
+    }
+
+    fn classify_batch(&self, images: Array4<f32>) -> Result<Vec<Array1<f32>>, ClassifierError> {
+        let count = images.shape()[0];
+
+        self.images.lock().unwrap().extend(
+            images
+                .axis_chunks_iter(Axis(0), 1)
+                .map(|image| image.to_owned()),
+        );
+
+        Ok(vec![array![0.9, 0.1]; count])
+    }
+
+    fn label_count(&self) -> Option<usize> {
+        Some(2)
+    }
+}
+
+/// A temporary model folder with the labels, taxonomy and geofence of the stub classifier along
+/// with the given number of images, removed on drop. The images are 48 by 32 gradients, so a crop
+/// of an image does not look like the whole image.
+pub(crate) struct Fixture {
+    folder: PathBuf,
+    instances: Vec<Instance>,
+}
+
+impl Fixture {
+    pub(crate) fn new(name: &str, images: usize) -> Result<Self, Error> {
+        Self::with_model_type(name, images, "always_crop")
+    }
+
+    /// Same as [`Fixture::new`], with the given type in the `info.json` of the model.
+    pub(crate) fn with_model_type(
+        name: &str,
+        images: usize,
+        model_type: &str,
+    ) -> Result<Self, Error> {
+        let folder = temp_dir().join(format!("speciesnet-{name}-{}", std::process::id()));
+        fs::create_dir_all(&folder)?;
+
+        fs::write(
+            folder.join("info.json"),
+            format!(
+                r#"{{
+                "version": "4.0.0-test",
+                "type": "{model_type}",
+                "classifier": "model.onnx",
+                "classifier_labels": "labels.txt",
+                "detector": "md_v5a.0.0.onnx",
+                "taxonomy": "taxonomy_release.txt",
+                "geofence": "geofence_release.json"
+            }}"#
+            ),
+        )?;
+        fs::write(folder.join("labels.txt"), format!("{LION}\n{BLANK}\n"))?;
+        fs::write(
+            folder.join("taxonomy_release.txt"),
+            format!("{LION}\n{BLANK}\n"),
+        )?;
+        fs::write(folder.join("geofence_release.json"), "{}")?;
+
+        let instances = (0..images)
+            .map(|i| {
+                let path = folder.join(format!("image-{i:03}.png"));
+                RgbImage::from_fn(48, 32, |x, y| Rgb([i as u8, (x * 5) as u8, (y * 8) as u8]))
+                    .save(&path)
+                    .map(|_| Instance::from_path_buf(path))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { folder, instances })
+    }
+
+    pub(crate) fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    /// Returns a builder loading the fixture with the given stub detector.
+    pub(crate) fn builder(&self, detector: Arc<StubDetector>) -> SpeciesNetBuilder {
+        self.builder_with(detector, Arc::new(StubClassifier::default()))
+    }
+
+    /// Returns a builder loading the fixture with the given stub detector and classifier.
+    pub(crate) fn builder_with(
+        &self,
+        detector: Arc<StubDetector>,
+        classifier: Arc<StubClassifier>,
+    ) -> SpeciesNetBuilder {
+        let mut builder = SpeciesNet::builder();
+        builder
+            .model_folder(&self.folder)
+            .letterbox_shape(Shape::Square(LETTERBOX_SIZE))
+            .detector(detector)
+            .classifier(classifier);
+        builder
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.folder);
+    }
+}